@@ -0,0 +1,271 @@
+//! Persistent cache of resolved address records.
+//!
+//! Addresses are stored as RVAs, keyed by the game revision and a hash of the module headers.
+//! A cache built for another game binary is discarded as a whole, and a single entry is
+//! discarded if the record it was resolved from has changed in the address file.
+//!
+//! A record is compared by its definition, see `RecordVariant::definition`.
+
+use std::{collections::HashMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+/// Bump when the cache layout changes, older caches are discarded.
+const CACHE_FORMAT_VERSION: u32 = 2;
+
+/// Number of bytes at the start of the image hashed for [`CacheKey`].
+///
+/// The first page of a mapped PE image holds its headers, including the timestamp, checksum
+/// and section table.
+const HEADER_SIZE: usize = 0x1000;
+
+/// Identifies the game binary a cache was built for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheKey {
+    pub game_revision: String,
+    pub module_hash: u64,
+}
+
+impl CacheKey {
+    /// Build a key from the game revision and the mapped module image.
+    pub fn new(game_revision: &str, image: &[u8]) -> Self {
+        let headers = &image[..image.len().min(HEADER_SIZE)];
+
+        Self {
+            game_revision: game_revision.to_string(),
+            module_hash: fnv1a64(headers),
+        }
+    }
+}
+
+/// A resolved record.
+///
+/// `definition` is the record definition the RVA was resolved from, and `alternative` the
+/// index of the pattern that matched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub definition: String,
+    pub alternative: usize,
+    pub rva: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressCache {
+    version: u32,
+    key: CacheKey,
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl AddressCache {
+    pub fn new(key: CacheKey) -> Self {
+        Self {
+            version: CACHE_FORMAT_VERSION,
+            key,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Load a cache file.
+    ///
+    /// Returns `None` if the file does not exist, cannot be parsed, or was built for another
+    /// key or format version.
+    pub fn load<P: AsRef<Path>>(path: P, key: &CacheKey) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+
+        Self::from_json(&content)
+            .ok()
+            .filter(|cache| cache.is_valid_for(key))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_json()?)?;
+
+        Ok(())
+    }
+
+    pub fn from_json(content: &str) -> Result<Self> {
+        Ok(serde_json::from_str(content)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Whether the cache was built by this format version for `key`.
+    pub fn is_valid_for(&self, key: &CacheKey) -> bool {
+        self.version == CACHE_FORMAT_VERSION && &self.key == key
+    }
+
+    /// Get the cached entry of a record, if it was resolved from the same definition.
+    pub fn get(&self, name: &str, definition: &str) -> Option<&CacheEntry> {
+        self.entries
+            .get(name)
+            .filter(|entry| entry.definition == definition)
+    }
+
+    pub fn insert(&mut self, name: &str, definition: String, alternative: usize, rva: usize) {
+        self.entries.insert(
+            name.to_string(),
+            CacheEntry {
+                definition,
+                alternative,
+                rva,
+            },
+        );
+    }
+}
+
+/// 64-bit FNV-1a hash.
+///
+/// Used instead of `DefaultHasher`, whose output may change between Rust releases.
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_image() -> Vec<u8> {
+        let mut image = vec![0u8; 0x3000];
+        image[..2].copy_from_slice(b"MZ");
+        image[0x80..0x84].copy_from_slice(b"PE\0\0");
+        image
+    }
+
+    fn sample_cache() -> AddressCache {
+        let key = CacheKey::new("421810", &sample_image());
+        let mut cache = AddressCache::new(key);
+        cache.insert("Core:MhMainCtor", "BA 00 00 08 00@-122".into(), 0, 0x1234);
+        cache.insert(
+            "Chat:MessageSent",
+            "81 08 ^ 10 00@0|81 08@2".into(),
+            1,
+            0x5678,
+        );
+        cache
+    }
+
+    #[test]
+    fn fnv1a64_known_values() {
+        assert_eq!(fnv1a64(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a64(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn key_hashes_headers_only() {
+        let image = sample_image();
+        let mut patched = image.clone();
+        patched[0x2000] = 0xCC;
+
+        assert_eq!(
+            CacheKey::new("421810", &image),
+            CacheKey::new("421810", &patched)
+        );
+
+        patched[0x88] = 0x01;
+        assert_ne!(
+            CacheKey::new("421810", &image),
+            CacheKey::new("421810", &patched)
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let cache = sample_cache();
+
+        let json = cache.to_json().unwrap();
+        let loaded = AddressCache::from_json(&json).unwrap();
+
+        assert_eq!(loaded, cache);
+        assert_eq!(
+            loaded
+                .get("Core:MhMainCtor", "BA 00 00 08 00@-122")
+                .map(|entry| entry.rva),
+            Some(0x1234)
+        );
+        assert_eq!(
+            loaded
+                .get("Chat:MessageSent", "81 08 ^ 10 00@0|81 08@2")
+                .map(|entry| entry.alternative),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn round_trip_file() {
+        let cache = sample_cache();
+        let path = std::env::temp_dir()
+            .join(format!("eigeen_loader_cache_{}", std::process::id()))
+            .join("address_cache.json");
+
+        cache.save(&path).unwrap();
+        let loaded = AddressCache::load(&path, &cache.key);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+
+        assert_eq!(loaded, Some(cache));
+    }
+
+    #[test]
+    fn invalidated_by_key() {
+        let cache = sample_cache();
+        let image = sample_image();
+
+        assert!(cache.is_valid_for(&CacheKey::new("421810", &image)));
+        assert!(!cache.is_valid_for(&CacheKey::new("421740", &image)));
+
+        let mut patched = image.clone();
+        patched[0x88] = 0x01;
+        assert!(!cache.is_valid_for(&CacheKey::new("421810", &patched)));
+    }
+
+    #[test]
+    fn invalidated_by_format_version() {
+        let cache = sample_cache();
+        let json = cache.to_json().unwrap().replacen(
+            &format!("\"version\": {}", CACHE_FORMAT_VERSION),
+            "\"version\": 0",
+            1,
+        );
+        let loaded = AddressCache::from_json(&json).unwrap();
+
+        assert!(!loaded.is_valid_for(&cache.key));
+    }
+
+    #[test]
+    fn entry_invalidated_by_record_change() {
+        let cache = sample_cache();
+
+        assert_eq!(cache.get("Core:MhMainCtor", "BA 00 00 08 00@-121"), None);
+        assert_eq!(cache.get("Core:MhMainCtor", "BA 00 00 08 01@-122"), None);
+        assert_eq!(cache.get("Core:Unknown", "BA 00 00 08 00@-122"), None);
+        assert_eq!(cache.get("Chat:MessageSent", "81 08 ^ 10 00@0"), None);
+    }
+
+    #[test]
+    fn load_missing_or_corrupt_file() {
+        let key = CacheKey::new("421810", &sample_image());
+        let path = std::env::temp_dir().join(format!(
+            "eigeen_loader_cache_corrupt_{}.json",
+            std::process::id()
+        ));
+
+        assert_eq!(AddressCache::load(&path, &key), None);
+
+        std::fs::write(&path, "{ not json").unwrap();
+        let loaded = AddressCache::load(&path, &key);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded, None);
+    }
+}
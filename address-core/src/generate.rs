@@ -0,0 +1,295 @@
+//! Pattern generation for a given RVA.
+//!
+//! Patterns are built from whole instructions, with the bytes that change between game builds
+//! or on relocation replaced by wildcards: `rel32` displacements, RIP-relative operands and
+//! immediates that look like pointers into the image.
+
+use std::{io::Cursor, str::FromStr};
+
+use crate::{
+    pattern_scan::{self, Pattern},
+    record::AddressRecord,
+    x86,
+};
+
+/// Longest generated pattern, in bytes.
+const MAX_PATTERN_LEN: usize = 64;
+
+/// How far before the target a pattern may start, in bytes.
+const MAX_BACKTRACK: usize = 48;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GenerateError {
+    #[error("RVA 0x{0:x} is out of the image")]
+    OutOfImage(usize),
+    #[error("no unique pattern found for RVA 0x{0:x}")]
+    NotUnique(usize),
+}
+
+/// A pattern matching exactly once in the image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedPattern {
+    pub pattern: String,
+    /// Offset from the pattern start to the target.
+    pub offset: isize,
+}
+
+impl GeneratedPattern {
+    /// Build a record from the pattern.
+    pub fn to_record(&self, description: Option<String>) -> AddressRecord {
+        AddressRecord {
+            pattern: self.pattern.clone(),
+            offset: self.offset,
+            description,
+            required: false,
+            strict: None,
+            steps: Vec::new(),
+            fallbacks: Vec::new(),
+            revisions: Default::default(),
+        }
+    }
+}
+
+/// Generate the shortest unique pattern for `rva` in `image`, mapped at `base`.
+///
+/// Patterns starting at the target and at instruction boundaries up to [`MAX_BACKTRACK`]
+/// bytes before it are tried, and the shortest one wins. A pattern always covers the
+/// instruction at the target. The result is verified with [`pattern_scan::scan`].
+pub fn generate_pattern(
+    image: &[u8],
+    base: usize,
+    rva: usize,
+) -> Result<GeneratedPattern, GenerateError> {
+    if rva >= image.len() {
+        return Err(GenerateError::OutOfImage(rva));
+    }
+
+    let mut best: Option<(usize, Vec<Option<u8>>)> = None;
+    for back in 0..=MAX_BACKTRACK.min(rva) {
+        let start = rva - back;
+        let Some((mut bytes, target_end)) = masked_bytes(image, base, start, rva) else {
+            continue;
+        };
+        let Some(len) = shortest_unique_len(image, start, &bytes, target_end - start) else {
+            continue;
+        };
+        bytes.truncate(len);
+        // a wildcard does not narrow the matches down
+        while bytes.last().is_some_and(Option::is_none) {
+            bytes.pop();
+        }
+        if best
+            .as_ref()
+            .is_some_and(|(_, best)| best.len() <= bytes.len())
+        {
+            continue;
+        }
+
+        if verify(image, start, &bytes) {
+            best = Some((back, bytes));
+        }
+    }
+
+    let Some((back, bytes)) = best else {
+        return Err(GenerateError::NotUnique(rva));
+    };
+
+    Ok(GeneratedPattern {
+        pattern: format_pattern(&bytes),
+        offset: back as isize,
+    })
+}
+
+/// Instruction bytes from `start`, with relocation-sensitive bytes masked out.
+///
+/// Returns the bytes and the end of the instruction at `target`, or `None` if `start` is a
+/// wildcard or decoding from `start` does not reach an instruction boundary at `target`.
+fn masked_bytes(
+    image: &[u8],
+    base: usize,
+    start: usize,
+    target: usize,
+) -> Option<(Vec<Option<u8>>, usize)> {
+    let image_range = base..base + image.len();
+    let mut bytes: Vec<Option<u8>> = Vec::new();
+    let mut pos = start;
+    let mut synced = start == target;
+    // an undecodable target is covered by its first byte
+    let mut target_end = target + 1;
+
+    while bytes.len() < MAX_PATTERN_LEN {
+        let Some(instruction) = x86::decode(&image[pos..]) else {
+            break;
+        };
+        let code = &image[pos..pos + instruction.len];
+        let mut masked = code.iter().copied().map(Some).collect::<Vec<_>>();
+
+        if let Some(operand) = instruction.relative.filter(|operand| operand.size == 4) {
+            masked[operand.offset..operand.offset + 4].fill(None);
+        }
+        if let Some(operand) = instruction.immediate {
+            let value = match operand.size {
+                4 => u32::from_le_bytes(code[operand.offset..][..4].try_into().unwrap()) as u64,
+                8 => u64::from_le_bytes(code[operand.offset..][..8].try_into().unwrap()),
+                _ => 0,
+            };
+            if image_range.contains(&(value as usize)) {
+                masked[operand.offset..operand.offset + operand.size].fill(None);
+            }
+        }
+
+        bytes.extend(masked);
+        if pos == target {
+            target_end = target + instruction.len;
+        }
+        pos += instruction.len;
+        synced |= pos == target;
+        if pos > target && !synced {
+            return None;
+        }
+    }
+
+    if !synced || bytes.first()?.is_none() || target_end - start > MAX_PATTERN_LEN {
+        return None;
+    }
+    bytes.truncate(MAX_PATTERN_LEN);
+
+    Some((bytes, target_end))
+}
+
+/// Length of the shortest prefix of `bytes`, at least `min_len` long, matching only at `start`.
+fn shortest_unique_len(
+    image: &[u8],
+    start: usize,
+    bytes: &[Option<u8>],
+    min_len: usize,
+) -> Option<usize> {
+    const INITIAL_LEN: usize = 8;
+
+    let initial_len = bytes.len().min(min_len.max(INITIAL_LEN));
+    let initial = Pattern::from_str(&format_pattern(&bytes[..initial_len])).ok()?;
+    let mut candidates = initial.find_iter(image).collect::<Vec<_>>();
+
+    if candidates.len() == 1 {
+        // a shorter prefix may already be unique
+        return (min_len..=initial_len).find(|&len| {
+            Pattern::from_str(&format_pattern(&bytes[..len]))
+                .is_ok_and(|pattern| pattern.find_iter(image).take(2).count() == 1)
+        });
+    }
+
+    for len in initial_len + 1..=bytes.len() {
+        if let Some(byte) = bytes[len - 1] {
+            candidates.retain(|&pos| image.get(pos + len - 1) == Some(&byte));
+        }
+        if candidates == [start] {
+            return Some(len);
+        }
+    }
+
+    None
+}
+
+/// Whether the pattern of `bytes` matches exactly once, at `start`.
+fn verify(image: &[u8], start: usize, bytes: &[Option<u8>]) -> bool {
+    pattern_scan::scan(Cursor::new(image), &format_pattern(bytes))
+        .is_ok_and(|matches| matches == [start])
+}
+
+fn format_pattern(bytes: &[Option<u8>]) -> String {
+    bytes
+        .iter()
+        .map(|byte| match byte {
+            Some(byte) => format!("{:02X}", byte),
+            None => "??".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: usize = 0x1_4000_0000;
+
+    /// Two copies of a function differing only in relocated operands, with a unique
+    /// instruction after the target in the second one.
+    fn sample_image() -> Vec<u8> {
+        let function: &[u8] = &[
+            0x48, 0x83, 0xEC, 0x28, // sub rsp, 0x28
+            0x48, 0x8B, 0x05, 0x10, 0x20, 0x30, 0x40, // mov rax, [rip+disp32]
+            0xE8, 0x11, 0x22, 0x33, 0x44, // call rel32
+            0x48, 0xB9, 0x00, 0x01, 0x00, 0x40, 0x01, 0x00, 0x00, 0x00, // mov rcx, imm64
+        ];
+        let mut image = vec![0xCC; 0x400];
+        image[0x100..0x100 + function.len()].copy_from_slice(function);
+        image[0x200..0x200 + function.len()].copy_from_slice(function);
+        // relocated operands of the second copy
+        image[0x207..0x20B].copy_from_slice(&[0xAA, 0xBB, 0xCC, 0xDD]);
+        image[0x20C..0x210].copy_from_slice(&[0x01, 0x02, 0x03, 0x04]);
+        image[0x212..0x21A].copy_from_slice(&(BASE + 0x300).to_le_bytes());
+        // add eax, 0x10 after the second copy
+        image[0x21A..0x21D].copy_from_slice(&[0x83, 0xC0, 0x10]);
+        image
+    }
+
+    #[test]
+    fn masks_relocated_operands() {
+        let image = sample_image();
+
+        let (bytes, target_end) = masked_bytes(&image, BASE, 0x100, 0x100).unwrap();
+        assert_eq!(target_end, 0x104);
+        assert_eq!(
+            format_pattern(&bytes[..26]),
+            "48 83 EC 28 48 8B 05 ?? ?? ?? ?? E8 ?? ?? ?? ?? 48 B9 ?? ?? ?? ?? ?? ?? ?? ??"
+        );
+        // does not reach an instruction boundary at the target
+        assert_eq!(masked_bytes(&image, BASE, 0x105, 0x107), None);
+    }
+
+    #[test]
+    fn generate_unique_pattern() {
+        let image = sample_image();
+
+        // the function start is ambiguous until the instruction after the copies differ
+        let generated = generate_pattern(&image, BASE, 0x200).unwrap();
+        assert_eq!(
+            generated.pattern,
+            "48 83 EC 28 48 8B 05 ?? ?? ?? ?? E8 ?? ?? ?? ?? 48 B9 ?? ?? ?? ?? ?? ?? ?? ?? 83"
+        );
+        assert_eq!(generated.offset, 0);
+
+        // a pattern may start before the target
+        let generated = generate_pattern(&image, BASE, 0x21A).unwrap();
+        assert!(generated.offset >= 0);
+        let pattern = Pattern::from_str(&generated.pattern).unwrap();
+        let matches = pattern.find_iter(&image).collect::<Vec<_>>();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0] as isize + generated.offset, 0x21A);
+    }
+
+    #[test]
+    fn generated_record() {
+        let image = sample_image();
+        let generated = generate_pattern(&image, BASE, 0x21A).unwrap();
+
+        let record = generated.to_record(Some("test".to_string()));
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(
+            json,
+            format!(
+                r#"{{"pattern":"{}","offset":{},"description":"test"}}"#,
+                generated.pattern, generated.offset
+            )
+        );
+    }
+
+    #[test]
+    fn reject_out_of_image() {
+        assert!(matches!(
+            generate_pattern(&sample_image(), BASE, 0x400),
+            Err(GenerateError::OutOfImage(0x400))
+        ));
+    }
+}
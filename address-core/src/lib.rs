@@ -0,0 +1,15 @@
+//! Address file format and pattern scanning.
+//!
+//! Does not depend on the game process, so address files can be checked against a dumped
+//! game binary.
+
+pub mod cache;
+pub mod error;
+pub mod generate;
+pub mod pattern_scan;
+pub mod record;
+pub mod revision;
+pub mod step;
+pub mod x86;
+
+pub use error::{Error, Result};
@@ -0,0 +1,1158 @@
+//! from [](https://github.com/lewisclark/patternscan/blob/master/src/lib.rs) v1.2.0
+//!
+//! Modified by Eigeen
+//!
+//! Two scanners are provided:
+//! - [`scan_slice`] / [`Pattern::find_iter`]: scans an in-memory slice, anchored on the rarest
+//!   exact byte of the pattern with `memchr` (SSE2/AVX2 when available). Used for memory scans.
+//! - [`PatternSet`]: scans a slice for many patterns in a single pass, using Aho-Corasick on
+//!   each pattern's literal run and verifying wildcards afterwards.
+//! - [`scan`] / [`Matches`]: the original streaming scanner over a [`Read`] source.
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::io::Read;
+use std::str::FromStr;
+
+use aho_corasick::AhoCorasick;
+
+/// Size of chunks to be read from `reader` when looking for patterns.
+///
+/// In [`Matches`] (which in turn is used in [`scan`] and [`scan_first_match`]), bytes are read
+/// from the provided [`Read`] type into a fixed-size internal buffer. The length of this buffer is
+/// given by `CHUNK_SIZE`.
+pub const CHUNK_SIZE: usize = 0x4096;
+
+/// Scan for any instances of `pattern` in the bytes read by `reader`.
+///
+/// Returns a [`Result`] containing a vector of indices of the start of each match within the
+/// bytes. If no matches are found, this vector will be empty. Returns an [`Error`] if an error was
+/// encountered while scanning, which could occur if the pattern is invalid (i.e: contains
+/// something other than 8-bit hex values and wildcards), or if the reader encounters an error.
+///
+/// A match must fit entirely in the bytes read from `reader`. Earlier versions also compared the
+/// pattern against the zero-filled buffer tail past the end of the input, and could report
+/// matches there.
+pub fn scan(reader: impl Read, pattern: &str) -> Result<Vec<usize>, Error> {
+    let matches = Matches::from_pattern_str(reader, pattern)?;
+    matches.collect()
+}
+
+/// Scan for the first instance of `pattern` in the bytes read by `reader`.
+///
+/// This function should be used instead of [`scan`] if you just want to test whether a byte string
+/// contains a given pattern, or just find the first instance, as it returns as soon as the first
+/// match is found, and will therefore be more efficient for these purposes in long strings where
+/// the pattern occurs early.
+///
+/// Returns a [`Result`] containing an [`Option`]. This Option will contain `Some(index)` if the
+/// pattern was found, where `index` is the index of the first match, and `None` if the pattern
+/// was not found. Returns an [`Error`] if an error was encountered while scanning, which could
+/// occur if the pattern is invalid (i.e: contains something other than 8-bit hex values and
+/// wildcards), or if the reader encounters an error.
+pub fn scan_first_match(reader: impl Read, pattern: &str) -> Result<Option<usize>, Error> {
+    let mut matches = Matches::from_pattern_str(reader, pattern)?;
+    matches.next().transpose()
+}
+
+/// Scan for any instances of `pattern` in `bytes`.
+///
+/// Returns the same indices as [`scan`] would for a reader over `bytes`, but works directly on
+/// the slice without copying it. Only matches that fit entirely in `bytes` are reported.
+pub fn scan_slice(bytes: &[u8], pattern: &str) -> Result<Vec<usize>, Error> {
+    let pattern = Pattern::from_str(pattern)?;
+    Ok(pattern.find_iter(bytes).collect())
+}
+
+/// Scan for the first instance of `pattern` in `bytes`.
+pub fn scan_slice_first(bytes: &[u8], pattern: &str) -> Result<Option<usize>, Error> {
+    let pattern = Pattern::from_str(pattern)?;
+    Ok(pattern.find_first(bytes))
+}
+
+/// Determine whether a byte slice matches a pattern.
+pub fn pattern_matches(bytes: &[u8], pattern: &Pattern) -> bool {
+    if bytes.len() < pattern.len() {
+        false
+    } else {
+        pattern == bytes
+    }
+}
+
+/// Represents an error which occurred while scanning for a pattern.
+#[derive(Debug)]
+pub struct Error {
+    /// String detailing the error
+    e: String,
+}
+
+impl Error {
+    pub fn new(e: String) -> Self {
+        Self { e }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "Pattern scan error: {}", self.e)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Represents a single byte in a search pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternByte {
+    Byte(u8),
+    /// Only the bits set in `mask` are compared, e.g. `4?` or `40[F0]`.
+    Masked {
+        value: u8,
+        mask: u8,
+    },
+    Any,
+}
+
+impl PatternByte {
+    /// Create a masked pattern byte, normalizing full and empty masks.
+    pub fn masked(value: u8, mask: u8) -> Self {
+        match mask {
+            0xFF => Self::Byte(value),
+            0x00 => Self::Any,
+            _ => Self::Masked {
+                value: value & mask,
+                mask,
+            },
+        }
+    }
+
+    /// Parse a two character token where each nibble is either a hex digit or `?`.
+    fn from_nibbles(s: &str) -> Option<Self> {
+        let chars = s.as_bytes();
+        if chars.len() != 2 {
+            return None;
+        }
+
+        let mut value = 0u8;
+        let mut mask = 0u8;
+        for &c in chars {
+            value <<= 4;
+            mask <<= 4;
+            if c != b'?' {
+                value |= (c as char).to_digit(16)? as u8;
+                mask |= 0xF;
+            }
+        }
+
+        Some(Self::masked(value, mask))
+    }
+
+    /// Parse a `value[mask]` token, e.g. `40[F0]`.
+    fn from_bracket_mask(s: &str) -> Result<Option<Self>, Error> {
+        let Some((value, rest)) = s.split_once('[') else {
+            return Ok(None);
+        };
+        let Some(mask) = rest.strip_suffix(']') else {
+            return Err(Error::new(format!("unclosed mask bracket: {}", s)));
+        };
+
+        let value = u8::from_str_radix(value, 16)
+            .map_err(|e| Error::new(format!("invalid masked value '{}': {}", s, e)))?;
+        let mask = u8::from_str_radix(mask, 16)
+            .map_err(|e| Error::new(format!("invalid mask '{}': {}", s, e)))?;
+
+        Ok(Some(Self::masked(value, mask)))
+    }
+}
+
+impl FromStr for PatternByte {
+    type Err = Error;
+
+    /// Create an instance of [`PatternByte`] from a string.
+    ///
+    /// Accepted forms:
+    /// - a hexadecimal byte, e.g. `48`
+    /// - a full wildcard: `?`, `??`, `*` or `**`
+    /// - a nibble wildcard, e.g. `4?` or `?B`
+    /// - a bit mask, e.g. `40[F0]` (value, then mask in brackets)
+    fn from_str(s: &str) -> Result<Self, Error> {
+        if ["?", "??", "*", "**"].contains(&s) {
+            return Ok(Self::Any);
+        }
+        if let Some(byte) = Self::from_bracket_mask(s)? {
+            return Ok(byte);
+        }
+        if s.contains('?') {
+            return Self::from_nibbles(s)
+                .ok_or_else(|| Error::new(format!("invalid nibble wildcard: {}", s)));
+        }
+
+        let n = match u8::from_str_radix(s, 16) {
+            Ok(n) => Ok(n),
+            Err(e) => Err(Error::new(format!("from_str_radix failed: {}", e))),
+        }?;
+
+        Ok(Self::Byte(n))
+    }
+}
+
+impl PartialEq<u8> for PatternByte {
+    fn eq(&self, other: &u8) -> bool {
+        match self {
+            PatternByte::Any => true,
+            PatternByte::Byte(b) => b == other,
+            PatternByte::Masked { value, mask } => other & mask == *value,
+        }
+    }
+}
+
+/// Tokens marking the position of the result address inside a pattern.
+///
+/// e.g. `48 8B 05 ^ ? ? ? ?` resolves to the address of the displacement.
+const MARKER_TOKENS: [&str; 2] = ["^", "&"];
+
+/// Represents a pattern to search for in a byte string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    bytes: Vec<PatternByte>,
+    /// Offset of the reference marker from the start of the match, if any.
+    marker: Option<usize>,
+}
+
+impl Pattern {
+    fn new(bytes: Vec<PatternByte>) -> Self {
+        Self {
+            bytes,
+            marker: None,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn bytes(&self) -> &[PatternByte] {
+        &self.bytes
+    }
+
+    /// Offset of the reference marker (`^` or `&`) from the start of a match.
+    pub fn marker(&self) -> Option<usize> {
+        self.marker
+    }
+
+    /// Offset that should be added to a match index to get the result address.
+    ///
+    /// Equals to the marker offset, or 0 if the pattern has no marker.
+    pub fn result_offset(&self) -> usize {
+        self.marker.unwrap_or(0)
+    }
+
+    /// Iterate over the start indices of all matches in `haystack`.
+    pub fn find_iter<'p, 'h>(&'p self, haystack: &'h [u8]) -> SliceMatches<'p, 'h> {
+        SliceMatches::new(self, haystack)
+    }
+
+    /// Find the start index of the first match in `haystack`.
+    pub fn find_first(&self, haystack: &[u8]) -> Option<usize> {
+        self.find_iter(haystack).next()
+    }
+
+    /// Index and value of the exact byte used to anchor slice scans.
+    ///
+    /// Picks the exact byte that is least common in x86-64 code, so the anchor search yields as
+    /// few candidates as possible. Returns `None` if the pattern has no exact byte.
+    fn anchor(&self) -> Option<(usize, u8)> {
+        self.bytes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, pb)| match pb {
+                PatternByte::Byte(b) => Some((i, *b)),
+                _ => None,
+            })
+            .max_by_key(|(i, b)| (byte_rarity(*b), std::cmp::Reverse(*i)))
+    }
+
+    /// Longest run of exact bytes in the pattern and its offset from the pattern start.
+    ///
+    /// Returns `None` if the pattern has no exact byte.
+    fn literal(&self) -> Option<(usize, Vec<u8>)> {
+        let mut best: Option<(usize, usize)> = None;
+        let mut run_start = 0;
+
+        for (i, pb) in self.bytes.iter().enumerate() {
+            if !matches!(pb, PatternByte::Byte(_)) {
+                run_start = i + 1;
+                continue;
+            }
+            let run_len = i + 1 - run_start;
+            if best.is_none_or(|(_, len)| run_len > len) {
+                best = Some((run_start, run_len));
+            }
+        }
+
+        best.map(|(start, len)| {
+            let literal = self.bytes[start..start + len]
+                .iter()
+                .map(|pb| match pb {
+                    PatternByte::Byte(b) => *b,
+                    _ => unreachable!(),
+                })
+                .collect();
+            (start, literal)
+        })
+    }
+
+    pub fn scan(self, reader: impl Read) -> Result<Vec<usize>, Error> {
+        let matches = Matches::from_pattern(reader, self)?;
+        matches.collect()
+    }
+
+    pub fn scan_first_match(self, reader: impl Read) -> Result<Option<usize>, Error> {
+        let mut matches = Matches::from_pattern(reader, self)?;
+        matches.next().transpose()
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let mut bytes = Vec::new();
+        let mut marker = None;
+
+        for segment in s.split_ascii_whitespace() {
+            if MARKER_TOKENS.contains(&segment) {
+                if marker.is_some() {
+                    return Err(Error::new(format!(
+                        "multiple reference markers in pattern: {}",
+                        s
+                    )));
+                }
+                marker = Some(bytes.len());
+                continue;
+            }
+
+            bytes.push(PatternByte::from_str(segment)?);
+        }
+
+        Ok(Self { bytes, marker })
+    }
+}
+
+impl PartialEq<[u8]> for Pattern {
+    fn eq(&self, other: &[u8]) -> bool {
+        Iterator::zip(self.bytes.iter(), other.iter()).all(|(pb, b)| pb == b)
+    }
+}
+
+/// Bytes that are most frequent in x86-64 code, most frequent first.
+///
+/// Used to rank anchor candidates: bytes not listed here are considered rare.
+const COMMON_CODE_BYTES: [u8; 48] = [
+    0x00, 0xFF, 0x48, 0x8B, 0xCC, 0x89, 0x0F, 0xE8, 0x4C, 0x24, 0x44, 0x8D, 0x01, 0x83, 0x41, 0xC0,
+    0x45, 0x85, 0xC3, 0x74, 0x10, 0x20, 0x08, 0x40, 0x49, 0x4D, 0x75, 0x90, 0x33, 0xC7, 0x05, 0x84,
+    0x28, 0x30, 0x18, 0x38, 0x80, 0x02, 0x03, 0x04, 0xF8, 0xEB, 0x50, 0x11, 0x15, 0x0D, 0xE9, 0xC4,
+];
+
+/// Rarity of a byte in x86-64 code, higher is rarer.
+fn byte_rarity(b: u8) -> usize {
+    COMMON_CODE_BYTES
+        .iter()
+        .position(|&c| c == b)
+        .unwrap_or(COMMON_CODE_BYTES.len())
+}
+
+/// Iterator over locations of matches for a pattern found within a byte slice.
+///
+/// Candidates are located with [`memchr::memchr_iter`] on the pattern's anchor byte (see
+/// [`Pattern::anchor`]) and then verified against the whole pattern. Patterns without any exact
+/// byte fall back to checking every position.
+pub struct SliceMatches<'p, 'h> {
+    pattern: &'p Pattern,
+    haystack: &'h [u8],
+    /// Anchor candidates, indexed by match start.
+    candidates: Option<memchr::Memchr<'h>>,
+    /// Next position to check when there is no anchor.
+    position: usize,
+}
+
+impl<'p, 'h> SliceMatches<'p, 'h> {
+    fn new(pattern: &'p Pattern, haystack: &'h [u8]) -> Self {
+        let len = pattern.len();
+        let candidates = pattern.anchor().map(|(offset, byte)| {
+            // the anchor of a match starting at `start` lives at `start + offset`, and a match
+            // must fit entirely in the haystack
+            let end = (haystack.len() + offset + 1).saturating_sub(len);
+            let region = &haystack[offset.min(end)..end];
+            memchr::memchr_iter(byte, region)
+        });
+
+        Self {
+            pattern,
+            haystack,
+            candidates,
+            position: 0,
+        }
+    }
+}
+
+impl Iterator for SliceMatches<'_, '_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (pattern, haystack) = (self.pattern, self.haystack);
+
+        if let Some(candidates) = self.candidates.as_mut() {
+            // the region starts at the anchor offset, so candidate indices are match starts
+            return candidates.find(|&start| pattern_matches(&haystack[start..], pattern));
+        }
+
+        while self.position + pattern.len() <= haystack.len() {
+            let start = self.position;
+            self.position += 1;
+            if pattern_matches(&haystack[start..], pattern) {
+                return Some(start);
+            }
+        }
+
+        None
+    }
+}
+
+/// A set of patterns scanned together in a single pass over a slice.
+///
+/// Each pattern contributes its longest literal run (see [`Pattern::literal`]) to an
+/// Aho-Corasick automaton. Every literal hit is then verified against the full pattern, so
+/// wildcards and masks behave exactly like in [`Pattern::find_iter`]. Patterns without any
+/// exact byte are scanned on their own.
+pub struct PatternSet {
+    patterns: Vec<Pattern>,
+    automaton: AhoCorasick,
+    /// For each automaton literal: `(pattern index, literal offset in pattern)`.
+    literal_owners: Vec<Vec<(usize, usize)>>,
+    /// Patterns that have no literal and need a separate scan.
+    unanchored: Vec<usize>,
+}
+
+impl PatternSet {
+    pub fn new(patterns: Vec<Pattern>) -> Result<Self, Error> {
+        let mut literals: Vec<Vec<u8>> = Vec::new();
+        let mut literal_ids: HashMap<Vec<u8>, usize> = HashMap::new();
+        let mut literal_owners: Vec<Vec<(usize, usize)>> = Vec::new();
+        let mut unanchored = Vec::new();
+
+        for (index, pattern) in patterns.iter().enumerate() {
+            let Some((offset, literal)) = pattern.literal() else {
+                unanchored.push(index);
+                continue;
+            };
+
+            // identical literals share one automaton entry
+            let id = *literal_ids.entry(literal.clone()).or_insert_with(|| {
+                literals.push(literal);
+                literal_owners.push(Vec::new());
+                literals.len() - 1
+            });
+            literal_owners[id].push((index, offset));
+        }
+
+        let automaton = AhoCorasick::new(&literals)
+            .map_err(|e| Error::new(format!("failed to build pattern set: {}", e)))?;
+
+        Ok(Self {
+            patterns,
+            automaton,
+            literal_owners,
+            unanchored,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn patterns(&self) -> &[Pattern] {
+        &self.patterns
+    }
+
+    /// Find all matches of every pattern in `haystack`.
+    ///
+    /// The result is indexed like the patterns passed to [`PatternSet::new`], each entry holding
+    /// the sorted match start indices of that pattern.
+    pub fn scan_all(&self, haystack: &[u8]) -> Vec<Vec<usize>> {
+        self.scan_impl(haystack, false)
+    }
+
+    /// Find the first match of every pattern in `haystack`.
+    ///
+    /// Stops as soon as every pattern has been found.
+    pub fn scan_first(&self, haystack: &[u8]) -> Vec<Option<usize>> {
+        self.scan_impl(haystack, true)
+            .into_iter()
+            .map(|matches| matches.first().copied())
+            .collect()
+    }
+
+    fn scan_impl(&self, haystack: &[u8], first_only: bool) -> Vec<Vec<usize>> {
+        let mut results = vec![Vec::new(); self.patterns.len()];
+
+        for &index in &self.unanchored {
+            let mut matches = self.patterns[index].find_iter(haystack);
+            results[index] = if first_only {
+                matches.next().into_iter().collect()
+            } else {
+                matches.collect()
+            };
+        }
+
+        let mut remaining = self.patterns.len() - self.unanchored.len();
+        if remaining == 0 {
+            return results;
+        }
+
+        // Literal hits are reported in order of their end position, so hits of one pattern
+        // arrive in increasing start order.
+        for hit in self.automaton.find_overlapping_iter(haystack) {
+            for &(index, offset) in &self.literal_owners[hit.pattern().as_usize()] {
+                if first_only && !results[index].is_empty() {
+                    continue;
+                }
+                let Some(start) = hit.start().checked_sub(offset) else {
+                    continue;
+                };
+                if !pattern_matches(&haystack[start..], &self.patterns[index]) {
+                    continue;
+                }
+
+                results[index].push(start);
+                if first_only {
+                    remaining -= 1;
+                }
+            }
+
+            if first_only && remaining == 0 {
+                break;
+            }
+        }
+
+        results
+    }
+}
+
+/// Iterator over locations of matches for a pattern found within a byte string.
+///
+/// This struct implements the actual logic for pattern matching, and is used by the [`scan`] and
+/// [`scan_first_match`] functions to locate matches. The values returned by the iterator are
+/// indices of locations of the pattern matches within the byte string produced by `reader`.
+///
+/// The byte string which the pattern should be searched against is read from `reader` in
+/// [`CHUNK_SIZE`] chunks, when required by the iterator.
+///
+/// ## Example Usage
+/// ```rust
+/// use address_core::pattern_scan;
+/// use std::io::Cursor;
+///
+/// let bytes = [0x10, 0x20, 0x30, 0x40];
+/// let reader = Cursor::new(bytes);
+/// let pattern = pattern_scan::Matches::from_pattern_str(reader, "20 30").unwrap();
+/// let match_indices: Result<Vec<usize>, _> = pattern.collect();
+/// let match_indices = match_indices.unwrap();
+/// ```
+pub struct Matches<R: Read> {
+    /// Reader from which the byte string to search will be read.
+    pub reader: R,
+    /// Pattern to search for in the byte string.
+    pub pattern: Pattern,
+
+    // Internal state, would be nice to reduce this somehow
+    bytes_buf: [u8; CHUNK_SIZE],
+    last_bytes_read: usize,
+    /// End of the bytes in `bytes_buf` that were actually read from `reader`.
+    valid_end: usize,
+    abs_position: usize,
+    rel_position: usize,
+}
+
+impl<R: Read> Matches<R> {
+    /// Create a new instance of [`Matches`] from an instance of [`Pattern`].
+    ///
+    /// `reader` should be some [`Read`] type which will produce a byte string to search. `pattern`
+    /// should be a [`Pattern`] to search for.
+    pub fn from_pattern(mut reader: R, pattern: Pattern) -> Result<Self, Error> {
+        // Constraint imposed due to the method used to detect matches over chunk boundaries. We
+        // might want to increase the chunk size to account for this?
+        if 2 * pattern.len() > CHUNK_SIZE {
+            return Err(Error::new(format!(
+                "Pattern too long: It can be at most {} bytes",
+                CHUNK_SIZE / 2
+            )));
+        }
+
+        // Perform initial read into the bytes buffer on creation
+        // Might be more idiomatic to only perform a read once we're stepping through the iterator,
+        // I'm not sure, but this ensures that the state of the struct when an instance is created
+        // is reasonable.
+        let mut bytes_buf = [0; CHUNK_SIZE];
+        let bytes_read = reader
+            .read(&mut bytes_buf)
+            .map_err(|e| Error::new(format!("Failed to read bytes: {}", e)))?;
+
+        Ok(Self {
+            reader,
+            pattern,
+            bytes_buf,
+            last_bytes_read: bytes_read,
+            valid_end: bytes_read,
+            abs_position: 0,
+            rel_position: 0,
+        })
+    }
+
+    /// Create a new instance of [`Matches`] from a string pattern.
+    pub fn from_pattern_str(reader: R, pattern: &str) -> Result<Self, Error> {
+        let pattern = Pattern::from_str(pattern)?;
+        Self::from_pattern(reader, pattern)
+    }
+}
+
+impl<R: Read> Iterator for Matches<R> {
+    type Item = Result<usize, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.rel_position == CHUNK_SIZE - self.pattern.len() {
+                // This block is what allows us to detect matches over chunk boundaries.
+                // When we're close enough to a boundary that a pattern match could overrun, we
+                // copy the final bytes in the buffer to the start of the buffer, then read into
+                // the rest of the buffer.
+                let len = self.pattern.len();
+
+                let boundary_bytes = &self.bytes_buf[CHUNK_SIZE - len..].to_owned();
+                self.bytes_buf[..len].copy_from_slice(boundary_bytes);
+
+                self.last_bytes_read = match self.reader.read(&mut self.bytes_buf[len..]) {
+                    Ok(b) => b,
+                    Err(e) => return Some(Err(Error::new(format!("Failed to read bytes: {}", e)))),
+                };
+                self.valid_end = len + self.last_bytes_read;
+
+                self.rel_position = 0;
+            }
+
+            if self.rel_position == self.last_bytes_read + self.pattern.len() {
+                break;
+            }
+
+            for i in self.rel_position..self.last_bytes_read + self.pattern.len() {
+                if i == CHUNK_SIZE - self.pattern.len() {
+                    break;
+                }
+
+                self.abs_position += 1;
+                self.rel_position += 1;
+                // never compare against the stale tail of the buffer past the end of the input
+                if pattern_matches(&self.bytes_buf[i..self.valid_end.max(i)], &self.pattern) {
+                    return Some(Ok(self.abs_position - 1));
+                }
+            }
+
+            if self.last_bytes_read == 0 {
+                break;
+            }
+        }
+
+        None
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn simple_scan_start() {
+        let bytes = [0x10, 0x20, 0x30, 0x40, 0x50];
+        let pattern = "10 20 30";
+
+        assert_eq!(scan(Cursor::new(bytes), pattern).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn simple_scan_middle() {
+        let bytes = [0x10, 0x20, 0x30, 0x40, 0x50];
+        let pattern = "20 30 40";
+
+        assert_eq!(scan(Cursor::new(bytes), pattern).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn scan_bad_exceeds() {
+        let bytes = [0x10, 0x20, 0x30, 0x40, 0x50];
+        let pattern = "40 50 60";
+
+        assert_eq!(
+            scan(Cursor::new(bytes), pattern).unwrap(),
+            Vec::<usize>::new()
+        );
+    }
+
+    #[test]
+    fn scan_ignores_bytes_past_end() {
+        let bytes = [0x10, 0x20, 0x30, 0x40];
+
+        assert_eq!(
+            scan(Cursor::new(bytes), "40 00").unwrap(),
+            Vec::<usize>::new()
+        );
+        assert_eq!(
+            scan(Cursor::new(bytes), "30 40 ??").unwrap(),
+            Vec::<usize>::new()
+        );
+        assert_eq!(scan(Cursor::new(bytes), "30 40").unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn scan_exists() {
+        let bytes = [0xff, 0xfe, 0x7c, 0x88, 0xfd, 0x90, 0x00];
+        let pattern = "fe 7c 88 fd 90 0";
+
+        assert_eq!(scan(Cursor::new(bytes), pattern).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn scan_exists_multiple_q() {
+        let bytes = [0xff, 0xfe, 0x7c, 0x88, 0xfd, 0x90, 0x00];
+        let pattern = "fe ? ? ? 90";
+
+        assert_eq!(scan(Cursor::new(bytes), pattern).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn scan_exists_multiple_q_starts() {
+        let bytes = [0xff, 0xfe, 0x7c, 0x88, 0xfd, 0x90, 0x00];
+        let pattern = "? ? ? ? fd";
+
+        assert_eq!(scan(Cursor::new(bytes), pattern).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn scan_nexists_1() {
+        let bytes = [0xff, 0xfe, 0x7c, 0x88, 0xfd, 0x90, 0x00];
+        let pattern = "78 90 cc dd fe";
+
+        assert_eq!(
+            scan(Cursor::new(bytes), pattern).unwrap(),
+            Vec::<usize>::new()
+        );
+    }
+
+    #[test]
+    fn scan_nexists_2() {
+        let bytes = [0xff, 0xfe, 0x7c, 0x88, 0xfd, 0x90, 0x00];
+        let pattern = "fe 7c 88 fd 90 1";
+
+        assert_eq!(
+            scan(Cursor::new(bytes), pattern).unwrap(),
+            Vec::<usize>::new()
+        );
+    }
+
+    #[test]
+    fn scan_pattern_larger_than_bytes() {
+        let bytes = [0xff, 0xfe, 0x7c, 0x88, 0xfd, 0x90, 0x00];
+        let pattern = "fe 7c 88 fd 90 0 1";
+
+        assert_eq!(
+            scan(Cursor::new(bytes), pattern).unwrap(),
+            Vec::<usize>::new()
+        );
+    }
+
+    #[test]
+    fn scan_multiple_instances_of_pattern() {
+        let bytes = [0x10, 0x20, 0x30, 0x10, 0x20, 0x30];
+        let pattern = "10 20 30";
+
+        assert_eq!(scan(Cursor::new(bytes), pattern).unwrap(), vec![0, 3]);
+    }
+
+    #[test]
+    fn scan_multiple_instances_q() {
+        let bytes = [0x10, 0x20, 0x30, 0x10, 0x40, 0x30];
+        let pattern = "10 ? 30";
+
+        assert_eq!(scan(Cursor::new(bytes), pattern).unwrap(), vec![0, 3]);
+    }
+
+    #[test]
+    fn scan_rejects_invalid_pattern() {
+        let bytes = [0x10, 0x20, 0x30];
+        let pattern = "10 fff 20";
+
+        assert!(scan(Cursor::new(bytes), pattern).is_err());
+    }
+
+    #[test]
+    fn scan_nibble_wildcard_high() {
+        let bytes = [0x10, 0x48, 0x8b, 0x05, 0x49, 0x8b, 0x15];
+        let pattern = "4? 8B ?5";
+
+        assert_eq!(scan(Cursor::new(bytes), pattern).unwrap(), vec![1, 4]);
+    }
+
+    #[test]
+    fn scan_nibble_wildcard_low() {
+        let bytes = [0x48, 0x8b, 0x0b, 0x48, 0x8b, 0x1c];
+        let pattern = "48 8B ?B";
+
+        assert_eq!(scan(Cursor::new(bytes), pattern).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn scan_bracket_mask() {
+        let bytes = [0x48, 0x8d, 0x0d, 0x48, 0x8d, 0x4d, 0x48, 0x8d, 0x8d];
+        // mod = 00, any reg, rm = 101
+        let pattern = "48 8D 05[C7]";
+
+        assert_eq!(scan(Cursor::new(bytes), pattern).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn parse_mask_normalizes() {
+        assert_eq!(
+            PatternByte::from_str("48[FF]").unwrap(),
+            PatternByte::Byte(0x48)
+        );
+        assert_eq!(PatternByte::from_str("48[00]").unwrap(), PatternByte::Any);
+        assert_eq!(
+            PatternByte::from_str("4?").unwrap(),
+            PatternByte::Masked {
+                value: 0x40,
+                mask: 0xF0
+            }
+        );
+    }
+
+    #[test]
+    fn parse_reference_marker() {
+        let pattern = Pattern::from_str("48 8B 05 ^ ? ? ? ? 48 85 C0").unwrap();
+        assert_eq!(pattern.marker(), Some(3));
+        assert_eq!(pattern.len(), 10);
+
+        let pattern = Pattern::from_str("& E8 ? ? ? ?").unwrap();
+        assert_eq!(pattern.marker(), Some(0));
+
+        let pattern = Pattern::from_str("E8 ? ? ? ?").unwrap();
+        assert_eq!(pattern.marker(), None);
+        assert_eq!(pattern.result_offset(), 0);
+    }
+
+    #[test]
+    fn parse_empty_pattern() {
+        let pattern = Pattern::from_str("").unwrap();
+        assert!(pattern.bytes().is_empty());
+        assert_eq!(pattern.marker(), None);
+
+        let pattern = Pattern::from_str("  ").unwrap();
+        assert!(pattern.bytes().is_empty());
+    }
+
+    #[test]
+    fn scan_ignores_reference_marker() {
+        let bytes = [0xff, 0xfe, 0x7c, 0x88, 0xfd, 0x90, 0x00];
+        let pattern = "fe 7c ^ 88 fd";
+
+        assert_eq!(scan(Cursor::new(bytes), pattern).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn scan_rejects_multiple_markers() {
+        let bytes = [0x10, 0x20, 0x30];
+        let pattern = "10 ^ 20 & 30";
+
+        assert!(scan(Cursor::new(bytes), pattern).is_err());
+    }
+
+    #[test]
+    fn scan_rejects_invalid_mask() {
+        let bytes = [0x10, 0x20, 0x30];
+
+        assert!(scan(Cursor::new(bytes), "10 2G").is_err());
+        assert!(scan(Cursor::new(bytes), "10 ?G").is_err());
+        assert!(scan(Cursor::new(bytes), "10 20[F0").is_err());
+        assert!(scan(Cursor::new(bytes), "10 20[FFF]").is_err());
+        assert!(scan(Cursor::new(bytes), "???").is_err());
+    }
+
+    #[test]
+    fn scan_first_match_simple_start() {
+        let bytes = [0x10, 0x20, 0x30, 0x40, 0x50];
+        let pattern = "10 20 30";
+
+        assert_eq!(
+            scan_first_match(Cursor::new(bytes), pattern)
+                .unwrap()
+                .unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn scan_first_match_simple_middle() {
+        let bytes = [0x10, 0x20, 0x30, 0x40, 0x50];
+        let pattern = "20 30 40";
+
+        assert_eq!(
+            scan_first_match(Cursor::new(bytes), pattern)
+                .unwrap()
+                .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn scan_first_match_no_match() {
+        let bytes = [0x10, 0x20, 0x30, 0x40, 0x50];
+        let pattern = "10 11 12";
+
+        assert!(scan_first_match(Cursor::new(bytes), pattern)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn find_across_chunk_boundary() {
+        let mut bytes = vec![0; super::CHUNK_SIZE - 2];
+        bytes.push(0xaa);
+        bytes.push(0xbb);
+        bytes.push(0xcc);
+        bytes.push(0xdd);
+        let pattern = "aa bb cc dd";
+
+        assert!(scan_first_match(Cursor::new(bytes), pattern)
+            .unwrap()
+            .is_some())
+    }
+
+    #[test]
+    fn correct_index_noninitial_chunk() {
+        let mut bytes = vec![0; super::CHUNK_SIZE];
+        bytes.push(0xaa);
+        bytes.push(0xbb);
+        bytes.push(0xcc);
+        bytes.push(0xdd);
+        let pattern = "aa bb cc dd";
+
+        assert_eq!(
+            scan_first_match(Cursor::new(bytes), pattern)
+                .unwrap()
+                .unwrap(),
+            super::CHUNK_SIZE
+        );
+    }
+
+    /// Small xorshift generator, enough for reproducible synthetic buffers.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    /// Synthetic code-like buffer: mostly common code bytes with some noise.
+    fn synthetic_buffer(rng: &mut XorShift, len: usize, alphabet: usize) -> Vec<u8> {
+        (0..len)
+            .map(|_| {
+                if rng.below(8) == 0 {
+                    rng.next() as u8
+                } else {
+                    COMMON_CODE_BYTES[rng.below(alphabet)]
+                }
+            })
+            .collect()
+    }
+
+    /// Take a pattern from `bytes` at `start` and wildcard some of its bytes.
+    fn pattern_from(rng: &mut XorShift, bytes: &[u8], start: usize, len: usize) -> String {
+        bytes[start..start + len]
+            .iter()
+            .map(|b| match rng.below(10) {
+                0 => "??".to_string(),
+                1 => format!("{:X}?", b >> 4),
+                2 => format!("{:02X}[3C]", b),
+                _ => format!("{:02X}", b),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn slice_scan_matches_reader_scan() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+
+        for round in 0..200 {
+            let len = 1 + rng.below(3 * CHUNK_SIZE);
+            let bytes = synthetic_buffer(&mut rng, len, 1 + round % 8);
+            let pattern_len = 1 + rng.below(12.min(len));
+            let start = rng.below(len - pattern_len + 1);
+            let pattern = pattern_from(&mut rng, &bytes, start, pattern_len);
+
+            // Only matches that fit in `bytes` are compared, as earlier versions of the reader
+            // scanner also reported matches in the zero-filled tail of its buffer.
+            let expected = scan(Cursor::new(&bytes), &pattern)
+                .unwrap()
+                .into_iter()
+                .filter(|&i| i + pattern_len <= len)
+                .collect::<Vec<_>>();
+            let actual = scan_slice(&bytes, &pattern).unwrap();
+
+            assert_eq!(actual, expected, "pattern: {}", pattern);
+            assert!(actual.contains(&start));
+            assert_eq!(
+                scan_slice_first(&bytes, &pattern).unwrap(),
+                expected.first().copied()
+            );
+        }
+    }
+
+    #[test]
+    fn slice_scan_without_exact_bytes() {
+        let bytes = [0x10, 0x48, 0x8b, 0x05, 0x49, 0x8b, 0x15];
+
+        assert_eq!(scan_slice(&bytes, "4? ?B").unwrap(), vec![1, 4]);
+        assert_eq!(scan_slice(&bytes, "?? ??").unwrap(), vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn slice_scan_bounds() {
+        let bytes = [0x10, 0x20, 0x30, 0x40, 0x50];
+
+        assert_eq!(scan_slice(&bytes, "40 50").unwrap(), vec![3]);
+        assert_eq!(scan_slice(&bytes, "40 50 00").unwrap(), Vec::<usize>::new());
+        assert_eq!(
+            scan_slice(&bytes, "10 20 30 40 50 60").unwrap(),
+            Vec::<usize>::new()
+        );
+        assert_eq!(scan_slice(&[], "10").unwrap(), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn anchor_prefers_rare_bytes() {
+        let pattern = Pattern::from_str("48 8B 05 ? ? ? ? 3B 48").unwrap();
+        assert_eq!(pattern.anchor(), Some((7, 0x3B)));
+
+        let pattern = Pattern::from_str("? 4?").unwrap();
+        assert_eq!(pattern.anchor(), None);
+    }
+
+    #[test]
+    fn pattern_literal_is_longest_exact_run() {
+        let pattern = Pattern::from_str("48 ? 8B 05 4? 3B 48 85 C0 ??").unwrap();
+        assert_eq!(pattern.literal(), Some((5, vec![0x3B, 0x48, 0x85, 0xC0])));
+
+        let pattern = Pattern::from_str("? 4? ??").unwrap();
+        assert_eq!(pattern.literal(), None);
+    }
+
+    #[test]
+    fn pattern_set_matches_single_scans() {
+        let mut rng = XorShift(0x1234_5678_9abc_def1);
+
+        for round in 0..50 {
+            let len = 1 + rng.below(2 * CHUNK_SIZE);
+            let bytes = synthetic_buffer(&mut rng, len, 1 + round % 8);
+
+            let patterns = (0..1 + rng.below(16))
+                .map(|_| {
+                    let pattern_len = 1 + rng.below(10.min(len));
+                    let start = rng.below(len - pattern_len + 1);
+                    Pattern::from_str(&pattern_from(&mut rng, &bytes, start, pattern_len)).unwrap()
+                })
+                .collect::<Vec<_>>();
+            let set = PatternSet::new(patterns.clone()).unwrap();
+
+            let all = set.scan_all(&bytes);
+            let first = set.scan_first(&bytes);
+            for (i, pattern) in patterns.iter().enumerate() {
+                let expected = pattern.find_iter(&bytes).collect::<Vec<_>>();
+                assert_eq!(all[i], expected, "pattern: {:?}", pattern);
+                assert_eq!(first[i], expected.first().copied());
+            }
+        }
+    }
+
+    #[test]
+    fn pattern_set_shared_literals_and_unanchored() {
+        let bytes = [0x10, 0x48, 0x8b, 0x05, 0x49, 0x8b, 0x15, 0x48, 0x8b, 0x0d];
+        let patterns = ["48 8B 05", "? 48 8B", "48 8B ?D", "4? 8B ?5", "AA BB"]
+            .iter()
+            .map(|p| Pattern::from_str(p).unwrap())
+            .collect::<Vec<_>>();
+        let set = PatternSet::new(patterns).unwrap();
+
+        assert_eq!(
+            set.scan_all(&bytes),
+            vec![vec![1], vec![0, 6], vec![7], vec![1, 4], vec![]]
+        );
+        assert_eq!(
+            set.scan_first(&bytes),
+            vec![Some(1), Some(0), Some(7), Some(1), None]
+        );
+    }
+
+    /// Compare both scanners over a large synthetic image.
+    ///
+    /// Run with `cargo test --release -- --ignored --nocapture bench_scan_large_buffer`.
+    #[test]
+    #[ignore]
+    fn bench_scan_large_buffer() {
+        const SIZE: usize = 384 * 1024 * 1024;
+
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        let mut bytes = synthetic_buffer(&mut rng, SIZE, COMMON_CODE_BYTES.len());
+        let pattern = "48 83 EC 48 48 8B 05 ? ? ? ? 4C 8D 0D ? ? ? ? BA 0A 00 00 00";
+        let needle = space_hex_to_bytes(pattern);
+        bytes[SIZE - 0x1000..SIZE - 0x1000 + needle.len()].copy_from_slice(&needle);
+
+        let now = std::time::Instant::now();
+        let expected = scan(Cursor::new(&bytes), pattern).unwrap();
+        let reader_elapsed = now.elapsed();
+
+        let now = std::time::Instant::now();
+        let actual = scan_slice(&bytes, pattern).unwrap();
+        let slice_elapsed = now.elapsed();
+
+        eprintln!(
+            "{} MiB: reader scan {:?}, slice scan {:?}",
+            SIZE / 1024 / 1024,
+            reader_elapsed,
+            slice_elapsed
+        );
+        assert_eq!(actual, expected);
+        assert_eq!(actual, vec![SIZE - 0x1000]);
+    }
+
+    fn space_hex_to_bytes(pattern: &str) -> Vec<u8> {
+        pattern
+            .split_ascii_whitespace()
+            .map(|s| u8::from_str_radix(s, 16).unwrap_or(0))
+            .collect()
+    }
+}
@@ -0,0 +1,797 @@
+//! Address file format.
+//!
+//! ```json
+//! {
+//!     "schema_version": 3,
+//!     "records": {
+//!         "Core:MhMainCtor": {
+//!             "pattern": "BA 00 00 08 00 48 8B CF E8 ?? ?? ?? ??",
+//!             "offset": -122,
+//!             "description": "mhMain constructor",
+//!             "required": true,
+//!             "strict": true,
+//!             "fallbacks": [
+//!                 { "pattern": "48 8B CF E8 ?? ?? ?? ?? 84 C0", "offset": 3, "steps": [{ "op": "call" }] }
+//!             ],
+//!             "revisions": {
+//!                 "410013": { "offset": -118 },
+//!                 "421810": { "rva": 20022576 }
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Files without `schema_version` are version 1 and only contain `pattern` and `offset`.
+//! Resolve `steps` (see [`crate::step`]) were added in version 3.
+//!
+//! A strict record must resolve to a single address. `strict` may also be set at the top level
+//! of the file, for all records that do not set it themselves.
+
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::pattern_scan::{Pattern, PatternSet};
+use crate::step::{resolve_steps, MemoryReader, ResolveError, ResolveStep};
+
+/// Latest supported address file schema version.
+pub const SCHEMA_VERSION: u32 = 3;
+
+fn legacy_schema_version() -> u32 {
+    1
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressFile {
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32,
+    /// Require every record to match exactly once, unless the record sets `strict` itself.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub strict: bool,
+    pub records: HashMap<String, AddressRecord>,
+}
+
+impl AddressFile {
+    /// Parse an address file, rejecting schema versions newer than [`SCHEMA_VERSION`].
+    pub fn from_json(content: &str) -> Result<Self> {
+        let address_file: AddressFile = serde_json::from_str(content)?;
+        if address_file.schema_version > SCHEMA_VERSION {
+            return Err(Error::UnsupportedSchema(address_file.schema_version));
+        }
+
+        Ok(address_file)
+    }
+
+    /// Whether `record` must match exactly once.
+    ///
+    /// The record flag takes precedence over the file-wide `strict` setting.
+    pub fn is_strict(&self, record: &AddressRecord) -> bool {
+        record.strict.unwrap_or(self.strict)
+    }
+
+    /// Resolve the named records against `image`, mapped at `base`, in a single pass.
+    ///
+    /// `revision` selects the per-revision overrides of each record. All alternatives of a
+    /// record are scanned together, and the first one in order that matches and whose resolve
+    /// steps succeed wins. Steps read memory through `reader`.
+    ///
+    /// In strict mode (see [`AddressFile::is_strict`]), the winning alternative must resolve to
+    /// a single address, otherwise the record is reported as ambiguous. Records with invalid
+    /// patterns or without a match are reported as missing.
+    pub fn resolve_records<'a>(
+        &'a self,
+        names: impl IntoIterator<Item = &'a String>,
+        image: &[u8],
+        base: usize,
+        revision: Option<&str>,
+        reader: &impl MemoryReader,
+    ) -> ResolveReport {
+        let mut report = ResolveReport::default();
+
+        // alternatives of a record are pushed together and in order
+        let mut scanned_names = Vec::new();
+        let mut owners = Vec::new();
+        let mut patterns = Vec::new();
+        let mut any_strict = false;
+        for name in names {
+            let Some(record) = self.records.get(name) else {
+                continue;
+            };
+            let variant = record.variant(revision);
+
+            // fixed RVA for this revision, no scan needed
+            if let Some(rva) = variant.rva {
+                report.resolved.insert(
+                    name.clone(),
+                    ResolvedRecord {
+                        addr: base + rva,
+                        alternative: 0,
+                    },
+                );
+                continue;
+            }
+
+            let strict = self.is_strict(record);
+            any_strict |= strict;
+            scanned_names.push(name);
+            for (index, alternative) in variant.alternatives().enumerate() {
+                match Pattern::from_str(alternative.pattern) {
+                    Ok(pattern) => {
+                        owners.push((name, index, alternative, strict));
+                        patterns.push(pattern);
+                    }
+                    Err(e) => error!("Invalid pattern #{} of {}: {}", index, name, e),
+                }
+            }
+        }
+
+        let pattern_set = match PatternSet::new(patterns) {
+            Ok(set) => set,
+            Err(e) => {
+                error!("Pattern scan failed: {}", e);
+                return report;
+            }
+        };
+
+        // every match is needed to tell whether a strict record is ambiguous
+        let matches = if any_strict {
+            pattern_set.scan_all(image)
+        } else {
+            pattern_set
+                .scan_first(image)
+                .into_iter()
+                .map(|start| start.into_iter().collect())
+                .collect()
+        };
+        for (((name, index, alternative, strict), pattern), starts) in
+            owners.into_iter().zip(pattern_set.patterns()).zip(matches)
+        {
+            if report.resolved.contains_key(name) || report.ambiguous.contains_key(name) {
+                continue;
+            }
+            let starts = if strict {
+                &starts[..]
+            } else {
+                &starts[..starts.len().min(1)]
+            };
+
+            let mut addrs = Vec::new();
+            for &start in starts {
+                let Some(rva) = alternative.resolve(start, pattern) else {
+                    warn!("Offset of {} points before the image start", name);
+                    continue;
+                };
+                match alternative.apply_steps(reader, base + rva) {
+                    Ok(addr) if !addrs.contains(&addr) => addrs.push(addr),
+                    Ok(_) => {}
+                    Err(e) => warn!("Resolve steps of {} pattern #{} failed: {}", name, index, e),
+                }
+            }
+
+            match addrs[..] {
+                [] => continue,
+                [addr] => {
+                    if index > 0 {
+                        debug!("{} matched fallback pattern #{}", name, index);
+                    }
+                    report.resolved.insert(
+                        name.clone(),
+                        ResolvedRecord {
+                            addr,
+                            alternative: index,
+                        },
+                    );
+                }
+                _ => {
+                    error!(
+                        "Pattern #{} of strict record {} matched {} addresses",
+                        index,
+                        name,
+                        addrs.len()
+                    );
+                    report.ambiguous.insert(name.clone(), addrs);
+                }
+            }
+        }
+
+        for name in scanned_names {
+            if report.resolved.contains_key(name) || report.ambiguous.contains_key(name) {
+                continue;
+            }
+            let required = self.records[name].required;
+            if required {
+                error!("Pattern of required record {} not found", name);
+            } else {
+                warn!("Pattern of {} not found", name);
+            }
+            report.missing.push(MissingRecord {
+                name: name.clone(),
+                required,
+            });
+        }
+
+        report
+    }
+}
+
+/// Outcome of [`AddressFile::resolve_records`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolveReport {
+    pub resolved: HashMap<String, ResolvedRecord>,
+    /// Strict records that resolved to more than one address, with those addresses.
+    pub ambiguous: HashMap<String, Vec<usize>>,
+    /// Records without a match, or with invalid patterns only.
+    pub missing: Vec<MissingRecord>,
+}
+
+impl ResolveReport {
+    /// Whether every record was resolved.
+    pub fn is_clean(&self) -> bool {
+        self.ambiguous.is_empty() && self.missing.is_empty()
+    }
+}
+
+impl Display for ResolveReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} resolved, {} ambiguous, {} missing",
+            self.resolved.len(),
+            self.ambiguous.len(),
+            self.missing.len()
+        )?;
+
+        let mut ambiguous = self.ambiguous.iter().collect::<Vec<_>>();
+        ambiguous.sort();
+        for (name, addrs) in ambiguous {
+            let addrs = addrs
+                .iter()
+                .map(|addr| format!("0x{:x}", addr))
+                .collect::<Vec<_>>()
+                .join(", ");
+            write!(f, "\n  ambiguous: {} ({})", name, addrs)?;
+        }
+
+        let mut missing = self.missing.iter().collect::<Vec<_>>();
+        missing.sort_by(|a, b| a.name.cmp(&b.name));
+        for record in missing {
+            if record.required {
+                write!(f, "\n  missing: {} (required)", record.name)?;
+            } else {
+                write!(f, "\n  missing: {}", record.name)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A record reported missing by [`AddressFile::resolve_records`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingRecord {
+    pub name: String,
+    pub required: bool,
+}
+
+/// Result of resolving an [`AddressRecord`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResolvedRecord {
+    pub addr: usize,
+    /// Index of the matched alternative, 0 for the primary pattern (or a fixed RVA).
+    pub alternative: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressRecord {
+    /// Pattern string, may contain a reference marker (`^` or `&`).
+    pub pattern: String,
+    /// Offset applied after the reference marker (or match start if no marker).
+    #[serde(default)]
+    pub offset: isize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Whether the loader or common plugins cannot work without this record.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub required: bool,
+    /// Whether the record must match exactly once, overrides the file-wide setting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+    /// Steps applied after `offset`, e.g. to follow a call or a RIP-relative operand.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<ResolveStep>,
+    /// Patterns tried in order when `pattern` does not match.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallbacks: Vec<PatternAlternative>,
+    /// Overrides keyed by game revision, e.g. "421810".
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub revisions: HashMap<String, RevisionOverride>,
+}
+
+impl AddressRecord {
+    /// Get the record definition for a game revision.
+    ///
+    /// Fields not set by the revision override fall back to the base record.
+    pub fn variant(&self, revision: Option<&str>) -> RecordVariant<'_> {
+        let base = RecordVariant {
+            pattern: &self.pattern,
+            offset: self.offset,
+            steps: &self.steps,
+            fallbacks: &self.fallbacks,
+            rva: None,
+        };
+
+        let Some(rev_override) = revision.and_then(|rev| self.revisions.get(rev)) else {
+            return base;
+        };
+
+        RecordVariant {
+            pattern: rev_override.pattern.as_deref().unwrap_or(base.pattern),
+            offset: rev_override.offset.unwrap_or(base.offset),
+            steps: rev_override.steps.as_deref().unwrap_or(base.steps),
+            fallbacks: base.fallbacks,
+            rva: rev_override.rva,
+        }
+    }
+}
+
+/// A fallback pattern of an [`AddressRecord`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatternAlternative {
+    pub pattern: String,
+    #[serde(default)]
+    pub offset: isize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<ResolveStep>,
+}
+
+/// Per-revision override of an [`AddressRecord`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RevisionOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<isize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steps: Option<Vec<ResolveStep>>,
+    /// Fixed RVA, skips pattern scanning and resolve steps entirely.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rva: Option<usize>,
+}
+
+/// An [`AddressRecord`] with the overrides of a game revision applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordVariant<'a> {
+    pub pattern: &'a str,
+    pub offset: isize,
+    pub steps: &'a [ResolveStep],
+    pub fallbacks: &'a [PatternAlternative],
+    pub rva: Option<usize>,
+}
+
+impl<'a> RecordVariant<'a> {
+    /// Patterns to try in order: the primary pattern, then the fallbacks.
+    pub fn alternatives(&self) -> impl Iterator<Item = Alternative<'a>> {
+        let primary = Alternative {
+            pattern: self.pattern,
+            offset: self.offset,
+            steps: self.steps,
+        };
+        let fallbacks = self.fallbacks.iter().map(|fallback| Alternative {
+            pattern: &fallback.pattern,
+            offset: fallback.offset,
+            steps: &fallback.steps,
+        });
+
+        std::iter::once(primary).chain(fallbacks)
+    }
+
+    /// Canonical text of all alternatives, used to tell whether a record has changed.
+    pub fn definition(&self) -> String {
+        self.alternatives()
+            .map(|alternative| alternative.definition())
+            .collect::<Vec<_>>()
+            .join("|")
+    }
+}
+
+/// A single pattern and offset of a [`RecordVariant`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alternative<'a> {
+    pub pattern: &'a str,
+    pub offset: isize,
+    pub steps: &'a [ResolveStep],
+}
+
+impl Alternative<'_> {
+    /// RVA of the record given the start of a pattern match, before resolve steps.
+    pub fn resolve(&self, match_start: usize, pattern: &Pattern) -> Option<usize> {
+        let marker = (match_start + pattern.result_offset()) as isize;
+        usize::try_from(marker + self.offset).ok()
+    }
+
+    /// Apply the resolve steps to the address of a match.
+    pub fn apply_steps(
+        &self,
+        reader: &impl MemoryReader,
+        addr: usize,
+    ) -> std::result::Result<usize, ResolveError> {
+        resolve_steps(reader, addr, self.steps)
+    }
+
+    /// Whether the resolved address only depends on the module image, and can be cached.
+    pub fn is_static(&self) -> bool {
+        self.steps.iter().all(ResolveStep::is_static)
+    }
+
+    fn definition(&self) -> String {
+        if self.steps.is_empty() {
+            return format!("{}@{}", self.pattern, self.offset);
+        }
+
+        let steps = serde_json::to_string(self.steps).unwrap_or_default();
+        format!("{}@{}{}", self.pattern, self.offset, steps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::step::ImageReader;
+
+    const LEGACY_JSON: &str = r#"{
+        "records": {
+            "Test:Start": { "pattern": "48 83 EC 48 48 8B 05", "offset": -2 }
+        }
+    }"#;
+
+    fn synthetic_image() -> Vec<u8> {
+        let mut image = vec![0xCC; 0x1000];
+        image[0x100..0x107].copy_from_slice(&[0x48, 0x83, 0xEC, 0x48, 0x48, 0x8B, 0x05]);
+        image[0x800..0x808].copy_from_slice(&[0xE8, 0x10, 0x20, 0x30, 0x40, 0x48, 0x8B, 0xD8]);
+        image
+    }
+
+    /// Resolve all records of `address_file` against `image`, mapped at 0.
+    fn resolve_all(
+        address_file: &AddressFile,
+        image: &[u8],
+        revision: Option<&str>,
+    ) -> ResolveReport {
+        let reader = ImageReader::new(0, image);
+        address_file.resolve_records(address_file.records.keys(), image, 0, revision, &reader)
+    }
+
+    #[test]
+    fn parse_legacy_file() {
+        let address_file = AddressFile::from_json(LEGACY_JSON).unwrap();
+
+        assert_eq!(address_file.schema_version, 1);
+        let record = &address_file.records["Test:Start"];
+        assert_eq!(record.offset, -2);
+        assert!(!record.required);
+        assert!(record.description.is_none());
+        assert!(record.revisions.is_empty());
+    }
+
+    #[test]
+    fn reject_newer_schema() {
+        let json = format!(
+            r#"{{ "schema_version": {}, "records": {{}} }}"#,
+            SCHEMA_VERSION + 1
+        );
+
+        assert!(matches!(
+            AddressFile::from_json(&json),
+            Err(Error::UnsupportedSchema(_))
+        ));
+    }
+
+    #[test]
+    fn select_revision_variant() {
+        let address_file = AddressFile::from_json(
+            r#"{
+                "schema_version": 2,
+                "records": {
+                    "Test:Start": {
+                        "pattern": "48 83 EC 48",
+                        "offset": -2,
+                        "description": "test record",
+                        "required": true,
+                        "revisions": {
+                            "410013": { "offset": 4 },
+                            "421470": { "pattern": "48 8B 05", "offset": 0 },
+                            "421810": { "rva": 4660 }
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let record = &address_file.records["Test:Start"];
+
+        assert_eq!(
+            record.variant(None),
+            RecordVariant {
+                pattern: "48 83 EC 48",
+                offset: -2,
+                steps: &[],
+                fallbacks: &[],
+                rva: None
+            }
+        );
+        assert_eq!(record.variant(Some("400000")), record.variant(None));
+        assert_eq!(
+            record.variant(Some("410013")),
+            RecordVariant {
+                pattern: "48 83 EC 48",
+                offset: 4,
+                steps: &[],
+                fallbacks: &[],
+                rva: None
+            }
+        );
+        assert_eq!(record.variant(Some("421470")).pattern, "48 8B 05");
+        assert_eq!(record.variant(Some("421810")).rva, Some(0x1234));
+    }
+
+    #[test]
+    fn resolve_records_synthetic_image() {
+        let address_file = AddressFile::from_json(
+            r#"{
+                "records": {
+                    "Test:Start": { "pattern": "48 83 EC 48 48 8B 05", "offset": -2 },
+                    "Test:Marker": { "pattern": "E8 ^ ? ? ? ? 4? 8B D8", "offset": 4 },
+                    "Test:Missing": { "pattern": "AA BB CC DD" },
+                    "Test:Invalid": { "pattern": "AA BB CCC" }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let resolved = resolve_all(&address_file, &synthetic_image(), None).resolved;
+
+        assert_eq!(resolved.len(), 2);
+        assert_eq!(resolved["Test:Start"].addr, 0xFE);
+        assert_eq!(resolved["Test:Marker"].addr, 0x805);
+    }
+
+    #[test]
+    fn resolve_records_with_revision() {
+        let address_file = AddressFile::from_json(
+            r#"{
+                "schema_version": 2,
+                "records": {
+                    "Test:Start": {
+                        "pattern": "AA BB CC DD",
+                        "revisions": {
+                            "421810": { "pattern": "48 83 EC 48 48 8B 05", "offset": -2 }
+                        }
+                    },
+                    "Test:Fixed": {
+                        "pattern": "AA BB CC DD",
+                        "revisions": { "421810": { "rva": 4660 } }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let image = synthetic_image();
+
+        let resolved = resolve_all(&address_file, &image, None).resolved;
+        assert!(resolved.is_empty());
+
+        let resolved = resolve_all(&address_file, &image, Some("421810")).resolved;
+        assert_eq!(resolved["Test:Start"].addr, 0xFE);
+        assert_eq!(resolved["Test:Fixed"].addr, 0x1234);
+    }
+
+    #[test]
+    fn resolve_records_with_fallbacks() {
+        let address_file = AddressFile::from_json(
+            r#"{
+                "schema_version": 2,
+                "records": {
+                    "Test:Primary": {
+                        "pattern": "48 83 EC 48",
+                        "fallbacks": [{ "pattern": "E8 ? ? ? ? 48 8B D8", "offset": 1 }]
+                    },
+                    "Test:Fallback": {
+                        "pattern": "AA BB CC DD",
+                        "fallbacks": [
+                            { "pattern": "BB CC DD EE" },
+                            { "pattern": "E8 ? ? ? ? 48 8B D8", "offset": 1 },
+                            { "pattern": "48 83 EC 48" }
+                        ]
+                    },
+                    "Test:Missing": {
+                        "pattern": "AA BB CC DD",
+                        "fallbacks": [{ "pattern": "BB CC DD EE" }]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let resolved = resolve_all(&address_file, &synthetic_image(), None).resolved;
+
+        assert_eq!(resolved.len(), 2);
+        assert_eq!(
+            resolved["Test:Primary"],
+            ResolvedRecord {
+                addr: 0x100,
+                alternative: 0
+            }
+        );
+        assert_eq!(
+            resolved["Test:Fallback"],
+            ResolvedRecord {
+                addr: 0x801,
+                alternative: 2
+            }
+        );
+    }
+
+    #[test]
+    fn variant_definition_covers_fallbacks() {
+        let address_file = AddressFile::from_json(
+            r#"{
+                "schema_version": 2,
+                "records": {
+                    "Test:Record": {
+                        "pattern": "AA BB",
+                        "offset": -3,
+                        "fallbacks": [{ "pattern": "CC DD", "offset": 2 }]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let record = &address_file.records["Test:Record"];
+
+        assert_eq!(record.variant(None).definition(), "AA BB@-3|CC DD@2");
+        assert_eq!(record.variant(None).alternatives().count(), 2);
+    }
+
+    #[test]
+    fn resolve_records_with_steps() {
+        let address_file = AddressFile::from_json(
+            r#"{
+                "schema_version": 3,
+                "records": {
+                    "Test:Call": {
+                        "pattern": "E8 ? ? ? ? 48 8B D8",
+                        "steps": [{ "op": "call" }]
+                    },
+                    "Test:Overflow": {
+                        "pattern": "48 83 EC 48",
+                        "steps": [{ "op": "add", "value": 4 }, { "op": "rip", "disp_offset": 3, "length": 7 }],
+                        "fallbacks": [{ "pattern": "48 83 EC 48", "steps": [{ "op": "add", "value": 4 }] }]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let mut image = synthetic_image();
+        image[0x801..0x805].copy_from_slice(&(0x200 - 0x805i32).to_le_bytes());
+
+        let resolved = resolve_all(&address_file, &image, None).resolved;
+
+        assert_eq!(resolved["Test:Call"].addr, 0x200);
+        // the displacement points before address 0, the fallback is used instead
+        assert_eq!(
+            resolved["Test:Overflow"],
+            ResolvedRecord {
+                addr: 0x104,
+                alternative: 1
+            }
+        );
+    }
+
+    #[test]
+    fn variant_definition_covers_steps() {
+        let address_file = AddressFile::from_json(
+            r#"{
+                "schema_version": 3,
+                "records": {
+                    "Test:Record": {
+                        "pattern": "AA BB",
+                        "steps": [{ "op": "call" }],
+                        "revisions": { "421810": { "steps": [{ "op": "deref" }] } }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let record = &address_file.records["Test:Record"];
+
+        let variant = record.variant(None);
+        assert_ne!(variant.definition(), "AA BB@0");
+        assert!(variant
+            .alternatives()
+            .all(|alternative| alternative.is_static()));
+
+        let variant = record.variant(Some("421810"));
+        assert_ne!(variant.definition(), record.variant(None).definition());
+        assert!(!variant
+            .alternatives()
+            .all(|alternative| alternative.is_static()));
+    }
+
+    #[test]
+    fn resolve_records_strict() {
+        let address_file = AddressFile::from_json(
+            r#"{
+                "schema_version": 3,
+                "records": {
+                    "Test:Loose": { "pattern": "CC CC CC CC" },
+                    "Test:Ambiguous": { "pattern": "CC CC CC CC", "strict": true },
+                    "Test:Unique": { "pattern": "48 83 EC 48", "strict": true },
+                    "Test:SameTarget": {
+                        "pattern": "E8 ? ? ? ? 90",
+                        "strict": true,
+                        "steps": [{ "op": "call" }]
+                    },
+                    "Test:Missing": { "pattern": "AA BB CC DD", "strict": true, "required": true }
+                }
+            }"#,
+        )
+        .unwrap();
+        let mut image = synthetic_image();
+        // two calls to the same function
+        for start in [0x300, 0x310] {
+            image[start] = 0xE8;
+            image[start + 1..start + 5].copy_from_slice(&(0x200 - start as i32 - 5).to_le_bytes());
+            image[start + 5] = 0x90;
+        }
+
+        let report = resolve_all(&address_file, &image, None);
+
+        assert!(!report.is_clean());
+        assert_eq!(report.resolved["Test:Loose"].addr, 0);
+        assert_eq!(report.resolved["Test:Unique"].addr, 0x100);
+        assert_eq!(report.resolved["Test:SameTarget"].addr, 0x200);
+        assert!(!report.resolved.contains_key("Test:Ambiguous"));
+        assert!(report.ambiguous["Test:Ambiguous"].len() > 1);
+        assert_eq!(
+            report.missing,
+            vec![MissingRecord {
+                name: "Test:Missing".to_string(),
+                required: true
+            }]
+        );
+
+        let text = report.to_string();
+        assert!(text.starts_with("3 resolved, 1 ambiguous, 1 missing"));
+        assert!(text.contains("ambiguous: Test:Ambiguous (0x0, 0x1, "));
+        assert!(text.contains("missing: Test:Missing (required)"));
+    }
+
+    #[test]
+    fn file_wide_strict() {
+        let address_file = AddressFile::from_json(
+            r#"{
+                "schema_version": 3,
+                "strict": true,
+                "records": {
+                    "Test:Ambiguous": { "pattern": "CC CC CC CC" },
+                    "Test:OptOut": { "pattern": "CC CC CC CC", "strict": false }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(address_file.is_strict(&address_file.records["Test:Ambiguous"]));
+        assert!(!address_file.is_strict(&address_file.records["Test:OptOut"]));
+
+        let report = resolve_all(&address_file, &synthetic_image(), None);
+        assert!(report.ambiguous.contains_key("Test:Ambiguous"));
+        assert_eq!(report.resolved["Test:OptOut"].addr, 0);
+    }
+}
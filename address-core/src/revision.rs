@@ -0,0 +1,83 @@
+//! Game revision lookup, from the `Core:GameRevision` record.
+
+use crate::step::{resolve_steps, MemoryReader, ResolveError, ResolveStep};
+
+/// From the start of `Core:GameRevision` to the revision string pointer:
+/// `mov rax, [rip + disp32]` at +4, then read the pointer.
+pub const GAME_REVISION_STEPS: [ResolveStep; 3] = [
+    ResolveStep::Add { value: 4 },
+    ResolveStep::Rip {
+        disp_offset: 3,
+        length: 7,
+    },
+    ResolveStep::Deref,
+];
+
+/// Revision strings are short, e.g. "421810".
+const MAX_REVISION_LEN: usize = 32;
+
+/// Read the game revision string, given the address of `Core:GameRevision`.
+pub fn read_game_revision(
+    reader: &impl MemoryReader,
+    func_addr: usize,
+) -> Result<String, ResolveError> {
+    // validate if it is MOV op
+    let mov_addr = func_addr + 4;
+    let mut op = [0; 2];
+    reader.read_bytes(mov_addr, &mut op)?;
+    if op != [0x48, 0x8B] {
+        return Err(ResolveError::UnexpectedInstruction(mov_addr));
+    }
+
+    let str_addr = resolve_steps(reader, func_addr, &GAME_REVISION_STEPS)?;
+
+    let mut bytes = Vec::new();
+    for addr in str_addr..str_addr + MAX_REVISION_LEN {
+        let mut byte = [0];
+        reader.read_bytes(addr, &mut byte)?;
+        if byte[0] == 0 {
+            break;
+        }
+        bytes.push(byte[0]);
+    }
+
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::step::ImageReader;
+
+    const BASE: usize = 0x1_4000_0000;
+
+    /// `Core:GameRevision` at 0x100, the string pointer at 0x200 and the string at 0x300.
+    fn sample_image() -> Vec<u8> {
+        let mut image = vec![0xCC; 0x400];
+        image[0x100..0x107].copy_from_slice(&[0x48, 0x83, 0xEC, 0x48, 0x48, 0x8B, 0x05]);
+        image[0x107..0x10B].copy_from_slice(&(0x200 - 0x10Bi32).to_le_bytes());
+        image[0x200..0x208].copy_from_slice(&(BASE + 0x300).to_le_bytes());
+        image[0x300..0x307].copy_from_slice(b"421810\0");
+        image
+    }
+
+    #[test]
+    fn read_revision() {
+        let image = sample_image();
+        let reader = ImageReader::new(BASE, &image);
+
+        assert_eq!(read_game_revision(&reader, BASE + 0x100).unwrap(), "421810");
+    }
+
+    #[test]
+    fn reject_unexpected_instruction() {
+        let mut image = sample_image();
+        image[0x105] = 0x8D;
+        let reader = ImageReader::new(BASE, &image);
+
+        assert!(matches!(
+            read_game_revision(&reader, BASE + 0x100),
+            Err(ResolveError::UnexpectedInstruction(_))
+        ));
+    }
+}
@@ -0,0 +1,253 @@
+//! Resolve steps applied to an address after its pattern is matched.
+//!
+//! In the address file, steps are written as a list of operations:
+//!
+//! ```json
+//! "steps": [
+//!     { "op": "add", "value": 4 },
+//!     { "op": "rip", "disp_offset": 3, "length": 7 },
+//!     { "op": "deref" }
+//! ]
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ResolveError {
+    #[error("failed to read memory at 0x{0:x}")]
+    Read(usize),
+    #[error("null pointer dereferenced at 0x{0:x}")]
+    NullPointer(usize),
+    #[error("address overflow at 0x{0:x}")]
+    Overflow(usize),
+    #[error("unexpected instruction at 0x{0:x}")]
+    UnexpectedInstruction(usize),
+}
+
+/// A single resolve step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ResolveStep {
+    /// Add a constant offset.
+    Add { value: isize },
+    /// Follow a `call rel32` or `jmp rel32` (`E8`/`E9`) at the current address.
+    Call,
+    /// Follow a RIP-relative operand of the instruction at the current address.
+    ///
+    /// The 32-bit displacement is read at `disp_offset`, and is relative to the end of the
+    /// instruction, `length` bytes from its start.
+    Rip { disp_offset: usize, length: usize },
+    /// Read a pointer at the current address.
+    Deref,
+}
+
+impl ResolveStep {
+    /// Whether the result of the step only depends on the module image.
+    ///
+    /// Dereferenced pointers may change on each launch, and should not be cached.
+    pub fn is_static(&self) -> bool {
+        !matches!(self, ResolveStep::Deref)
+    }
+
+    /// Apply the step to `addr`.
+    pub fn apply(&self, reader: &impl MemoryReader, addr: usize) -> Result<usize, ResolveError> {
+        match *self {
+            ResolveStep::Add { value } => addr
+                .checked_add_signed(value)
+                .ok_or(ResolveError::Overflow(addr)),
+            ResolveStep::Call => ResolveStep::Rip {
+                disp_offset: 1,
+                length: 5,
+            }
+            .apply(reader, addr),
+            ResolveStep::Rip {
+                disp_offset,
+                length,
+            } => {
+                let disp_addr = addr
+                    .checked_add(disp_offset)
+                    .ok_or(ResolveError::Overflow(addr))?;
+                let disp = reader.read_i32(disp_addr)?;
+
+                addr.checked_add(length)
+                    .and_then(|end| end.checked_add_signed(disp as isize))
+                    .ok_or(ResolveError::Overflow(addr))
+            }
+            ResolveStep::Deref => {
+                let ptr = reader.read_usize(addr)?;
+                if ptr == 0 {
+                    return Err(ResolveError::NullPointer(addr));
+                }
+
+                Ok(ptr)
+            }
+        }
+    }
+}
+
+/// Apply `steps` to `addr` in order.
+pub fn resolve_steps(
+    reader: &impl MemoryReader,
+    addr: usize,
+    steps: &[ResolveStep],
+) -> Result<usize, ResolveError> {
+    steps
+        .iter()
+        .try_fold(addr, |addr, step| step.apply(reader, addr))
+}
+
+/// Read access to memory, used by resolve steps.
+pub trait MemoryReader {
+    /// Read `buf.len()` bytes at `addr`.
+    fn read_bytes(&self, addr: usize, buf: &mut [u8]) -> Result<(), ResolveError>;
+
+    fn read_i32(&self, addr: usize) -> Result<i32, ResolveError> {
+        let mut buf = [0; 4];
+        self.read_bytes(addr, &mut buf)?;
+
+        Ok(i32::from_le_bytes(buf))
+    }
+
+    fn read_usize(&self, addr: usize) -> Result<usize, ResolveError> {
+        let mut buf = [0; std::mem::size_of::<usize>()];
+        self.read_bytes(addr, &mut buf)?;
+
+        Ok(usize::from_le_bytes(buf))
+    }
+}
+
+/// Reads a module image held in a byte buffer, as if it was mapped at `base`.
+pub struct ImageReader<'a> {
+    base: usize,
+    bytes: &'a [u8],
+}
+
+impl<'a> ImageReader<'a> {
+    pub fn new(base: usize, bytes: &'a [u8]) -> Self {
+        Self { base, bytes }
+    }
+}
+
+impl MemoryReader for ImageReader<'_> {
+    fn read_bytes(&self, addr: usize, buf: &mut [u8]) -> Result<(), ResolveError> {
+        let bytes = addr
+            .checked_sub(self.base)
+            .and_then(|start| self.bytes.get(start..start.checked_add(buf.len())?))
+            .ok_or(ResolveError::Read(addr))?;
+        buf.copy_from_slice(bytes);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: usize = 0x1_4000_0000;
+
+    /// Image with:
+    /// - at 0x10: `call 0x100`
+    /// - at 0x20: `mov rax, [rip + 0x1d9]` (target 0x200)
+    /// - at 0x200: pointer to 0x300
+    fn sample_image() -> Vec<u8> {
+        let mut image = vec![0xCC; 0x400];
+        image[0x10] = 0xE8;
+        image[0x11..0x15].copy_from_slice(&(0x100 - 0x15i32).to_le_bytes());
+        image[0x20..0x23].copy_from_slice(&[0x48, 0x8B, 0x05]);
+        image[0x23..0x27].copy_from_slice(&(0x200 - 0x27i32).to_le_bytes());
+        image[0x200..0x208].copy_from_slice(&(BASE + 0x300).to_le_bytes());
+        image
+    }
+
+    #[test]
+    fn follow_call() {
+        let image = sample_image();
+        let reader = ImageReader::new(BASE, &image);
+
+        let addr = resolve_steps(&reader, BASE + 0x10, &[ResolveStep::Call]).unwrap();
+        assert_eq!(addr, BASE + 0x100);
+    }
+
+    #[test]
+    fn follow_rip_relative_and_deref() {
+        let image = sample_image();
+        let reader = ImageReader::new(BASE, &image);
+        let steps = [
+            ResolveStep::Add { value: 0x10 },
+            ResolveStep::Rip {
+                disp_offset: 3,
+                length: 7,
+            },
+            ResolveStep::Deref,
+            ResolveStep::Add { value: -0x10 },
+        ];
+
+        let addr = resolve_steps(&reader, BASE + 0x10, &steps).unwrap();
+        assert_eq!(addr, BASE + 0x2F0);
+    }
+
+    #[test]
+    fn negative_displacement() {
+        let mut image = sample_image();
+        image[0x300] = 0xE9;
+        image[0x301..0x305].copy_from_slice(&(0x10 - 0x305i32).to_le_bytes());
+        let reader = ImageReader::new(BASE, &image);
+
+        let addr = resolve_steps(&reader, BASE + 0x300, &[ResolveStep::Call]).unwrap();
+        assert_eq!(addr, BASE + 0x10);
+    }
+
+    #[test]
+    fn read_out_of_image() {
+        let image = sample_image();
+        let reader = ImageReader::new(BASE, &image);
+
+        assert!(matches!(
+            resolve_steps(&reader, BASE + 0x3FE, &[ResolveStep::Deref]),
+            Err(ResolveError::Read(_))
+        ));
+        assert!(matches!(
+            resolve_steps(&reader, BASE - 2, &[ResolveStep::Call]),
+            Err(ResolveError::Read(_))
+        ));
+    }
+
+    #[test]
+    fn deref_null_pointer() {
+        let image = vec![0; 0x10];
+        let reader = ImageReader::new(BASE, &image);
+
+        assert!(matches!(
+            resolve_steps(&reader, BASE, &[ResolveStep::Deref]),
+            Err(ResolveError::NullPointer(_))
+        ));
+    }
+
+    #[test]
+    fn parse_steps() {
+        let steps: Vec<ResolveStep> = serde_json::from_str(
+            r#"[
+                { "op": "add", "value": -4 },
+                { "op": "call" },
+                { "op": "rip", "disp_offset": 3, "length": 7 },
+                { "op": "deref" }
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            steps,
+            vec![
+                ResolveStep::Add { value: -4 },
+                ResolveStep::Call,
+                ResolveStep::Rip {
+                    disp_offset: 3,
+                    length: 7
+                },
+                ResolveStep::Deref,
+            ]
+        );
+        assert!(!steps.iter().all(ResolveStep::is_static));
+    }
+}
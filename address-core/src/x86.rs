@@ -0,0 +1,415 @@
+//! Minimal x86-64 instruction length decoder.
+//!
+//! Only decodes what pattern generation needs: the instruction length and where its
+//! relative and immediate operands are. Covers the legacy, `0F`, `0F 38`, `0F 3A`, VEX and
+//! EVEX opcode maps; unknown or invalid encodings are rejected.
+
+/// Location of an operand within an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operand {
+    pub offset: usize,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub len: usize,
+    /// Branch displacement (`rel8`/`rel32`) or RIP-relative `disp32`.
+    pub relative: Option<Operand>,
+    /// Immediate, or the absolute address of `mov` with `moffs`.
+    pub immediate: Option<Operand>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Map {
+    Legacy,
+    Escape0F,
+    Escape0F38,
+    Escape0F3A,
+}
+
+#[derive(Debug, Default)]
+struct Prefixes {
+    operand_size: bool,
+    address_size: bool,
+    rex_w: bool,
+}
+
+/// Decode the instruction at the start of `code`.
+///
+/// Returns `None` for invalid or unsupported encodings, or if `code` ends mid-instruction.
+pub fn decode(code: &[u8]) -> Option<Instruction> {
+    let mut decoder = Decoder { code, pos: 0 };
+    let mut prefixes = Prefixes::default();
+
+    // legacy prefixes
+    loop {
+        match decoder.peek()? {
+            0x66 => prefixes.operand_size = true,
+            0x67 => prefixes.address_size = true,
+            0xF0 | 0xF2 | 0xF3 | 0x2E | 0x36 | 0x3E | 0x26 | 0x64 | 0x65 => {}
+            _ => break,
+        }
+        decoder.pos += 1;
+        if decoder.pos > 14 {
+            return None;
+        }
+    }
+    // REX prefix, must directly precede the opcode
+    if let 0x40..=0x4F = decoder.peek()? {
+        prefixes.rex_w = decoder.next()? & 0x08 != 0;
+    }
+
+    let opcode = decoder.next()?;
+    let (map, opcode) = match opcode {
+        0x0F => match decoder.next()? {
+            0x38 => (Map::Escape0F38, decoder.next()?),
+            0x3A => (Map::Escape0F3A, decoder.next()?),
+            opcode => (Map::Escape0F, opcode),
+        },
+        // VEX, always in 64-bit mode
+        0xC4 => {
+            let p0 = decoder.next()?;
+            prefixes.rex_w = decoder.next()? & 0x80 != 0;
+            (vex_map(p0 & 0x1F)?, decoder.next()?)
+        }
+        0xC5 => {
+            decoder.next()?;
+            (Map::Escape0F, decoder.next()?)
+        }
+        // EVEX
+        0x62 => {
+            let p0 = decoder.next()?;
+            prefixes.rex_w = decoder.next()? & 0x80 != 0;
+            decoder.next()?;
+            (vex_map(p0 & 0x07)?, decoder.next()?)
+        }
+        opcode => (Map::Legacy, opcode),
+    };
+
+    let layout = match map {
+        Map::Legacy => legacy_layout(opcode, &prefixes)?,
+        Map::Escape0F => map_0f_layout(opcode)?,
+        Map::Escape0F38 => Layout::modrm(),
+        Map::Escape0F3A => Layout::modrm().imm(1),
+    };
+
+    let mut relative = None;
+    let mut group_reg = 0;
+    if layout.modrm {
+        let modrm = decoder.next()?;
+        let (mode, reg, rm) = (modrm >> 6, (modrm >> 3) & 7, modrm & 7);
+        group_reg = reg;
+        if mode != 3 {
+            let mut base_is_disp32 = false;
+            if rm == 4 {
+                let sib = decoder.next()?;
+                base_is_disp32 = mode == 0 && sib & 7 == 5;
+            }
+            match mode {
+                0 if rm == 5 => {
+                    relative = Some(Operand {
+                        offset: decoder.pos,
+                        size: 4,
+                    });
+                    decoder.skip(4)?;
+                }
+                0 if base_is_disp32 => decoder.skip(4)?,
+                0 => {}
+                1 => decoder.skip(1)?,
+                _ => decoder.skip(4)?,
+            }
+        }
+    }
+
+    // group 3 `test` is the only form of F6/F7 with an immediate
+    let imm_size = match (map, opcode) {
+        (Map::Legacy, 0xF6) if group_reg < 2 => 1,
+        (Map::Legacy, 0xF7) if group_reg < 2 => z_size(&prefixes),
+        _ => layout.imm,
+    };
+    let immediate = (imm_size > 0).then_some(Operand {
+        offset: decoder.pos,
+        size: imm_size,
+    });
+    decoder.skip(imm_size)?;
+
+    if layout.rel > 0 {
+        relative = Some(Operand {
+            offset: decoder.pos,
+            size: layout.rel,
+        });
+        decoder.skip(layout.rel)?;
+    }
+
+    Some(Instruction {
+        len: decoder.pos,
+        relative,
+        immediate,
+    })
+}
+
+struct Decoder<'a> {
+    code: &'a [u8],
+    pos: usize,
+}
+
+impl Decoder<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.code.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        if self.pos + len > self.code.len() {
+            return None;
+        }
+        self.pos += len;
+        Some(())
+    }
+}
+
+/// Operands following the opcode.
+#[derive(Debug, Clone, Copy, Default)]
+struct Layout {
+    modrm: bool,
+    imm: usize,
+    rel: usize,
+}
+
+impl Layout {
+    fn none() -> Self {
+        Self::default()
+    }
+
+    fn modrm() -> Self {
+        Self {
+            modrm: true,
+            ..Self::default()
+        }
+    }
+
+    fn imm(self, imm: usize) -> Self {
+        Self { imm, ..self }
+    }
+
+    fn rel(self, rel: usize) -> Self {
+        Self { rel, ..self }
+    }
+}
+
+fn vex_map(map: u8) -> Option<Map> {
+    match map {
+        1 => Some(Map::Escape0F),
+        2 => Some(Map::Escape0F38),
+        3 => Some(Map::Escape0F3A),
+        _ => None,
+    }
+}
+
+/// Size of a 16/32-bit immediate.
+fn z_size(prefixes: &Prefixes) -> usize {
+    if prefixes.operand_size && !prefixes.rex_w {
+        2
+    } else {
+        4
+    }
+}
+
+fn legacy_layout(opcode: u8, prefixes: &Prefixes) -> Option<Layout> {
+    let z = z_size(prefixes);
+
+    let layout = match opcode {
+        // invalid in 64-bit mode
+        0x06 | 0x07 | 0x0E | 0x16 | 0x17 | 0x1E | 0x1F | 0x27 | 0x2F | 0x37 | 0x3F | 0x60
+        | 0x61 | 0x82 | 0x9A | 0xD4 | 0xD5 | 0xD6 | 0xEA => return None,
+
+        // ALU: r/m forms, then AL/eAX with an immediate
+        0x00..=0x3F => match opcode & 7 {
+            0..=3 => Layout::modrm(),
+            4 => Layout::none().imm(1),
+            5 => Layout::none().imm(z),
+            _ => return None,
+        },
+
+        0x50..=0x5F => Layout::none(),
+        0x63 => Layout::modrm(),
+        0x68 => Layout::none().imm(z),
+        0x69 => Layout::modrm().imm(z),
+        0x6A => Layout::none().imm(1),
+        0x6B => Layout::modrm().imm(1),
+        0x6C..=0x6F => Layout::none(),
+        0x70..=0x7F => Layout::none().rel(1),
+        0x80 | 0x83 => Layout::modrm().imm(1),
+        0x81 => Layout::modrm().imm(z),
+        0x84..=0x8F => Layout::modrm(),
+        0x90..=0x9F => Layout::none(),
+        0xA0..=0xA3 => Layout::none().imm(if prefixes.address_size { 4 } else { 8 }),
+        0xA4..=0xA7 | 0xAA..=0xAF => Layout::none(),
+        0xA8 => Layout::none().imm(1),
+        0xA9 => Layout::none().imm(z),
+        0xB0..=0xB7 => Layout::none().imm(1),
+        0xB8..=0xBF if prefixes.rex_w => Layout::none().imm(8),
+        0xB8..=0xBF => Layout::none().imm(z),
+        0xC0 | 0xC1 => Layout::modrm().imm(1),
+        0xC2 | 0xCA => Layout::none().imm(2),
+        0xC3 | 0xC9 | 0xCB | 0xCC | 0xCE | 0xCF => Layout::none(),
+        0xC6 => Layout::modrm().imm(1),
+        0xC7 => Layout::modrm().imm(z),
+        0xC8 => Layout::none().imm(3),
+        0xCD => Layout::none().imm(1),
+        0xD0..=0xD3 | 0xD8..=0xDF => Layout::modrm(),
+        0xD7 => Layout::none(),
+        0xE0..=0xE3 | 0xEB => Layout::none().rel(1),
+        0xE4..=0xE7 => Layout::none().imm(1),
+        0xE8 | 0xE9 => Layout::none().rel(4),
+        0xEC..=0xEF | 0xF1 | 0xF4 | 0xF5 | 0xF8..=0xFD => Layout::none(),
+        // immediate of group 3 depends on ModRM.reg
+        0xF6 | 0xF7 | 0xFE | 0xFF => Layout::modrm(),
+        _ => return None,
+    };
+
+    Some(layout)
+}
+
+fn map_0f_layout(opcode: u8) -> Option<Layout> {
+    let layout = match opcode {
+        0x04
+        | 0x0A
+        | 0x0C
+        | 0x0E
+        | 0x0F
+        | 0x24..=0x27
+        | 0x36
+        | 0x39
+        | 0x3B..=0x3F
+        | 0x7A
+        | 0x7B
+        | 0xA6
+        | 0xA7
+        | 0xB9 => return None,
+        0x05..=0x09 | 0x0B | 0x30..=0x35 | 0x37 | 0x77 | 0xA0..=0xA2 | 0xA8..=0xAA => {
+            Layout::none()
+        }
+        0xC8..=0xCF => Layout::none(),
+        0x80..=0x8F => Layout::none().rel(4),
+        0x70..=0x73 | 0xA4 | 0xAC | 0xBA | 0xC2 | 0xC4..=0xC6 => Layout::modrm().imm(1),
+        _ => Layout::modrm(),
+    };
+
+    Some(layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_hex(hex: &str) -> Option<Instruction> {
+        let code = hex
+            .split_whitespace()
+            .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+            .collect::<Vec<_>>();
+        let instruction = decode(&code)?;
+        assert_eq!(instruction.len, code.len(), "{}", hex);
+        Some(instruction)
+    }
+
+    fn operand(offset: usize, size: usize) -> Option<Operand> {
+        Some(Operand { offset, size })
+    }
+
+    #[test]
+    fn decode_common_instructions() {
+        // sub rsp, 0x48
+        let i = decode_hex("48 83 EC 48").unwrap();
+        assert_eq!(i.immediate, operand(3, 1));
+        // mov [rsp+8], rbx
+        let i = decode_hex("48 89 5C 24 08").unwrap();
+        assert_eq!((i.relative, i.immediate), (None, None));
+        // mov dword [rsp+0x20], 1
+        let i = decode_hex("C7 44 24 20 01 00 00 00").unwrap();
+        assert_eq!(i.immediate, operand(4, 4));
+        // mov ax, 0x1234
+        let i = decode_hex("66 B8 34 12").unwrap();
+        assert_eq!(i.immediate, operand(2, 2));
+        // mov rax, imm64
+        let i = decode_hex("48 B8 00 00 00 40 01 00 00 00").unwrap();
+        assert_eq!(i.immediate, operand(2, 8));
+        // test cl, 1 / neg eax / call r8 / ret
+        assert_eq!(decode_hex("F6 C1 01").unwrap().immediate, operand(2, 1));
+        assert_eq!(decode_hex("F7 D8").unwrap().immediate, None);
+        decode_hex("41 FF D0").unwrap();
+        decode_hex("C3").unwrap();
+        // nop word [rax+rax]
+        decode_hex("66 0F 1F 44 00 00").unwrap();
+        // mov rax, [rax+rcx*8+0x12345678]
+        decode_hex("48 8B 84 C8 78 56 34 12").unwrap();
+        // mov eax, [0x12345678] via SIB without base
+        decode_hex("8B 04 25 78 56 34 12").unwrap();
+    }
+
+    #[test]
+    fn decode_relative_operands() {
+        // call rel32
+        assert_eq!(
+            decode_hex("E8 10 20 30 40").unwrap().relative,
+            operand(1, 4)
+        );
+        // jz rel32
+        assert_eq!(
+            decode_hex("0F 84 10 20 30 40").unwrap().relative,
+            operand(2, 4)
+        );
+        // jnz rel8
+        assert_eq!(decode_hex("75 F0").unwrap().relative, operand(1, 1));
+        // mov rax, [rip+disp32]
+        assert_eq!(
+            decode_hex("48 8B 05 10 20 30 40").unwrap().relative,
+            operand(3, 4)
+        );
+        // lea r9, [rip+disp32]
+        assert_eq!(
+            decode_hex("4C 8D 0D 10 20 30 40").unwrap().relative,
+            operand(3, 4)
+        );
+        // movss xmm0, [rip+disp32]
+        assert_eq!(
+            decode_hex("F3 0F 10 05 10 20 30 40").unwrap().relative,
+            operand(4, 4)
+        );
+        // cmp byte [rip+disp32], 0
+        let i = decode_hex("80 3D 10 20 30 40 00").unwrap();
+        assert_eq!((i.relative, i.immediate), (operand(2, 4), operand(6, 1)));
+    }
+
+    #[test]
+    fn decode_vex_and_evex() {
+        // vzeroupper
+        decode_hex("C5 F8 77").unwrap();
+        // vmovss xmm0, [rip+disp32]
+        assert_eq!(
+            decode_hex("C5 FA 10 05 10 20 30 40").unwrap().relative,
+            operand(4, 4)
+        );
+        // vpermq ymm0, ymm1, 0x4E
+        assert_eq!(
+            decode_hex("C4 E3 FD 00 C1 4E").unwrap().immediate,
+            operand(5, 1)
+        );
+        // vmovups zmm0, [rax]
+        decode_hex("62 F1 7C 48 10 00").unwrap();
+    }
+
+    #[test]
+    fn reject_invalid_or_truncated() {
+        assert_eq!(decode(&[0x06]), None);
+        assert_eq!(decode(&[0xE8, 0x00, 0x00]), None);
+        assert_eq!(decode(&[0x48]), None);
+        assert_eq!(decode(&[0x0F, 0x04]), None);
+    }
+}
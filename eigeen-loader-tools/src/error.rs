@@ -0,0 +1,16 @@
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Address file error: {0}")]
+    Address(#[from] address_core::Error),
+    #[error("Pattern generation error: {0}")]
+    Generate(#[from] address_core::generate::GenerateError),
+
+    #[error("Invalid image: {0}")]
+    InvalidImage(String),
+    #[error("{0}")]
+    Usage(String),
+}
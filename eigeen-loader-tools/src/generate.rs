@@ -0,0 +1,109 @@
+//! `generate`: generate a unique pattern for an RVA.
+
+use std::path::PathBuf;
+
+use address_core::generate::{generate_pattern, GeneratedPattern};
+
+use crate::{error::Result, image::Image, parse_hex, Options};
+
+pub const USAGE: &str = "\
+generate <image> <rva> [options]
+
+Generate the shortest unique pattern for a hex RVA and print it as an address record.
+
+Options:
+    --raw               <image> is a memory dump of the mapped module, not a PE file
+    --base <hex>        image base of a memory dump
+    --name <name>       record name, prints the record as a JSON entry
+    --description <s>   record description";
+
+struct Args {
+    image: PathBuf,
+    rva: usize,
+    raw: bool,
+    base: Option<usize>,
+    name: Option<String>,
+    description: Option<String>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut options = Options::parse(args, &["--raw"], &["--base", "--name", "--description"])?;
+        let [image, rva] = options.positional::<2>()?;
+        let base = options
+            .value("--base")
+            .map(|base| parse_hex("base", base))
+            .transpose()?;
+
+        Ok(Self {
+            image: image.into(),
+            rva: parse_hex("RVA", &rva)?,
+            raw: options.switch("--raw"),
+            base,
+            name: options.value("--name").map(str::to_string),
+            description: options.value("--description").map(str::to_string),
+        })
+    }
+}
+
+/// Run the command, prints the generated record.
+pub fn run(args: &[String]) -> Result<bool> {
+    let args = Args::parse(args)?;
+
+    let image = Image::open(&args.image, args.raw, args.base)?;
+    let generated = generate_pattern(&image.bytes, image.base, args.rva)?;
+    println!(
+        "{}",
+        format_record(&generated, args.name.as_deref(), args.description)
+    );
+
+    Ok(true)
+}
+
+/// Format the record as JSON, as an object entry if `name` is given.
+fn format_record(
+    generated: &GeneratedPattern,
+    name: Option<&str>,
+    description: Option<String>,
+) -> String {
+    let record = generated.to_record(description);
+    // serializing a record does not fail
+    let json = serde_json::to_string_pretty(&record).unwrap();
+
+    match name {
+        Some(name) => format!("{}: {}", serde_json::Value::from(name), json),
+        None => json,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::tests::sample_pe;
+
+    #[test]
+    fn generate_record() {
+        let mut text = vec![0xCC; 0x100];
+        // call rel32 in two functions
+        text[0x10..0x19].copy_from_slice(&[0x48, 0x83, 0xEC, 0x28, 0xE8, 0x01, 0x02, 0x03, 0x04]);
+        text[0x30..0x36].copy_from_slice(&[0xE8, 0x05, 0x06, 0x07, 0x08, 0xC3]);
+        let image = Image::from_pe_file(&sample_pe(&text)).unwrap();
+
+        let generated = generate_pattern(&image.bytes, image.base, 0x1014).unwrap();
+        let matches = address_core::pattern_scan::scan(
+            std::io::Cursor::new(&image.bytes),
+            &generated.pattern,
+        )
+        .unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0] as isize + generated.offset, 0x1014);
+
+        assert_eq!(
+            format_record(&generated, Some("Test:Call"), None),
+            format!(
+                "\"Test:Call\": {{\n  \"pattern\": \"{}\",\n  \"offset\": {}\n}}",
+                generated.pattern, generated.offset
+            )
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! Game images loaded from disk, laid out as they are mapped in the game process.
+
+use std::path::Path;
+
+use address_core::step::ImageReader;
+
+use crate::error::{Error, Result};
+
+const PE32_MAGIC: u16 = 0x10B;
+const PE32_PLUS_MAGIC: u16 = 0x20B;
+
+/// A module image, laid out by RVA.
+pub struct Image {
+    pub base: usize,
+    pub bytes: Vec<u8>,
+}
+
+impl Image {
+    /// Load an image from disk, a PE file or a memory dump if `raw` is set.
+    pub fn open(path: &Path, raw: bool, base: Option<usize>) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        if raw {
+            Ok(Self::from_memory_dump(data, base))
+        } else {
+            Self::from_pe_file(&data)
+        }
+    }
+
+    /// Load a PE file (e.g. `MonsterHunterWorld.exe`), mapping each section to its RVA.
+    ///
+    /// The image is based at the preferred image base from the PE headers.
+    pub fn from_pe_file(data: &[u8]) -> Result<Self> {
+        let headers = PeHeaders::parse(data)?;
+
+        let mut bytes = vec![0; headers.size_of_image];
+        let header_len = headers.size_of_headers.min(data.len()).min(bytes.len());
+        bytes[..header_len].copy_from_slice(&data[..header_len]);
+
+        for section in headers.sections.iter() {
+            // raw data is padded to the file alignment, past the virtual size
+            let raw_len = match section.virtual_size {
+                0 => section.raw_size,
+                virtual_size => section.raw_size.min(virtual_size),
+            };
+            let src = data
+                .get(section.raw_offset..section.raw_offset + raw_len)
+                .ok_or_else(|| invalid("section data out of file"))?;
+            let dst = bytes
+                .get_mut(section.rva..section.rva + raw_len)
+                .ok_or_else(|| invalid("section out of image"))?;
+            dst.copy_from_slice(src);
+        }
+
+        Ok(Self {
+            base: headers.image_base,
+            bytes,
+        })
+    }
+
+    /// Load a dump of the mapped module.
+    ///
+    /// Without `base`, the preferred image base is read from the PE headers if the dump
+    /// starts with them, or 0 otherwise.
+    pub fn from_memory_dump(data: Vec<u8>, base: Option<usize>) -> Self {
+        let base = base
+            .or_else(|| PeHeaders::parse(&data).ok().map(|h| h.image_base))
+            .unwrap_or(0);
+
+        Self { base, bytes: data }
+    }
+
+    pub fn reader(&self) -> ImageReader<'_> {
+        ImageReader::new(self.base, &self.bytes)
+    }
+
+    /// Whether `addr` is inside the image.
+    pub fn contains(&self, addr: usize) -> bool {
+        addr >= self.base && addr - self.base < self.bytes.len()
+    }
+}
+
+struct PeHeaders {
+    image_base: usize,
+    size_of_image: usize,
+    size_of_headers: usize,
+    sections: Vec<Section>,
+}
+
+struct Section {
+    rva: usize,
+    virtual_size: usize,
+    raw_offset: usize,
+    raw_size: usize,
+}
+
+impl PeHeaders {
+    fn parse(data: &[u8]) -> Result<Self> {
+        if data.get(..2) != Some(b"MZ") {
+            return Err(invalid("missing DOS signature"));
+        }
+        let pe = read_u32(data, 0x3C)? as usize;
+        if data.get(pe..pe + 4) != Some(b"PE\0\0") {
+            return Err(invalid("missing PE signature"));
+        }
+
+        let coff = pe + 4;
+        let section_count = read_u16(data, coff + 2)? as usize;
+        let optional_size = read_u16(data, coff + 16)? as usize;
+
+        let optional = coff + 20;
+        let image_base = match read_u16(data, optional)? {
+            PE32_MAGIC => read_u32(data, optional + 28)? as usize,
+            PE32_PLUS_MAGIC => read_u64(data, optional + 24)? as usize,
+            magic => {
+                return Err(invalid(&format!(
+                    "unknown optional header magic 0x{magic:x}"
+                )))
+            }
+        };
+        let size_of_image = read_u32(data, optional + 56)? as usize;
+        let size_of_headers = read_u32(data, optional + 60)? as usize;
+
+        let section_table = optional + optional_size;
+        let sections = (0..section_count)
+            .map(|index| {
+                let header = section_table + index * 40;
+                Ok(Section {
+                    virtual_size: read_u32(data, header + 8)? as usize,
+                    rva: read_u32(data, header + 12)? as usize,
+                    raw_size: read_u32(data, header + 16)? as usize,
+                    raw_offset: read_u32(data, header + 20)? as usize,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            image_base,
+            size_of_image,
+            size_of_headers,
+            sections,
+        })
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidImage(reason.to_string())
+}
+
+fn read_bytes<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N]> {
+    data.get(offset..offset + N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| invalid("truncated PE headers"))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    read_bytes(data, offset).map(u16::from_le_bytes)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    read_bytes(data, offset).map(u32::from_le_bytes)
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64> {
+    read_bytes(data, offset).map(u64::from_le_bytes)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub const IMAGE_BASE: usize = 0x1_4000_0000;
+
+    /// A PE32+ file with one section, `.text` at RVA 0x1000 and file offset 0x400.
+    pub fn sample_pe(text: &[u8]) -> Vec<u8> {
+        let mut file = vec![0; 0x400 + text.len()];
+        file[..2].copy_from_slice(b"MZ");
+        file[0x3C..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        file[0x80..0x84].copy_from_slice(b"PE\0\0");
+        // COFF header
+        file[0x86..0x88].copy_from_slice(&1u16.to_le_bytes());
+        file[0x94..0x96].copy_from_slice(&0xF0u16.to_le_bytes());
+        // optional header
+        let optional = 0x98;
+        file[optional..optional + 2].copy_from_slice(&PE32_PLUS_MAGIC.to_le_bytes());
+        file[optional + 24..optional + 32].copy_from_slice(&(IMAGE_BASE as u64).to_le_bytes());
+        let size_of_image = 0x1000 + text.len().next_multiple_of(0x1000);
+        file[optional + 56..optional + 60].copy_from_slice(&(size_of_image as u32).to_le_bytes());
+        file[optional + 60..optional + 64].copy_from_slice(&0x400u32.to_le_bytes());
+        // section table
+        let section = optional + 0xF0;
+        file[section..section + 5].copy_from_slice(b".text");
+        let len = text.len() as u32;
+        file[section + 8..section + 12].copy_from_slice(&len.to_le_bytes());
+        file[section + 12..section + 16].copy_from_slice(&0x1000u32.to_le_bytes());
+        file[section + 16..section + 20].copy_from_slice(&len.to_le_bytes());
+        file[section + 20..section + 24].copy_from_slice(&0x400u32.to_le_bytes());
+
+        file[0x400..].copy_from_slice(text);
+        file
+    }
+
+    #[test]
+    fn map_pe_sections() {
+        let text = [0x48, 0x83, 0xEC, 0x48];
+        let image = Image::from_pe_file(&sample_pe(&text)).unwrap();
+
+        assert_eq!(image.base, IMAGE_BASE);
+        assert_eq!(image.bytes.len(), 0x2000);
+        assert_eq!(&image.bytes[..2], b"MZ");
+        assert_eq!(&image.bytes[0x1000..0x1004], &text);
+        assert!(image.contains(IMAGE_BASE + 0x1FFF));
+        assert!(!image.contains(IMAGE_BASE + 0x2000));
+    }
+
+    #[test]
+    fn memory_dump_base() {
+        let dump = sample_pe(&[0xCC; 4]);
+
+        assert_eq!(Image::from_memory_dump(dump.clone(), None).base, IMAGE_BASE);
+        assert_eq!(Image::from_memory_dump(dump, Some(0x1000)).base, 0x1000);
+        assert_eq!(Image::from_memory_dump(vec![0xCC; 4], None).base, 0);
+    }
+
+    #[test]
+    fn reject_invalid_pe() {
+        assert!(Image::from_pe_file(b"not a pe").is_err());
+
+        let mut file = sample_pe(&[0xCC; 4]);
+        file[0x80] = b'X';
+        assert!(Image::from_pe_file(&file).is_err());
+    }
+}
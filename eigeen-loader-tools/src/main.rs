@@ -0,0 +1,109 @@
+//! Offline tools for EigeenLoader address files.
+
+use std::process::ExitCode;
+
+use error::{Error, Result};
+
+mod error;
+mod generate;
+mod image;
+mod validate;
+
+const USAGE: &str = "Usage: eigeen-loader-tools <command> [args]
+
+Commands:
+    validate    check an address file against a game image
+    generate    generate a unique pattern for an RVA";
+
+/// A command, returns whether it succeeded.
+type Command = fn(&[String]) -> Result<bool>;
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    let (command, usage): (Command, &str) = match args.first().map(String::as_str) {
+        Some("validate") => (validate::run, validate::USAGE),
+        Some("generate") => (generate::run, generate::USAGE),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match command(&args[1..]) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(Error::Usage(e)) => {
+            eprintln!("{}\n\nUsage: eigeen-loader-tools {}", e, usage);
+            ExitCode::from(2)
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Command line arguments of a command: positional arguments, `--switch` and
+/// `--option <value>`.
+struct Options {
+    positional: Vec<String>,
+    switches: Vec<String>,
+    values: Vec<(String, String)>,
+}
+
+impl Options {
+    /// Parse `args`, `value_options` are the options followed by a value.
+    fn parse(args: &[String], switches: &[&str], value_options: &[&str]) -> Result<Self> {
+        let mut options = Options {
+            positional: Vec::new(),
+            switches: Vec::new(),
+            values: Vec::new(),
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if value_options.contains(&arg.as_str()) {
+                let Some(value) = args.next() else {
+                    return Err(Error::Usage(format!("missing value for {}", arg)));
+                };
+                options.values.push((arg.clone(), value.clone()));
+            } else if switches.contains(&arg.as_str()) {
+                options.switches.push(arg.clone());
+            } else if arg.starts_with("--") {
+                return Err(Error::Usage(format!("unknown option {}", arg)));
+            } else {
+                options.positional.push(arg.clone());
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Take exactly `N` positional arguments.
+    fn positional<const N: usize>(&mut self) -> Result<[String; N]> {
+        std::mem::take(&mut self.positional)
+            .try_into()
+            .map_err(|args: Vec<String>| {
+                Error::Usage(format!("expected {} arguments, got {}", N, args.len()))
+            })
+    }
+
+    fn switch(&self, name: &str) -> bool {
+        self.switches.iter().any(|switch| switch == name)
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Parse a hex number, with or without `0x`.
+fn parse_hex(name: &str, value: &str) -> Result<usize> {
+    usize::from_str_radix(value.trim_start_matches("0x"), 16)
+        .map_err(|_| Error::Usage(format!("invalid {}: {}", name, value)))
+}
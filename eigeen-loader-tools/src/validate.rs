@@ -0,0 +1,419 @@
+//! `validate`: check an address file against a game image.
+
+use std::{path::PathBuf, str::FromStr};
+
+use address_core::{
+    pattern_scan::{Pattern, PatternSet},
+    record::{AddressFile, ResolveReport},
+    revision::read_game_revision,
+};
+use shared::export::AddressName;
+
+use crate::{error::Result, image::Image, parse_hex, Options};
+
+pub const USAGE: &str = "\
+validate <address_records.json> <image> [options]
+
+Resolve every record against a game image, exits with 1 if a record is missing or ambiguous.
+
+Options:
+    --raw               <image> is a memory dump of the mapped module, not a PE file
+    --base <hex>        image base of a memory dump
+    --revision <rev>    game revision for record overrides, detected from the image by default
+    --lenient           only require records marked strict to match once";
+
+/// Number of addresses listed for an ambiguous record.
+const MAX_LISTED_RVAS: usize = 8;
+
+struct Args {
+    address_file: PathBuf,
+    image: PathBuf,
+    raw: bool,
+    base: Option<usize>,
+    revision: Option<String>,
+    lenient: bool,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut options = Options::parse(args, &["--raw", "--lenient"], &["--base", "--revision"])?;
+        let [address_file, image] = options.positional::<2>()?;
+        let base = options
+            .value("--base")
+            .map(|base| parse_hex("base", base))
+            .transpose()?;
+
+        Ok(Self {
+            address_file: address_file.into(),
+            image: image.into(),
+            raw: options.switch("--raw"),
+            base,
+            revision: options.value("--revision").map(str::to_string),
+            lenient: options.switch("--lenient"),
+        })
+    }
+}
+
+/// Run the command, returns whether every record was resolved.
+pub fn run(args: &[String]) -> Result<bool> {
+    let args = Args::parse(args)?;
+
+    let content = std::fs::read_to_string(&args.address_file)?;
+    let mut address_file = AddressFile::from_json(&content)?;
+    if !args.lenient {
+        address_file.strict = true;
+        for record in address_file.records.values_mut() {
+            record.strict = None;
+        }
+    }
+
+    let image = Image::open(&args.image, args.raw, args.base)?;
+
+    let revision = match args.revision {
+        Some(revision) => Some(revision),
+        None => detect_revision(&address_file, &image),
+    };
+    match revision.as_deref() {
+        Some(revision) => println!("game revision: {}", revision),
+        None => println!("game revision: unknown, revision overrides are not applied"),
+    }
+
+    let checks = check_records(&address_file, &image, revision.as_deref());
+    for check in checks.iter() {
+        println!("{}", check);
+    }
+
+    let failed = checks.iter().filter(|check| !check.is_ok()).count();
+    println!("{} records, {} failed", checks.len(), failed);
+
+    Ok(failed == 0)
+}
+
+/// Read the game revision from the image, through the `Core:GameRevision` record.
+fn detect_revision(address_file: &AddressFile, image: &Image) -> Option<String> {
+    let name = AddressName::CORE_GAME_REVISION.to_string();
+    let reader = image.reader();
+    let report = address_file.resolve_records([&name], &image.bytes, image.base, None, &reader);
+    let record = report.resolved.get(&name)?;
+
+    read_game_revision(&reader, record.addr).ok()
+}
+
+/// Result of checking a single record.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RecordCheck {
+    pub name: String,
+    pub status: Status,
+    /// Number of matches of each alternative, `None` for invalid patterns.
+    pub match_counts: Vec<Option<usize>>,
+    /// Whether a revision override applies.
+    pub overridden: bool,
+    pub required: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Resolved by the pattern alternative at `alternative`.
+    Resolved {
+        rva: usize,
+        alternative: usize,
+        in_image: bool,
+    },
+    /// Fixed RVA from a revision override.
+    Fixed {
+        rva: usize,
+    },
+    Ambiguous {
+        rvas: Vec<usize>,
+    },
+    Missing,
+}
+
+impl RecordCheck {
+    pub fn is_ok(&self) -> bool {
+        match self.status {
+            Status::Resolved { in_image, .. } => in_image,
+            Status::Fixed { .. } => true,
+            Status::Ambiguous { .. } | Status::Missing => false,
+        }
+    }
+}
+
+impl std::fmt::Display for RecordCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self.status {
+            Status::Resolved {
+                in_image: false, ..
+            } => "BAD OFFSET",
+            Status::Resolved { .. } | Status::Fixed { .. } => "OK",
+            Status::Ambiguous { .. } => "AMBIGUOUS",
+            Status::Missing => "MISSING",
+        };
+        write!(f, "{:<10} {}", state, self.name)?;
+
+        match &self.status {
+            Status::Resolved {
+                rva, alternative, ..
+            } => write!(f, ", rva 0x{:x}, pattern #{}", rva, alternative)?,
+            Status::Fixed { rva } => write!(f, ", rva 0x{:x}, fixed", rva)?,
+            Status::Ambiguous { rvas } => {
+                let mut listed = rvas
+                    .iter()
+                    .take(MAX_LISTED_RVAS)
+                    .map(|rva| format!("0x{:x}", rva))
+                    .collect::<Vec<_>>();
+                if rvas.len() > MAX_LISTED_RVAS {
+                    listed.push("...".to_string());
+                }
+                write!(f, ", rvas {}", listed.join(", "))?
+            }
+            Status::Missing => {}
+        }
+
+        if !self.match_counts.is_empty() {
+            let counts = self
+                .match_counts
+                .iter()
+                .map(|count| match count {
+                    Some(count) => count.to_string(),
+                    None => "invalid".to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+            write!(f, ", matches {}", counts)?;
+        }
+        if self.overridden {
+            write!(f, ", revision override")?;
+        }
+        if self.required {
+            write!(f, ", required")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Check every record of `address_file` against `image`, sorted by name.
+pub fn check_records(
+    address_file: &AddressFile,
+    image: &Image,
+    revision: Option<&str>,
+) -> Vec<RecordCheck> {
+    let reader = image.reader();
+    let ResolveReport {
+        resolved,
+        ambiguous,
+        ..
+    } = address_file.resolve_records(
+        address_file.records.keys(),
+        &image.bytes,
+        image.base,
+        revision,
+        &reader,
+    );
+    let match_counts = count_matches(address_file, image, revision);
+    let rva = |addr: usize| addr.wrapping_sub(image.base);
+
+    let mut names = address_file.records.keys().collect::<Vec<_>>();
+    names.sort();
+
+    names
+        .into_iter()
+        .map(|name| {
+            let record = &address_file.records[name];
+            let variant = record.variant(revision);
+
+            let status = if let Some(record) = resolved.get(name) {
+                match variant.rva {
+                    Some(fixed) => Status::Fixed { rva: fixed },
+                    None => {
+                        // dereferenced pointers may leave the image
+                        let is_static = variant
+                            .alternatives()
+                            .nth(record.alternative)
+                            .is_some_and(|alternative| alternative.is_static());
+                        Status::Resolved {
+                            rva: rva(record.addr),
+                            alternative: record.alternative,
+                            in_image: !is_static || image.contains(record.addr),
+                        }
+                    }
+                }
+            } else if let Some(addrs) = ambiguous.get(name) {
+                Status::Ambiguous {
+                    rvas: addrs.iter().map(|&addr| rva(addr)).collect(),
+                }
+            } else {
+                Status::Missing
+            };
+
+            RecordCheck {
+                name: name.clone(),
+                status,
+                match_counts: match_counts.get(name).cloned().unwrap_or_default(),
+                overridden: revision.is_some_and(|rev| record.revisions.contains_key(rev)),
+                required: record.required,
+            }
+        })
+        .collect()
+}
+
+/// Count the matches of every pattern alternative, by record name.
+fn count_matches(
+    address_file: &AddressFile,
+    image: &Image,
+    revision: Option<&str>,
+) -> std::collections::HashMap<String, Vec<Option<usize>>> {
+    let mut counts = std::collections::HashMap::new();
+    let mut owners = Vec::new();
+    let mut patterns = Vec::new();
+    for (name, record) in address_file.records.iter() {
+        let variant = record.variant(revision);
+        if variant.rva.is_some() {
+            continue;
+        }
+
+        let record_counts: &mut Vec<Option<usize>> = counts.entry(name.clone()).or_default();
+        for alternative in variant.alternatives() {
+            if let Ok(pattern) = Pattern::from_str(alternative.pattern) {
+                owners.push((name, record_counts.len()));
+                patterns.push(pattern);
+            }
+            record_counts.push(None);
+        }
+    }
+
+    let Ok(pattern_set) = PatternSet::new(patterns) else {
+        return counts;
+    };
+    for ((name, index), matches) in owners.into_iter().zip(pattern_set.scan_all(&image.bytes)) {
+        if let Some(record_counts) = counts.get_mut(name) {
+            record_counts[index] = Some(matches.len());
+        }
+    }
+
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::tests::{sample_pe, IMAGE_BASE};
+
+    /// `.text` at RVA 0x1000: a unique function, a repeated sequence, and a call into the
+    /// function.
+    fn sample_image() -> Image {
+        let mut text = vec![0xCC; 0x100];
+        text[0x10..0x17].copy_from_slice(&[0x48, 0x83, 0xEC, 0x48, 0x48, 0x8B, 0x05]);
+        text[0x40..0x43].copy_from_slice(&[0x90, 0x90, 0xC3]);
+        text[0x50..0x53].copy_from_slice(&[0x90, 0x90, 0xC3]);
+        text[0x60] = 0xE8;
+        text[0x61..0x65].copy_from_slice(&(0x10 - 0x65i32).to_le_bytes());
+
+        Image::from_pe_file(&sample_pe(&text)).unwrap()
+    }
+
+    fn sample_file(strict: bool) -> AddressFile {
+        let mut address_file = AddressFile::from_json(
+            r#"{
+                "schema_version": 3,
+                "records": {
+                    "Test:Func": { "pattern": "48 83 EC 48 48 8B 05", "required": true },
+                    "Test:Repeated": { "pattern": "90 90 C3" },
+                    "Test:Call": {
+                        "pattern": "AA BB CC",
+                        "fallbacks": [{ "pattern": "E8 ? ? ? ? CC", "steps": [{ "op": "call" }] }]
+                    },
+                    "Test:BadOffset": { "pattern": "48 83 EC 48", "offset": 1048576 },
+                    "Test:Missing": { "pattern": "AA BB CC", "revisions": { "421810": { "rva": 4096 } } }
+                }
+            }"#,
+        )
+        .unwrap();
+        address_file.strict = strict;
+        address_file
+    }
+
+    #[test]
+    fn check_sample_image() {
+        let image = sample_image();
+        let checks = check_records(&sample_file(true), &image, None);
+        let check = |name: &str| checks.iter().find(|check| check.name == name).unwrap();
+
+        assert_eq!(
+            check("Test:Func").status,
+            Status::Resolved {
+                rva: 0x1010,
+                alternative: 0,
+                in_image: true
+            }
+        );
+        assert_eq!(check("Test:Func").match_counts, vec![Some(1)]);
+        assert_eq!(
+            check("Test:Repeated").status,
+            Status::Ambiguous {
+                rvas: vec![0x1040, 0x1050]
+            }
+        );
+        assert_eq!(
+            check("Test:Call").status,
+            Status::Resolved {
+                rva: 0x1010,
+                alternative: 1,
+                in_image: true
+            }
+        );
+        assert_eq!(check("Test:Call").match_counts, vec![Some(0), Some(1)]);
+        assert!(!check("Test:BadOffset").is_ok());
+        assert_eq!(check("Test:Missing").status, Status::Missing);
+        assert!(!check("Test:Missing").overridden);
+
+        assert_eq!(checks.iter().filter(|check| check.is_ok()).count(), 2);
+    }
+
+    #[test]
+    fn check_lenient_with_revision() {
+        let image = sample_image();
+        let checks = check_records(&sample_file(false), &image, Some("421810"));
+        let check = |name: &str| checks.iter().find(|check| check.name == name).unwrap();
+
+        assert!(check("Test:Repeated").is_ok());
+        assert_eq!(check("Test:Missing").status, Status::Fixed { rva: 0x1000 });
+        assert!(check("Test:Missing").overridden);
+        assert!(check("Test:Missing").match_counts.is_empty());
+    }
+
+    #[test]
+    fn display_check() {
+        let check = RecordCheck {
+            name: "Test:Func".to_string(),
+            status: Status::Resolved {
+                rva: 0x1010,
+                alternative: 1,
+                in_image: true,
+            },
+            match_counts: vec![Some(0), Some(1), None],
+            overridden: true,
+            required: true,
+        };
+
+        assert_eq!(
+            check.to_string(),
+            "OK         Test:Func, rva 0x1010, pattern #1, matches 0/1/invalid, revision override, required"
+        );
+    }
+
+    #[test]
+    fn parse_args() {
+        let args = ["records.json", "dump.bin", "--raw", "--base", "0x140000000"].map(String::from);
+        let args = Args::parse(&args).unwrap();
+
+        assert_eq!(args.address_file, PathBuf::from("records.json"));
+        assert!(args.raw);
+        assert_eq!(args.base, Some(IMAGE_BASE));
+        assert!(!args.lenient);
+
+        assert!(Args::parse(&["records.json".to_string()]).is_err());
+        assert!(Args::parse(&["a", "b", "--unknown"].map(String::from)).is_err());
+    }
+}
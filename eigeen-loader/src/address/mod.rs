@@ -4,25 +4,42 @@ use std::{
     sync::{LazyLock, Mutex},
 };
 
-use log::{debug, error};
-use serde::{Deserialize, Serialize};
+use log::{debug, error, warn};
+
+use address_core::{
+    cache::{AddressCache, CacheKey},
+    record::{AddressFile, RecordVariant},
+};
 
 use crate::error::{Error, Result};
-use crate::utility;
+use crate::utility::{
+    self,
+    memory::{MemoryError, ProcessMemory},
+};
 
-static CACHE: LazyLock<Mutex<HashMap<String, usize>>> =
+static CACHE: LazyLock<Mutex<HashMap<String, CachedAddress>>> =
     LazyLock::new(|| Mutex::new(HashMap::new()));
 static mut ADDRESS_FILE: Option<AddressFile> = None;
+/// Game revision used to select per-revision record overrides.
+static REVISION: Mutex<Option<String>> = Mutex::new(None);
+
+/// A resolved address and the index of the pattern alternative that matched.
+#[derive(Debug, Clone, Copy)]
+struct CachedAddress {
+    addr: usize,
+    alternative: usize,
+}
 
 pub struct AddressRepository;
 
 impl AddressRepository {
+    const CACHE_FILE_PATH: &'static str = "./eigeen_loader/address/address_cache.json";
+
     /// Load address file.
     pub fn initialize<P: AsRef<Path>>(address_file_path: P) -> Result<()> {
-        let file = std::fs::File::open(address_file_path)?;
-        let reader = std::io::BufReader::new(file);
+        let content = std::fs::read_to_string(address_file_path)?;
 
-        let address_file: AddressFile = serde_json::from_reader(reader)?;
+        let address_file = AddressFile::from_json(&content)?;
 
         log::trace!("Address file loaded: {:?}", address_file);
 
@@ -35,25 +52,243 @@ impl AddressRepository {
 
     /// Get address by address file name.
     pub fn get_address(name: &str) -> Result<usize> {
+        Self::resolve(name).map(|cached| cached.addr)
+    }
+
+    /// Get the index of the pattern that matched for an address record.
+    ///
+    /// 0 for the primary pattern (or a fixed RVA), n for the n-th fallback pattern.
+    pub fn get_alternative(name: &str) -> Result<usize> {
+        Self::resolve(name).map(|cached| cached.alternative)
+    }
+
+    fn resolve(name: &str) -> Result<CachedAddress> {
         if let Some(cached) = CACHE.lock().unwrap().get(name) {
             return Ok(*cached);
         }
 
-        let Some(record) = Self::lookup_record(name) else {
+        let Some(variant) = Self::lookup_variant(name) else {
             return Err(Error::PatternUnmanaged(name.to_string()));
         };
 
-        let Some(addr_without_offset) = Self::pattern_scan(&record.pattern) else {
-            return Err(Error::PatternMismatch(name.to_string()));
+        let cached = if let Some(rva) = variant.rva {
+            let (base, _) =
+                unsafe { utility::windows::get_base_module_space() }.map_err(MemoryError::from)?;
+            CachedAddress {
+                addr: base + rva,
+                alternative: 0,
+            }
+        } else {
+            let strict = Self::is_strict(name);
+            // try alternatives in order
+            let mut found = None;
+            for (index, alternative) in variant.alternatives().enumerate() {
+                let mut addrs = Vec::new();
+                for addr_without_offset in Self::pattern_scan(alternative.pattern, strict) {
+                    // add offset
+                    let addr = (addr_without_offset as isize + alternative.offset) as usize;
+                    match alternative.apply_steps(&ProcessMemory, addr) {
+                        Ok(addr) if !addrs.contains(&addr) => addrs.push(addr),
+                        Ok(_) => {}
+                        Err(e) => {
+                            warn!("Resolve steps of {} pattern #{} failed: {}", name, index, e)
+                        }
+                    }
+                }
+
+                match addrs[..] {
+                    [] => continue,
+                    [addr] => {
+                        found = Some(CachedAddress {
+                            addr,
+                            alternative: index,
+                        });
+                        break;
+                    }
+                    _ => {
+                        error!(
+                            "Pattern #{} of strict record {} matched {} addresses",
+                            index,
+                            name,
+                            addrs.len()
+                        );
+                        return Err(MemoryError::MultipleMatchesFound.into());
+                    }
+                }
+            }
+            let Some(cached) = found else {
+                return Err(Error::PatternMismatch(name.to_string()));
+            };
+            cached
         };
+        CACHE.lock().unwrap().insert(name.to_string(), cached);
 
-        // add offset
-        let addr = (addr_without_offset as isize + record.offset) as usize;
-        CACHE.lock().unwrap().insert(name.to_string(), addr);
+        if cached.alternative > 0 {
+            debug!(
+                "{} found at 0x{:x} (fallback pattern #{})",
+                name, cached.addr, cached.alternative
+            );
+        } else {
+            debug!("{} found at 0x{:x}", name, cached.addr);
+        }
 
-        debug!("{} found at 0x{:x}", name, addr);
+        Ok(cached)
+    }
+
+    /// Resolve every record of the loaded address file in a single pass over the main module.
+    ///
+    /// Resolved addresses are stored in the cache.
+    ///
+    /// returns `(all_count, resolved_count)`
+    pub fn resolve_all() -> Result<(usize, usize)> {
+        let (base, size) =
+            unsafe { utility::windows::get_base_module_space() }.map_err(MemoryError::from)?;
+        let image = unsafe { std::slice::from_raw_parts(base as *const u8, size) };
+
+        // The revision record itself is resolved without overrides, as the revision is not
+        // known yet.
+        let revision = utility::game::get_game_revision();
+        if let Some(revision) = revision.as_ref() {
+            debug!("Selecting address records for game revision {}", revision);
+        }
+        *REVISION.lock().unwrap() = revision.clone();
+
+        // resolved addresses are kept on disk for the same game binary
+        let cache_key = revision.map(|revision| CacheKey::new(&revision, image));
+        let loaded = match cache_key.as_ref() {
+            Some(key) => Self::load_cache(key, base),
+            None => 0,
+        };
 
-        Ok(addr)
+        let (total, resolved) = Self::resolve_all_in(image, base);
+
+        if let Some(key) = cache_key {
+            if resolved > loaded {
+                if let Err(e) = Self::save_cache(key, base) {
+                    warn!("Failed to save address cache: {}", e);
+                }
+            }
+        }
+
+        Ok((total, resolved))
+    }
+
+    /// Resolve every record of the loaded address file against `image`, which is mapped at
+    /// `base` in the current process.
+    ///
+    /// returns `(all_count, resolved_count)`
+    pub fn resolve_all_in(image: &[u8], base: usize) -> (usize, usize) {
+        let Some(address_file) = Self::address_file() else {
+            return (0, 0);
+        };
+
+        let mut cache = CACHE.lock().unwrap();
+        let unresolved = address_file
+            .records
+            .keys()
+            .filter(|name| !cache.contains_key(name.as_str()))
+            .collect::<Vec<_>>();
+
+        let revision = REVISION.lock().unwrap().clone();
+        let report = address_file.resolve_records(
+            unresolved,
+            image,
+            base,
+            revision.as_deref(),
+            &ProcessMemory,
+        );
+        if !report.is_clean() {
+            warn!("Address diagnostic report: {}", report);
+        }
+        for (name, record) in report.resolved.into_iter() {
+            debug!("{} found at 0x{:x}", name, record.addr);
+            cache.insert(
+                name,
+                CachedAddress {
+                    addr: record.addr,
+                    alternative: record.alternative,
+                },
+            );
+        }
+
+        let resolved_count = address_file
+            .records
+            .keys()
+            .filter(|name| cache.contains_key(name.as_str()))
+            .count();
+
+        (address_file.records.len(), resolved_count)
+    }
+
+    /// Fill the cache from the address cache file, if it was built for the same game binary.
+    ///
+    /// Returns the number of loaded records.
+    fn load_cache(key: &CacheKey, base: usize) -> usize {
+        let Some(address_file) = Self::address_file() else {
+            return 0;
+        };
+        let Some(file_cache) = AddressCache::load(Self::CACHE_FILE_PATH, key) else {
+            debug!("Address cache missing or outdated.");
+            return 0;
+        };
+
+        let revision = REVISION.lock().unwrap().clone();
+        let mut cache = CACHE.lock().unwrap();
+        let mut count = 0;
+        for (name, record) in address_file.records.iter() {
+            let variant = record.variant(revision.as_deref());
+            if variant.rva.is_some() {
+                continue;
+            }
+            if let Some(entry) = file_cache.get(name, &variant.definition()) {
+                cache.insert(
+                    name.clone(),
+                    CachedAddress {
+                        addr: base + entry.rva,
+                        alternative: entry.alternative,
+                    },
+                );
+                count += 1;
+            }
+        }
+        debug!("Loaded {} addresses from cache.", count);
+
+        count
+    }
+
+    /// Write all resolved records to the address cache file.
+    fn save_cache(key: CacheKey, base: usize) -> Result<()> {
+        let Some(address_file) = Self::address_file() else {
+            return Ok(());
+        };
+
+        let revision = REVISION.lock().unwrap().clone();
+        let mut file_cache = AddressCache::new(key);
+        for (name, cached) in CACHE.lock().unwrap().iter() {
+            let Some(record) = address_file.records.get(name) else {
+                continue;
+            };
+            let variant = record.variant(revision.as_deref());
+            if variant.rva.is_some() {
+                continue;
+            }
+            // dereferenced pointers may change on each launch
+            let is_static = variant
+                .alternatives()
+                .nth(cached.alternative)
+                .is_some_and(|alternative| alternative.is_static());
+            if !is_static {
+                continue;
+            }
+            let Some(rva) = cached.addr.checked_sub(base) else {
+                continue;
+            };
+            file_cache.insert(name, variant.definition(), cached.alternative, rva);
+        }
+
+        file_cache.save(Self::CACHE_FILE_PATH)?;
+
+        Ok(())
     }
 
     /// Get pointer by address file name.
@@ -61,54 +296,120 @@ impl AddressRepository {
         Self::get_address(name).map(|addr| addr as *mut T)
     }
 
-    /// 从已加载的地址文件中获取特征码
-    fn lookup_record(name: &str) -> Option<&AddressRecord> {
-        unsafe {
-            if let Some(address_file) = ADDRESS_FILE.as_ref() {
-                if let Some(record) = address_file.records.get(name) {
-                    return Some(record);
-                }
-            }
-        }
+    /// 获取已加载的地址文件
+    fn address_file() -> Option<&'static AddressFile> {
+        unsafe { (*std::ptr::addr_of!(ADDRESS_FILE)).as_ref() }
+    }
+
+    /// 从已加载的地址文件中获取特征码，并应用当前游戏版本的覆盖项
+    fn lookup_variant(name: &str) -> Option<RecordVariant<'static>> {
+        let record = Self::address_file()?.records.get(name)?;
+        let revision = REVISION.lock().unwrap();
+
+        Some(record.variant(revision.as_deref()))
+    }
+
+    /// 记录是否必须唯一匹配
+    fn is_strict(name: &str) -> bool {
+        let Some(address_file) = Self::address_file() else {
+            return false;
+        };
 
-        None
+        address_file
+            .records
+            .get(name)
+            .is_some_and(|record| address_file.is_strict(record))
     }
 
-    /// Pattern scan.
-    fn pattern_scan(pattern: &str) -> Option<usize> {
-        match utility::memory::auto_scan_first(pattern) {
-            Ok(addr) => Some(addr),
+    /// Pattern scan, returns every match if `all` is set, or the first one.
+    fn pattern_scan(pattern: &str, all: bool) -> Vec<usize> {
+        let result = if all {
+            utility::memory::auto_scan_all(pattern)
+        } else {
+            utility::memory::auto_scan_first(pattern).map(|addr| vec![addr])
+        };
+
+        match result {
+            Ok(addrs) => addrs,
+            // not an error yet, a fallback pattern may match
+            Err(MemoryError::NotFound) => Vec::new(),
             Err(e) => {
                 error!("Pattern scan failed: {}", e);
-                None
+                Vec::new()
             }
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct AddressFile {
-    records: HashMap<String, AddressRecord>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct AddressRecord {
-    pattern: String,
-    offset: isize,
-}
-
 #[cfg(test)]
 mod tests {
+    use std::str::FromStr;
+
+    use address_core::{
+        pattern_scan::{Pattern, PatternByte},
+        record::SCHEMA_VERSION,
+        step::ImageReader,
+    };
     use convert_case::{Case, Casing};
 
     use super::*;
 
     const ADDRESS_RECORDS_JSON: &str = include_str!("address_records.json");
 
+    #[test]
+    fn bundled_records_parse() {
+        let address_file = AddressFile::from_json(ADDRESS_RECORDS_JSON).unwrap();
+
+        assert_eq!(address_file.schema_version, SCHEMA_VERSION);
+        for (name, record) in address_file.records.iter() {
+            assert!(Pattern::from_str(&record.pattern).is_ok(), "{}", name);
+        }
+
+        let image = [0; 0x100];
+        let report = address_file.resolve_records(
+            address_file.records.keys(),
+            &image,
+            0,
+            None,
+            &ImageReader::new(0, &image),
+        );
+        assert!(report.resolved.is_empty());
+    }
+
+    #[test]
+    fn bundled_records_use_markers() {
+        let address_file = AddressFile::from_json(ADDRESS_RECORDS_JSON).unwrap();
+
+        for (name, record) in address_file.records.iter() {
+            // targets before the pattern are marked with `^`, not a negative offset
+            assert!(record.offset >= 0, "{}", name);
+
+            // resolves to the marker plus offset of a match
+            let pattern = Pattern::from_str(&record.pattern).unwrap();
+            let start = 0x100;
+            let mut image = vec![0xCC; 0x400];
+            for (i, pb) in pattern.bytes().iter().enumerate() {
+                image[start + i] = match *pb {
+                    PatternByte::Byte(b) => b,
+                    PatternByte::Masked { value, .. } => value,
+                    PatternByte::Any => 0xCC,
+                };
+            }
+            let report =
+                address_file.resolve_records([name], &image, 0, None, &ImageReader::new(0, &image));
+            assert_eq!(
+                report.resolved[name].addr,
+                start + pattern.result_offset() + record.offset as usize,
+                "{}",
+                name
+            );
+        }
+    }
+
     #[test]
     #[ignore]
     fn create_managed_address_names() {
-        let address_file: AddressFile = serde_json::from_str(ADDRESS_RECORDS_JSON).unwrap();
+        let address_file = AddressFile::from_json(ADDRESS_RECORDS_JSON).unwrap();
 
         let mut names = address_file.records.keys().collect::<Vec<_>>();
         names.sort();
@@ -20,6 +20,9 @@ impl CommandHandler {
             "ping" => {
                 message_with_info("Pong!");
             }
+            "plugins" => {
+                do_plugins();
+            }
             "load" => {
                 let Some(name) = parts.get(1) else {
                     return;
@@ -34,6 +37,20 @@ impl CommandHandler {
 
                 do_unload(name);
             }
+            "reload" => {
+                let Some(name) = parts.get(1) else {
+                    return;
+                };
+
+                do_reload(name);
+            }
+            "enable" | "disable" => {
+                let Some(name) = parts.get(1) else {
+                    return;
+                };
+
+                do_set_enabled(name, program == "enable");
+            }
             _ => {}
         }
     }
@@ -61,6 +78,61 @@ fn do_unload(name: &str) {
     message_with_info(&format!("Unloaded plugin: {}", name));
 }
 
+fn do_reload(name: &str) {
+    let mut loader = crate::PLUGIN_LOADER.lock().unwrap();
+    let Some(loader) = loader.as_mut() else {
+        message_with_info("Failed to reload plugin: plugin loader is not initialized");
+        return;
+    };
+    if let Err(e) = loader.reload(name) {
+        message_with_info(&format!("Failed to reload plugin: {}", e));
+        return;
+    }
+
+    message_with_info(&format!("Reloaded plugin: {}", name));
+}
+
+fn do_plugins() {
+    let core_report = crate::core_extension::CoreAPI::instance().report();
+    if core_report.total() > 0 {
+        message_with_info(&format!("Core extensions ({}):", core_report.total()));
+        for entry in core_report.entries() {
+            message_with_info(&entry.to_string());
+        }
+    }
+
+    let report = crate::plugin::PluginLoader::report();
+    message_with_info(&format!(
+        "Plugins ({} loaded, {} total):",
+        report.loaded(),
+        report.total()
+    ));
+    for entry in report.entries() {
+        message_with_info(&entry.to_string());
+    }
+}
+
+fn do_set_enabled(name: &str, enabled: bool) {
+    let state = if enabled { "enable" } else { "disable" };
+    let mut loader = crate::PLUGIN_LOADER.lock().unwrap();
+    let Some(loader) = loader.as_mut() else {
+        message_with_info(&format!(
+            "Failed to {} plugin: plugin loader is not initialized",
+            state
+        ));
+        return;
+    };
+    if let Err(e) = loader.set_plugin_enabled(name, enabled) {
+        message_with_info(&format!("Failed to {} plugin: {}", state, e));
+        return;
+    }
+
+    message_with_info(&format!(
+        "Plugin {} {}d, takes effect on next launch.",
+        name, state
+    ));
+}
+
 fn message_with_info(msg: &str) {
     info!("{}", msg);
     utility::game::show_system_message(msg, 0);
@@ -0,0 +1,147 @@
+//! Loader configuration, persisted across launches.
+//!
+//! ```toml
+//! [plugins.MyPlugin]
+//! enabled = false
+//!
+//! [core_functions]
+//! conflict_policy = "last_wins"
+//! ```
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::Result, function_registry::ConflictPolicy};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoaderConfig {
+    /// Per-plugin settings, by plugin name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub plugins: BTreeMap<String, PluginConfig>,
+    #[serde(default)]
+    pub core_functions: CoreFunctionsConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoreFunctionsConfig {
+    /// Applied when core extensions register the same function name.
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl LoaderConfig {
+    pub const PATH: &'static str = "./eigeen_loader/config.toml";
+
+    pub fn from_toml(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// Load the config at `path`, the default config if the file does not exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        Self::from_toml(&content)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_toml()?)?;
+
+        Ok(())
+    }
+
+    /// Plugins are enabled unless disabled in the config.
+    pub fn is_plugin_enabled(&self, name: &str) -> bool {
+        self.plugins.get(name).is_none_or(|plugin| plugin.enabled)
+    }
+
+    pub fn set_plugin_enabled(&mut self, name: &str, enabled: bool) {
+        self.plugins.entry(name.to_string()).or_default().enabled = enabled;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plugin_enabled() {
+        let mut config = LoaderConfig::from_toml(
+            r#"
+            [plugins.Disabled]
+            enabled = false
+
+            [plugins.Enabled]
+            "#,
+        )
+        .unwrap();
+
+        assert!(!config.is_plugin_enabled("Disabled"));
+        assert!(config.is_plugin_enabled("Enabled"));
+        assert!(config.is_plugin_enabled("Unknown"));
+
+        config.set_plugin_enabled("Disabled", true);
+        config.set_plugin_enabled("Unknown", false);
+        assert!(config.is_plugin_enabled("Disabled"));
+        assert!(!config.is_plugin_enabled("Unknown"));
+
+        let saved = LoaderConfig::from_toml(&config.to_toml().unwrap()).unwrap();
+        assert_eq!(saved, config);
+
+        assert_eq!(
+            LoaderConfig::from_toml("").unwrap(),
+            LoaderConfig::default()
+        );
+    }
+
+    #[test]
+    fn core_function_conflict_policy() {
+        let config = LoaderConfig::from_toml(
+            r#"
+            [core_functions]
+            conflict_policy = "namespaced"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.core_functions.conflict_policy,
+            ConflictPolicy::Namespaced
+        );
+
+        assert_eq!(
+            LoaderConfig::default().core_functions.conflict_policy,
+            ConflictPolicy::LastWins
+        );
+        assert!(
+            LoaderConfig::from_toml("[core_functions]\nconflict_policy = \"unknown\"").is_err()
+        );
+    }
+}
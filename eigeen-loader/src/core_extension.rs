@@ -3,149 +3,228 @@
 //! Core plugin designed not to be safely unloaded.
 //! It provides some functions for other plugins or core module to use.
 
-use std::{collections::HashMap, ffi::c_void, path::Path};
+use std::{
+    cell::RefCell,
+    ffi::c_void,
+    path::Path,
+    sync::{LazyLock, Mutex},
+    time::Instant,
+};
 
 use log::{debug, error, info, warn};
-use shared::export::{core_extension::CoreAPIParam, LoaderVersion};
-use windows::{
-    core::{s, PCWSTR},
-    Win32::{
-        Foundation::HMODULE,
-        System::LibraryLoader::{GetProcAddress, LoadLibraryW},
+use shared::export::core_extension::{
+    CoreAPIParam, CoreFunctionCode, FunctionDescriptor, InterfaceHeader,
+};
+use windows::Win32::Foundation::HMODULE;
+
+use crate::{
+    config::LoaderConfig,
+    error::{Error, Result},
+    function_registry::{FunctionRegistry, Listing, RegisteredFunction, Registration},
+    module_loader::{
+        discovery::{self, ModuleLocation},
+        ModuleLoader, ModulePolicy, WindowsLoader,
     },
+    report::{LoadEntry, LoadReport, LoadStatus},
 };
 
-use crate::error::{Error, Result};
+static CORE_API: LazyLock<CoreAPI> = LazyLock::new(CoreAPI::new);
+
+thread_local! {
+    /// Core extension whose `CoreInitialize` runs on this thread.
+    static INITIALIZING: RefCell<Option<Provider>> = const { RefCell::new(None) };
+}
+
+/// The core extension owning a registration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Provider {
+    /// Module handle of the extension, 0 if unknown.
+    owner: usize,
+    name: String,
+}
+
+impl Provider {
+    /// The core extension initializing on this thread.
+    ///
+    /// Registrations made outside of `CoreInitialize`, or from another thread, have an
+    /// unknown provider, which never matches another one.
+    fn current() -> Self {
+        INITIALIZING.with_borrow(|provider| {
+            provider.clone().unwrap_or_else(|| Provider {
+                owner: 0,
+                name: "unknown".to_string(),
+            })
+        })
+    }
+}
 
 /// Core API for dynamic registration and usage.
 ///
-/// Non-thread-safe.
-#[derive(Debug, Default)]
+/// Thread-safe, functions and interfaces can be registered and looked up from any thread.
+#[derive(Debug)]
 pub struct CoreAPI {
-    extensions: Vec<CoreExtension>,
-    functions: HashMap<String, *const c_void>,
+    extensions: Mutex<Vec<CoreExtension>>,
+    functions: FunctionRegistry,
+    report: Mutex<LoadReport>,
 }
 
-unsafe impl Send for CoreAPI {}
-
 impl CoreAPI {
     const CORE_EXT_DIR: &str = "eigeen_loader/core_extensions/";
+    const MODULE_LOADER: ModuleLoader<WindowsLoader> =
+        ModuleLoader::new(WindowsLoader, ModulePolicy::CORE_EXTENSION);
+
+    fn new() -> Self {
+        let config = LoaderConfig::load(Path::new(LoaderConfig::PATH)).unwrap_or_else(|e| {
+            error!("Failed to load loader config, using defaults: {}", e);
+            LoaderConfig::default()
+        });
+
+        Self {
+            extensions: Mutex::default(),
+            functions: FunctionRegistry::new(config.core_functions.conflict_policy),
+            report: Mutex::default(),
+        }
+    }
 
-    pub fn instance() -> &'static mut CoreAPI {
-        static mut INSTANCE: Option<CoreAPI> = None;
-
-        unsafe {
-            if INSTANCE.is_none() {
-                INSTANCE = Some(CoreAPI::default());
-            }
+    pub fn instance() -> &'static CoreAPI {
+        &CORE_API
+    }
 
-            INSTANCE.as_mut().unwrap()
-        }
+    /// Register an untyped function, owned by the core extension initializing.
+    pub fn register_function(&self, name: &str, function: *const c_void) -> Result<()> {
+        self.register_typed_function(name, function, None, 0)
     }
 
-    pub fn register_function(&mut self, name: &str, function: *const c_void) {
-        self.functions.insert(name.to_string(), function);
+    /// Register a function with its signature and interface version.
+    pub fn register_typed_function(
+        &self,
+        name: &str,
+        function: *const c_void,
+        signature: Option<&str>,
+        version: u32,
+    ) -> Result<()> {
+        let provider = Provider::current();
+        let function = RegisteredFunction::new(function, provider.owner, &provider.name)
+            .with_descriptor(signature, version);
+
+        let registration = self.functions.register(name, function);
+        log_registration("function", name, &provider.name, &registration);
+        registration.map(|_| ())
     }
 
     pub fn get_function(&self, name: &str) -> Option<*const c_void> {
-        self.functions.get(name).copied()
+        self.functions.get_checked(name, None, 0).ok()
     }
 
-    pub fn load_core_exts(&mut self) -> Result<(usize, usize)> {
-        if !Path::new(Self::CORE_EXT_DIR).exists() {
-            info!("Core extensions directory not found, skipping.");
-            return Ok((0, 0));
-        }
+    /// Get a function, checking its signature and interface version if requested.
+    pub fn get_typed_function(
+        &self,
+        name: &str,
+        signature: Option<&str>,
+        version: u32,
+    ) -> Result<*const c_void> {
+        self.functions.get_checked(name, signature, version)
+    }
 
-        let mut stat = (0, 0);
-
-        for entry in std::fs::read_dir(Self::CORE_EXT_DIR)? {
-            let entry = entry?;
-            let path = entry.path();
-            if !path.is_file() {
-                continue;
-            }
-            if let Some(ext) = path.extension() {
-                if ext == "dll" {
-                    stat.0 += 1;
-
-                    match Self::init_core_extension(&path) {
-                        Ok(extension) => {
-                            info!("Core extension loaded: {}", extension.name);
-                            self.extensions.push(extension);
-
-                            stat.1 += 1;
-                        }
-                        Err(e) => error!(
-                            "Failed to load core extension {}: {}",
-                            path.file_stem()
-                                .unwrap_or_default()
-                                .to_str()
-                                .unwrap_or_default(),
-                            e
-                        ),
-                    }
-                }
-            }
-        }
+    /// Register an interface table, owned by the core extension initializing.
+    ///
+    /// # Safety
+    ///
+    /// `table` must be null, or point to a table starting with an [`InterfaceHeader`].
+    pub unsafe fn register_interface(
+        &self,
+        name: &str,
+        table: *const InterfaceHeader,
+    ) -> Result<()> {
+        let provider = Provider::current();
+
+        let registration =
+            self.functions
+                .register_interface(name, table, provider.owner, &provider.name);
+        log_registration("interface", name, &provider.name, &registration);
+        registration.map(|_| ())
+    }
+
+    pub fn query_interface(&self, name: &str, min_version: u32) -> Result<*const InterfaceHeader> {
+        self.functions.query_interface(name, min_version)
+    }
 
-        Ok(stat)
+    /// Registered functions and interfaces, with their providers.
+    pub fn list(&self) -> Listing {
+        self.functions.list()
     }
 
-    fn init_core_extension<P: AsRef<Path>>(path: P) -> Result<CoreExtension> {
-        let path_w =
-            crate::utility::string::to_wstring_bytes_with_nul(path.as_ref().to_str().unwrap());
+    /// Load results of all core extensions found.
+    pub fn report(&self) -> LoadReport {
+        self.report.lock().unwrap().clone()
+    }
 
-        // load module
-        let hmodule = unsafe { LoadLibraryW(PCWSTR::from_raw(path_w.as_ptr()))? };
+    /// Load all core extensions.
+    ///
+    /// No lock is held while an extension initializes, so it can use the whole API.
+    pub fn load_core_exts(&self) -> Result<LoadReport> {
+        if !Path::new(Self::CORE_EXT_DIR).exists() {
+            info!("Core extensions directory not found, skipping.");
+            return Ok(self.report());
+        }
 
-        // run initialize function
-        unsafe {
-            let init_func = GetProcAddress(hmodule, s!("CoreInitialize"));
-            if let Some(init_func) = init_func {
-                let init_func: InitializeFunc = std::mem::transmute(init_func);
+        let discovery = discovery::scan(Path::new(Self::CORE_EXT_DIR))?;
+        for path in discovery.conflicts {
+            warn!(
+                "Core extension skipped, another one has the same file name: {}",
+                path.display()
+            );
+        }
 
-                let param = new_core_api_param();
-                let code = init_func(&param);
-                if code != 0 {
-                    return Err(Error::InitCoreExtension(code));
+        for location in discovery.modules {
+            let start = Instant::now();
+            let status = match Self::init_core_extension(&location) {
+                Ok(extension) => {
+                    info!("Core extension loaded: {}", extension.name);
+                    self.extensions.lock().unwrap().push(extension);
+
+                    LoadStatus::Loaded
+                }
+                Err(e) => {
+                    error!("Failed to load core extension {}: {}", location.name, e);
+                    LoadStatus::from_error(&e)
                 }
-            } else {
-                warn!("Core extension has no CoreInitialize function. Is it a valid extension?");
-            }
+            };
+            self.report.lock().unwrap().record(LoadEntry::new(
+                &location.name,
+                None,
+                status,
+                start.elapsed(),
+            ));
         }
 
-        // run version function
-        let mut version = LoaderVersion::default();
+        Ok(self.report())
+    }
 
-        unsafe {
-            let version_func = GetProcAddress(hmodule, s!("LoaderVersion"));
-            if let Some(version_func) = version_func {
-                let version_func: VersionFunc = std::mem::transmute(version_func);
+    fn init_core_extension(location: &ModuleLocation) -> Result<CoreExtension> {
+        let hmodule = Self::MODULE_LOADER.load(&location.dll, None, |hmodule, init_func| {
+            let init_func: InitializeFunc = unsafe { std::mem::transmute(init_func) };
+            let param = new_core_api_param();
 
-                version_func(&mut version);
-            }
-        }
+            // registrations made while it initializes are owned by the extension
+            INITIALIZING.set(Some(Provider {
+                owner: hmodule.0 as usize,
+                name: location.name.clone(),
+            }));
+            let code = init_func(&param);
+            INITIALIZING.set(None);
 
-        // loader version compatibility check
-        if version.major != 1 {
-            return Err(Error::IncompatiblePluginRequiredVersion(version));
-        }
+            code
+        })?;
 
         Ok(CoreExtension {
-            name: path
-                .as_ref()
-                .file_stem()
-                .unwrap()
-                .to_string_lossy()
-                .to_string(),
+            name: location.name.clone(),
             handle: hmodule,
         })
     }
 }
 
 type InitializeFunc = extern "C" fn(&CoreAPIParam) -> i32;
-type VersionFunc = unsafe extern "C" fn(&mut LoaderVersion);
 
 #[derive(Debug)]
 struct CoreExtension {
@@ -153,48 +232,155 @@ struct CoreExtension {
     handle: HMODULE,
 }
 
+unsafe impl Send for CoreExtension {}
+
 fn new_core_api_param() -> CoreAPIParam {
     CoreAPIParam {
         add_core_function: add_core_function as *const c_void,
         get_core_function: get_core_function as *const c_void,
+        size: std::mem::size_of::<CoreAPIParam>() as u32,
+        version: CoreAPIParam::VERSION,
+        add_typed_core_function: add_typed_core_function as *const c_void,
+        get_typed_core_function: get_typed_core_function as *const c_void,
+        register_interface: register_interface as *const c_void,
+        query_interface: query_interface as *const c_void,
     }
 }
 
-extern "C" fn add_core_function(name: *const u8, len: u32, func: *const c_void) {
-    if len == 0 {
-        // try to initialize c-string
-        let c_name = unsafe { std::ffi::CStr::from_ptr(name as *const i8) };
-        let name = c_name.to_str().unwrap_or_default();
-
-        debug!("Core extension function added: {}", name);
-        CoreAPI::instance().register_function(name, func);
-        return;
+fn log_registration(kind: &str, name: &str, provider: &str, registration: &Result<Registration>) {
+    match registration {
+        Ok(Registration::Registered) => debug!("Core {} added: {} by {}", kind, name, provider),
+        Ok(Registration::Replaced { previous }) => warn!(
+            "Core {} {} of {} replaced by the one of {}",
+            kind, name, previous, provider
+        ),
+        Ok(Registration::Ignored { existing }) => warn!(
+            "Core {} {} of {} ignored, already registered by {}",
+            kind, name, provider, existing
+        ),
+        Ok(Registration::Namespaced(namespaced)) => warn!(
+            "Core {} {} of {} registered as {}, already registered by another module",
+            kind, name, provider, namespaced
+        ),
+        Err(e) => error!("{}", e),
     }
+}
 
-    let name_slice = unsafe { std::slice::from_raw_parts(name, len as usize) };
-    let name = std::str::from_utf8(name_slice).unwrap_or_default();
+extern "C" fn add_core_function(name: *const u8, len: u32, func: *const c_void) {
+    let name = unsafe { read_name(name, len) }.unwrap_or_default();
 
-    debug!("Core extension function added: {}", name);
-    CoreAPI::instance().register_function(name, func);
+    // errors are logged, there is no result to report them
+    let _ = CoreAPI::instance().register_function(name, func);
 }
 
 extern "C" fn get_core_function(name: *const u8, len: u32) -> *const c_void {
-    if len == 0 {
-        // try to initialize c-string
-        let c_name = unsafe { std::ffi::CStr::from_ptr(name as *const i8) };
-        let name = c_name.to_str().unwrap_or_default();
-
-        debug!("Core extension function get: {}", name);
-        return CoreAPI::instance()
-            .get_function(name)
-            .unwrap_or(std::ptr::null());
-    }
-
-    let name_slice = unsafe { std::slice::from_raw_parts(name, len as usize) };
-    let name = std::str::from_utf8(name_slice).unwrap_or_default();
+    let name = unsafe { read_name(name, len) }.unwrap_or_default();
 
     debug!("Core extension function get: {}", name);
     CoreAPI::instance()
         .get_function(name)
         .unwrap_or(std::ptr::null())
 }
+
+/// Read a function name, nul-terminated if `len` is 0.
+unsafe fn read_name<'a>(name: *const u8, len: u32) -> Option<&'a str> {
+    let bytes = if len == 0 {
+        std::ffi::CStr::from_ptr(name as *const i8).to_bytes()
+    } else {
+        std::slice::from_raw_parts(name, len as usize)
+    };
+
+    std::str::from_utf8(bytes).ok()
+}
+
+extern "C" fn add_typed_core_function(
+    name: *const u8,
+    len: u32,
+    descriptor: &FunctionDescriptor,
+    func: *const c_void,
+) -> i32 {
+    let Some(name) = (unsafe { read_name(name, len) }) else {
+        return CoreFunctionCode::InvalidUtf8String as i32;
+    };
+    let Ok(signature) = unsafe { descriptor.signature() }.transpose() else {
+        return CoreFunctionCode::InvalidUtf8String as i32;
+    };
+
+    match CoreAPI::instance().register_typed_function(name, func, signature, descriptor.version) {
+        Ok(()) => CoreFunctionCode::Ok as i32,
+        Err(e) => error_code(&e) as i32,
+    }
+}
+
+extern "C" fn get_typed_core_function(
+    name: *const u8,
+    len: u32,
+    descriptor: &FunctionDescriptor,
+    result: &mut *const c_void,
+) -> i32 {
+    let Some(name) = (unsafe { read_name(name, len) }) else {
+        return CoreFunctionCode::InvalidUtf8String as i32;
+    };
+    let Ok(signature) = unsafe { descriptor.signature() }.transpose() else {
+        return CoreFunctionCode::InvalidUtf8String as i32;
+    };
+
+    debug!("Core extension function get: {}", name);
+    match CoreAPI::instance().get_typed_function(name, signature, descriptor.version) {
+        Ok(function) => {
+            *result = function;
+            CoreFunctionCode::Ok as i32
+        }
+        Err(e) => {
+            warn!("{}", e);
+            error_code(&e) as i32
+        }
+    }
+}
+
+extern "C" fn register_interface(name: *const u8, len: u32, table: *const InterfaceHeader) -> i32 {
+    let Some(name) = (unsafe { read_name(name, len) }) else {
+        return CoreFunctionCode::InvalidUtf8String as i32;
+    };
+
+    match unsafe { CoreAPI::instance().register_interface(name, table) } {
+        Ok(()) => CoreFunctionCode::Ok as i32,
+        Err(e) => error_code(&e) as i32,
+    }
+}
+
+extern "C" fn query_interface(
+    name: *const u8,
+    len: u32,
+    min_version: u32,
+    result: &mut *const InterfaceHeader,
+) -> i32 {
+    let Some(name) = (unsafe { read_name(name, len) }) else {
+        return CoreFunctionCode::InvalidUtf8String as i32;
+    };
+
+    debug!("Core extension interface get: {} v{}", name, min_version);
+    match CoreAPI::instance().query_interface(name, min_version) {
+        Ok(table) => {
+            *result = table;
+            CoreFunctionCode::Ok as i32
+        }
+        Err(e) => {
+            warn!("{}", e);
+            error_code(&e) as i32
+        }
+    }
+}
+
+/// Result code of a registry error.
+pub fn error_code(e: &Error) -> CoreFunctionCode {
+    match e {
+        Error::CoreFunctionSignatureMismatch { .. } => CoreFunctionCode::SignatureMismatch,
+        Error::CoreFunctionVersionMismatch { .. } | Error::InterfaceVersionTooOld { .. } => {
+            CoreFunctionCode::VersionMismatch
+        }
+        Error::InvalidInterface(_) => CoreFunctionCode::InvalidInterface,
+        Error::CoreFunctionConflict { .. } => CoreFunctionCode::Conflict,
+        _ => CoreFunctionCode::NotFound,
+    }
+}
@@ -1,4 +1,4 @@
-use shared::export::LoaderVersion;
+use shared::export::{LoaderVersion, LoaderVersionRange, ParseVersionError};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -10,27 +10,85 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
+    #[error("TOML error: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("TOML serialize error: {0}")]
+    TomlSerialize(#[from] toml::ser::Error),
     #[error("Inline hook error: {0}")]
     InlineHook(#[from] safetyhook::inline_hook::InlineError),
     #[error("Mid hook error: {0}")]
     MidHook(#[from] safetyhook::mid_hook::MidError),
     #[error("Memory error: {0}")]
     Memory(#[from] crate::utility::memory::MemoryError),
+    #[error("Address file error: {0}")]
+    Address(#[from] address_core::Error),
 
     #[error("Failed to initialize plugin: code {0}")]
     InitPlugin(i32),
     #[error("Failed to initialize core extension: code {0}")]
     InitCoreExtension(i32),
-    #[error("Incompatible plugin version required: {0}")]
-    IncompatiblePluginRequiredVersion(LoaderVersion),
+    #[error("Incompatible plugin version required: {required}, loader version is {current}")]
+    IncompatiblePluginRequiredVersion {
+        required: LoaderVersionRange,
+        current: LoaderVersion,
+    },
+    #[error("Required loader version is not declared")]
+    MissingRequiredVersion,
+    #[error("Version error: {0}")]
+    Version(#[from] ParseVersionError),
 
     #[error("Pattern mismatch: {0}")]
     PatternMismatch(String),
     #[error("Pattern name is not managed by loader: {0}")]
     PatternUnmanaged(String),
 
+    #[error("Core function not found: {0}")]
+    CoreFunctionNotFound(String),
+    #[error(
+        "Core function {name} signature mismatch: requested {requested}, registered {registered}"
+    )]
+    CoreFunctionSignatureMismatch {
+        name: String,
+        requested: String,
+        registered: String,
+    },
+    #[error(
+        "Core function {name} version mismatch: requested {requested}, registered {registered}"
+    )]
+    CoreFunctionVersionMismatch {
+        name: String,
+        requested: u32,
+        registered: u32,
+    },
+    #[error("Core function {name} of {provider} conflicts with the one of {existing}")]
+    CoreFunctionConflict {
+        name: String,
+        provider: String,
+        existing: String,
+    },
+    #[error("Interface not found: {0}")]
+    InterfaceNotFound(String),
+    #[error("Invalid interface table: {0}")]
+    InvalidInterface(String),
+    #[error("Interface {name} version {registered} is older than required {required}")]
+    InterfaceVersionTooOld {
+        name: String,
+        required: u32,
+        registered: u32,
+    },
+
     #[error("Plugin not found at path: {0}")]
     PluginNotFound(String),
     #[error("Plugin cannot safely unload.")]
     UnloadPlugin,
+    #[error("Plugin {plugin} is required by {}, unload them first", .dependents.join(", "))]
+    PluginInUse {
+        plugin: String,
+        dependents: Vec<String>,
+    },
+    #[error("Plugin {plugin} depends on {dependency}, which is missing or failed to load")]
+    MissingDependency { plugin: String, dependency: String },
+    /// Each plugin depends on the next one.
+    #[error("Plugin dependency cycle: {}", .0.join(" -> "))]
+    DependencyCycle(Vec<String>),
 }
@@ -0,0 +1,428 @@
+//! Named events between the loader and plugins.
+//!
+//! Plugins subscribe callbacks to event names and publish events with a byte payload.
+//! Subscriptions are owned by the module of their callback, and are removed when the
+//! owning plugin is unloaded.
+//!
+//! Built-in events published by the loader:
+//!
+//! | Name | Payload |
+//! | --- | --- |
+//! | [`CHAT_SENT`] | the UTF-8 chat message |
+//! | [`AFTER_MH_MAIN_CTOR`] | the mhMain address, `usize` little endian |
+//! | [`SINGLETON_REGISTERED`] | the singleton address, `usize` little endian, then its UTF-8 name |
+//!
+//! [`AFTER_MH_MAIN_CTOR`] and [`SINGLETON_REGISTERED`] are published before any plugin is
+//! loaded, so they are retained and replayed to each later subscriber.
+
+use std::{ffi::c_void, panic::AssertUnwindSafe, sync::Mutex};
+
+use log::error;
+
+use crate::in_flight;
+
+pub const CHAT_SENT: &str = "loader:chat_sent";
+pub const AFTER_MH_MAIN_CTOR: &str = "loader:after_mh_main_ctor";
+pub const SINGLETON_REGISTERED: &str = "loader:singleton_registered";
+
+static EVENT_BUS: Mutex<EventBus<EventCallback>> = Mutex::new(EventBus::new());
+
+pub type SubscriptionId = u64;
+
+/// Subscriptions of callbacks `C` to event names.
+#[derive(Debug)]
+pub struct EventBus<C> {
+    next_id: SubscriptionId,
+    subscriptions: Vec<Subscription<C>>,
+    /// Retained events with their payload, in publish order.
+    retained: Vec<(String, Vec<u8>)>,
+}
+
+#[derive(Debug)]
+struct Subscription<C> {
+    id: SubscriptionId,
+    event: String,
+    /// Owner key, e.g. the module handle of a plugin.
+    owner: usize,
+    callback: C,
+}
+
+impl<C: Clone> EventBus<C> {
+    pub const fn new() -> Self {
+        Self {
+            next_id: 1,
+            subscriptions: Vec::new(),
+            retained: Vec::new(),
+        }
+    }
+
+    /// Subscribe `callback` to `event`. Ids are never reused, 0 is never returned.
+    pub fn subscribe(&mut self, event: &str, owner: usize, callback: C) -> SubscriptionId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.subscriptions.push(Subscription {
+            id,
+            event: event.to_string(),
+            owner,
+            callback,
+        });
+
+        id
+    }
+
+    /// Returns `false` if the subscription does not exist.
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let len = self.subscriptions.len();
+        self.subscriptions.retain(|s| s.id != id);
+
+        self.subscriptions.len() != len
+    }
+
+    /// Remove all subscriptions of `owner`, returns how many were removed.
+    pub fn unsubscribe_owner(&mut self, owner: usize) -> usize {
+        let len = self.subscriptions.len();
+        self.subscriptions.retain(|s| s.owner != owner);
+
+        len - self.subscriptions.len()
+    }
+
+    /// Callbacks subscribed to `event` with their owner, in subscription order.
+    ///
+    /// Returned as a copy, so callbacks can be called without holding the bus, and may
+    /// subscribe or publish themselves.
+    pub fn subscribers(&self, event: &str) -> Vec<(usize, C)> {
+        self.subscriptions
+            .iter()
+            .filter(|s| s.event == event)
+            .map(|s| (s.owner, s.callback.clone()))
+            .collect()
+    }
+
+    /// Keep `payload` of `event`, to replay it to later subscribers.
+    pub fn retain(&mut self, event: &str, payload: &[u8]) {
+        self.retained.push((event.to_string(), payload.to_vec()));
+    }
+
+    /// Payloads of `event` retained so far, in publish order.
+    pub fn retained(&self, event: &str) -> Vec<Vec<u8>> {
+        self.retained
+            .iter()
+            .filter(|(name, _)| name == event)
+            .map(|(_, payload)| payload.clone())
+            .collect()
+    }
+}
+
+impl<C: Clone> Default for EventBus<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Event callback of a plugin.
+///
+/// `fn(name, name_len, payload, payload_len, userdata)`
+pub type EventCallbackFunc =
+    unsafe extern "C-unwind" fn(*const u8, usize, *const u8, usize, *mut c_void);
+
+#[derive(Debug, Clone, Copy)]
+pub struct EventCallback {
+    pub func: EventCallbackFunc,
+    pub userdata: *mut c_void,
+}
+
+unsafe impl Send for EventCallback {}
+
+/// Subscribe `callback` to `event`.
+///
+/// Retained payloads of `event` are replayed to `callback` before this function returns.
+pub fn subscribe(event: &str, owner: usize, callback: EventCallback) -> SubscriptionId {
+    let (id, retained) = {
+        let mut bus = EVENT_BUS.lock().unwrap();
+        (bus.subscribe(event, owner, callback), bus.retained(event))
+    };
+
+    for payload in retained {
+        call(event, &payload, callback);
+    }
+
+    id
+}
+
+pub fn unsubscribe(id: SubscriptionId) -> bool {
+    EVENT_BUS.lock().unwrap().unsubscribe(id)
+}
+
+/// Remove all subscriptions with callbacks in the module `owner`.
+///
+/// Callbacks of `owner` being called by [`publish`] may still be running, wait for them with
+/// [`in_flight::wait_idle`] before freeing the module.
+pub fn unsubscribe_owner(owner: usize) -> usize {
+    EVENT_BUS.lock().unwrap().unsubscribe_owner(owner)
+}
+
+/// Call the subscribers of `event` with `payload`.
+///
+/// A panicking subscriber does not prevent the others from receiving the event. The owners of
+/// the subscribers are kept loaded until their callback returns.
+pub fn publish(event: &str, payload: &[u8]) {
+    publish_impl(event, payload, false)
+}
+
+/// Like [`publish`], and replay the event to later subscribers, see [`subscribe`].
+///
+/// For events published once, before plugins are loaded.
+pub fn publish_retained(event: &str, payload: &[u8]) {
+    publish_impl(event, payload, true)
+}
+
+fn publish_impl(event: &str, payload: &[u8], retain: bool) {
+    // retained under the same lock, so each subscriber receives the event exactly once
+    let subscribers = {
+        let mut bus = EVENT_BUS.lock().unwrap();
+        if retain {
+            bus.retain(event, payload);
+        }
+        bus.subscribers(event)
+            .into_iter()
+            .map(|(owner, callback)| (in_flight::enter(owner), callback))
+            .collect::<Vec<_>>()
+    };
+
+    for (_in_flight, callback) in subscribers {
+        call(event, payload, callback);
+    }
+}
+
+fn call(event: &str, payload: &[u8], callback: EventCallback) {
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| unsafe {
+        (callback.func)(
+            event.as_ptr(),
+            event.len(),
+            payload.as_ptr(),
+            payload.len(),
+            callback.userdata,
+        )
+    }));
+    if result.is_err() {
+        error!("Event subscriber of {} panicked.", event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscribe_and_unsubscribe() {
+        let mut bus = EventBus::new();
+        let a = bus.subscribe("chat", 1, "a");
+        let b = bus.subscribe("chat", 2, "b");
+        let c = bus.subscribe("quest", 1, "c");
+
+        assert_ne!(a, 0);
+        assert_eq!(bus.subscribers("chat"), vec![(1, "a"), (2, "b")]);
+        assert_eq!(bus.subscribers("quest"), vec![(1, "c")]);
+        assert!(bus.subscribers("other").is_empty());
+
+        assert!(bus.unsubscribe(b));
+        assert!(!bus.unsubscribe(b));
+        assert_eq!(bus.subscribers("chat"), vec![(1, "a")]);
+
+        // ids are not reused
+        let d = bus.subscribe("chat", 2, "d");
+        assert!(![a, b, c].contains(&d));
+        assert_eq!(bus.subscribers("chat"), vec![(1, "a"), (2, "d")]);
+
+        // owner unloaded
+        assert_eq!(bus.unsubscribe_owner(1), 2);
+        assert_eq!(bus.subscribers("chat"), vec![(2, "d")]);
+        assert!(bus.subscribers("quest").is_empty());
+        assert!(!bus.unsubscribe(c));
+    }
+
+    #[test]
+    fn publish_to_subscribers() {
+        unsafe extern "C-unwind" fn record(
+            name: *const u8,
+            name_len: usize,
+            payload: *const u8,
+            payload_len: usize,
+            userdata: *mut c_void,
+        ) {
+            let received = &mut *(userdata as *mut Vec<(String, Vec<u8>)>);
+            let name = std::slice::from_raw_parts(name, name_len);
+            let payload = std::slice::from_raw_parts(payload, payload_len);
+            received.push((String::from_utf8(name.to_vec()).unwrap(), payload.to_vec()));
+        }
+
+        unsafe extern "C-unwind" fn panic(
+            _: *const u8,
+            _: usize,
+            _: *const u8,
+            _: usize,
+            _: *mut c_void,
+        ) {
+            panic!("subscriber failed");
+        }
+
+        // names unique to this test, the bus is global
+        let mut received: Vec<(String, Vec<u8>)> = Vec::new();
+        let userdata = &mut received as *mut _ as *mut c_void;
+        let owner = usize::MAX;
+        subscribe(
+            "test:panic",
+            owner,
+            EventCallback {
+                func: panic,
+                userdata: std::ptr::null_mut(),
+            },
+        );
+        let id = subscribe(
+            "test:panic",
+            owner,
+            EventCallback {
+                func: record,
+                userdata,
+            },
+        );
+        subscribe(
+            "test:other",
+            owner,
+            EventCallback {
+                func: record,
+                userdata,
+            },
+        );
+
+        publish("test:panic", b"payload");
+        assert!(unsubscribe(id));
+        publish("test:panic", b"ignored");
+        assert_eq!(unsubscribe_owner(owner), 2);
+        publish("test:other", b"ignored");
+
+        assert_eq!(
+            received,
+            vec![("test:panic".to_string(), b"payload".to_vec())]
+        );
+    }
+
+    #[test]
+    fn retain_payloads() {
+        let mut bus = EventBus::<&str>::new();
+        bus.retain("ctor", b"a");
+        bus.retain("singleton", b"b");
+        bus.retain("singleton", b"c");
+
+        assert_eq!(bus.retained("ctor"), vec![b"a".to_vec()]);
+        assert_eq!(
+            bus.retained("singleton"),
+            vec![b"b".to_vec(), b"c".to_vec()]
+        );
+        assert!(bus.retained("chat").is_empty());
+    }
+
+    #[test]
+    fn replay_retained_to_late_subscribers() {
+        unsafe extern "C-unwind" fn record(
+            name: *const u8,
+            name_len: usize,
+            payload: *const u8,
+            payload_len: usize,
+            userdata: *mut c_void,
+        ) {
+            let received = &mut *(userdata as *mut Vec<(String, Vec<u8>)>);
+            let name = std::slice::from_raw_parts(name, name_len);
+            let payload = std::slice::from_raw_parts(payload, payload_len);
+            received.push((String::from_utf8(name.to_vec()).unwrap(), payload.to_vec()));
+        }
+
+        // published by the loader before plugins are loaded
+        publish_retained("test:retained", b"first");
+        publish_retained("test:retained", b"second");
+        publish("test:not_retained", b"lost");
+
+        // a plugin subscribing while it is loaded
+        let mut received: Vec<(String, Vec<u8>)> = Vec::new();
+        let userdata = &mut received as *mut _ as *mut c_void;
+        let owner = usize::MAX - 2;
+        for event in ["test:retained", "test:not_retained"] {
+            subscribe(
+                event,
+                owner,
+                EventCallback {
+                    func: record,
+                    userdata,
+                },
+            );
+        }
+        assert_eq!(
+            received,
+            vec![
+                ("test:retained".to_string(), b"first".to_vec()),
+                ("test:retained".to_string(), b"second".to_vec()),
+            ]
+        );
+
+        // published once to current subscribers
+        received.clear();
+        publish_retained("test:retained", b"third");
+        assert_eq!(
+            received,
+            vec![("test:retained".to_string(), b"third".to_vec())]
+        );
+        assert_eq!(unsubscribe_owner(owner), 2);
+    }
+
+    #[test]
+    fn unload_waits_for_publish() {
+        use std::sync::{
+            atomic::{AtomicBool, Ordering},
+            Barrier,
+        };
+
+        // entered, then released by the test
+        unsafe extern "C-unwind" fn block(
+            _: *const u8,
+            _: usize,
+            _: *const u8,
+            _: usize,
+            userdata: *mut c_void,
+        ) {
+            let barriers = &*(userdata as *const [Barrier; 2]);
+            barriers[0].wait();
+            barriers[1].wait();
+        }
+
+        let barriers = [Barrier::new(2), Barrier::new(2)];
+        let userdata = &barriers as *const _ as usize;
+        let owner = usize::MAX - 1;
+        subscribe(
+            "test:in_flight",
+            owner,
+            EventCallback {
+                func: block,
+                userdata: userdata as *mut c_void,
+            },
+        );
+
+        let freed = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            scope.spawn(|| publish("test:in_flight", b""));
+            barriers[0].wait();
+
+            // unload the owner while its callback runs
+            assert_eq!(unsubscribe_owner(owner), 1);
+            let unloader = scope.spawn(|| {
+                in_flight::wait_idle(owner);
+                freed.store(true, Ordering::SeqCst);
+            });
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            assert!(!freed.load(Ordering::SeqCst));
+
+            barriers[1].wait();
+            unloader.join().unwrap();
+        });
+        assert!(freed.load(Ordering::SeqCst));
+    }
+}
@@ -7,6 +7,7 @@ pub enum Code {
     InvalidUtf8String = 1,
     NotFound = 2,
     BadPattern = 3,
+    BufferTooSmall = 4,
 }
 
 /// Get address record by name.
@@ -30,11 +31,32 @@ pub extern "C" fn GetAddress(name: *const u8, len: usize, result: &mut usize) ->
     Code::Ok as i32
 }
 
+/// Get which pattern of an address record matched.
+///
+/// result: 0 for the primary pattern, n for the n-th fallback pattern.
+#[no_mangle]
+pub extern "C" fn GetAddressPatternIndex(name: *const u8, len: usize, result: &mut usize) -> i32 {
+    unsafe {
+        let buf = std::slice::from_raw_parts(name, len);
+        let Ok(name) = std::str::from_utf8(buf) else {
+            return Code::InvalidUtf8String as i32;
+        };
+
+        let Ok(index) = AddressRepository::get_alternative(name) else {
+            return Code::NotFound as i32;
+        };
+
+        *result = index;
+    }
+
+    Code::Ok as i32
+}
+
 /// Scan for the first pattern match.
 ///
 /// pattern: Space seperated hex bytes string.
 ///
-/// Example: "FF 00 ?? 00 ??"
+/// Example: "FF 00 ?? 00 ??", "48 8B 05 ^ ?? ?? ?? ??"
 #[no_mangle]
 pub extern "C" fn PatternScanFirst(pattern: *const u8, len: usize, result: &mut usize) -> i32 {
     unsafe {
@@ -68,7 +90,9 @@ pub extern "C" fn PatternScanFirst(pattern: *const u8, len: usize, result: &mut
 ///
 /// Example: "FF 00 ?? 00 ??"
 ///
-/// Wildcards allowed: ? ?? * **
+/// Wildcards allowed: ? ?? * ** and nibble wildcards like 4? or ?B.
+///
+/// Bit masks: `40[F0]`. Reference marker: `^` or `&` sets the result address.
 #[no_mangle]
 pub extern "C" fn PatternScanAll(
     pattern: *const u8,
@@ -0,0 +1,94 @@
+use shared::export::core_extension::CoreFunctionCode;
+
+use crate::core_extension::{self, CoreAPI};
+
+use super::Code;
+
+/// Get a function registered by a core extension, for plugins.
+///
+/// signature: Optional, see `FunctionDescriptor`. Null accepts any function, otherwise untyped
+/// functions never match.
+///
+/// version: Interface version, 0 accepts any version.
+///
+/// Returns a `CoreFunctionCode`.
+#[no_mangle]
+pub extern "C" fn GetCoreFunction(
+    name: *const u8,
+    len: usize,
+    signature: *const u8,
+    signature_len: usize,
+    version: u32,
+    result: &mut usize,
+) -> i32 {
+    let name = unsafe { std::slice::from_raw_parts(name, len) };
+    let Ok(name) = std::str::from_utf8(name) else {
+        return CoreFunctionCode::InvalidUtf8String as i32;
+    };
+    let signature: &[u8] = if signature.is_null() {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(signature, signature_len) }
+    };
+    let Ok(signature) = std::str::from_utf8(signature) else {
+        return CoreFunctionCode::InvalidUtf8String as i32;
+    };
+    let signature = Some(signature).filter(|s| !s.is_empty());
+
+    match CoreAPI::instance().get_typed_function(name, signature, version) {
+        Ok(function) => {
+            *result = function as usize;
+            CoreFunctionCode::Ok as i32
+        }
+        Err(e) => core_extension::error_code(&e) as i32,
+    }
+}
+
+/// Get an interface table registered by a core extension, of at least `min_version`, for
+/// plugins.
+///
+/// The caller should also check the table size, see `InterfaceHeader`.
+///
+/// Returns a `CoreFunctionCode`.
+#[no_mangle]
+pub extern "C" fn QueryInterface(
+    name: *const u8,
+    len: usize,
+    min_version: u32,
+    result: &mut usize,
+) -> i32 {
+    let name = unsafe { std::slice::from_raw_parts(name, len) };
+    let Ok(name) = std::str::from_utf8(name) else {
+        return CoreFunctionCode::InvalidUtf8String as i32;
+    };
+
+    match CoreAPI::instance().query_interface(name, min_version) {
+        Ok(table) => {
+            *result = table as usize;
+            CoreFunctionCode::Ok as i32
+        }
+        Err(e) => core_extension::error_code(&e) as i32,
+    }
+}
+
+/// List the functions and interfaces registered by core extensions, as a UTF-8 JSON string.
+///
+/// `{"functions": [{name, provider, signature, version}], "interfaces": [{name, provider, version}]}`,
+/// sorted by name. Buffer semantics as [GetLoadReport](super::GetLoadReport).
+#[no_mangle]
+pub extern "C" fn ListCoreFunctions(buf: *mut u8, cap: usize, len: &mut usize) -> i32 {
+    let Ok(json) = serde_json::to_string(&CoreAPI::instance().list()) else {
+        return Code::NotFound as i32;
+    };
+
+    *len = json.len();
+    if json.len() > cap {
+        return Code::BufferTooSmall as i32;
+    }
+
+    unsafe {
+        std::ptr::copy_nonoverlapping(json.as_ptr(), buf, json.len());
+    }
+
+    Code::Ok as i32
+}
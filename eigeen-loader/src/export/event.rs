@@ -0,0 +1,84 @@
+use std::ffi::c_void;
+
+use crate::{
+    event_bus::{self, EventCallback, EventCallbackFunc},
+    utility,
+};
+
+use super::Code;
+
+/// Subscribe `callback` to the event `name`.
+///
+/// `callback(name, name_len, payload, payload_len, userdata)` is called for each event
+/// published with this name, from the publishing thread. The subscription is removed
+/// when the plugin owning the callback is unloaded.
+///
+/// Retained built-in events are replayed to `callback` before this function returns.
+///
+/// result: Subscription id, for [UnsubscribeEvent].
+#[no_mangle]
+pub extern "C" fn SubscribeEvent(
+    name: *const u8,
+    len: usize,
+    callback: EventCallbackFunc,
+    userdata: *mut c_void,
+    result: &mut u64,
+) -> i32 {
+    unsafe {
+        let buf = std::slice::from_raw_parts(name, len);
+        let Ok(name) = std::str::from_utf8(buf) else {
+            return Code::InvalidUtf8String as i32;
+        };
+
+        let owner = utility::windows::module_from_address(callback as *const c_void)
+            .map_or(0, |hmodule| hmodule.0 as usize);
+
+        *result = event_bus::subscribe(
+            name,
+            owner,
+            EventCallback {
+                func: callback,
+                userdata,
+            },
+        );
+    }
+
+    Code::Ok as i32
+}
+
+/// Remove a subscription of [SubscribeEvent].
+#[no_mangle]
+pub extern "C" fn UnsubscribeEvent(id: u64) -> i32 {
+    if !event_bus::unsubscribe(id) {
+        return Code::NotFound as i32;
+    }
+
+    Code::Ok as i32
+}
+
+/// Publish the event `name` with a byte payload to its subscribers.
+///
+/// Subscribers are called before this function returns.
+#[no_mangle]
+pub extern "C" fn PublishEvent(
+    name: *const u8,
+    len: usize,
+    payload: *const u8,
+    payload_len: usize,
+) -> i32 {
+    unsafe {
+        let buf = std::slice::from_raw_parts(name, len);
+        let Ok(name) = std::str::from_utf8(buf) else {
+            return Code::InvalidUtf8String as i32;
+        };
+        let payload: &[u8] = if payload_len == 0 {
+            &[]
+        } else {
+            std::slice::from_raw_parts(payload, payload_len)
+        };
+
+        event_bus::publish(name, payload);
+    }
+
+    Code::Ok as i32
+}
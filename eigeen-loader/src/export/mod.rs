@@ -2,9 +2,15 @@
 #![allow(clippy::not_unsafe_ptr_arg_deref)]
 
 mod address;
+mod core_extension;
+mod event;
 mod game;
 mod logging;
+mod plugin;
 
 pub use address::*;
+pub use core_extension::*;
+pub use event::*;
 pub use game::*;
 pub use logging::*;
+pub use plugin::*;
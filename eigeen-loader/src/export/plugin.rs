@@ -0,0 +1,74 @@
+use serde::Serialize;
+
+use crate::{core_extension::CoreAPI, plugin::PluginLoader, report::LoadReport};
+
+use super::Code;
+
+#[derive(Serialize)]
+struct Report<'a> {
+    core_extensions: &'a LoadReport,
+    plugins: &'a LoadReport,
+}
+
+/// Get the load report of core extensions and plugins, as a UTF-8 JSON string.
+///
+/// `len` is set to the length of the report. If it is larger than `cap`, nothing is
+/// written and [`Code::BufferTooSmall`] is returned, call again with a larger buffer.
+///
+/// Available at any time, including while plugins are being loaded.
+#[no_mangle]
+pub extern "C" fn GetLoadReport(buf: *mut u8, cap: usize, len: &mut usize) -> i32 {
+    let report = Report {
+        core_extensions: &CoreAPI::instance().report(),
+        plugins: &PluginLoader::report(),
+    };
+    let Ok(json) = serde_json::to_string(&report) else {
+        return Code::NotFound as i32;
+    };
+
+    *len = json.len();
+    if json.len() > cap {
+        return Code::BufferTooSmall as i32;
+    }
+
+    unsafe {
+        std::ptr::copy_nonoverlapping(json.as_ptr(), buf, json.len());
+    }
+
+    Code::Ok as i32
+}
+
+/// Get the data directory of the plugin `name`, as a UTF-8 absolute path.
+///
+/// The directory is created if missing. Buffer semantics as [GetLoadReport].
+#[no_mangle]
+pub extern "C" fn GetPluginDataDir(
+    name: *const u8,
+    len: usize,
+    buf: *mut u8,
+    cap: usize,
+    result_len: &mut usize,
+) -> i32 {
+    let name = unsafe { std::slice::from_raw_parts(name, len) };
+    let Ok(name) = std::str::from_utf8(name) else {
+        return Code::InvalidUtf8String as i32;
+    };
+
+    let Ok(dir) = PluginLoader::data_dir(name) else {
+        return Code::NotFound as i32;
+    };
+    let Some(dir) = dir.to_str() else {
+        return Code::InvalidUtf8String as i32;
+    };
+
+    *result_len = dir.len();
+    if dir.len() > cap {
+        return Code::BufferTooSmall as i32;
+    }
+
+    unsafe {
+        std::ptr::copy_nonoverlapping(dir.as_ptr(), buf, dir.len());
+    }
+
+    Code::Ok as i32
+}
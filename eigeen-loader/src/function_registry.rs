@@ -0,0 +1,730 @@
+//! Functions and interface tables registered by core extensions, by name.
+//!
+//! Functions may be registered with a signature and an interface version, which callers
+//! can check before transmuting the function pointer. Interface tables carry their own
+//! version in their [`InterfaceHeader`].
+//!
+//! The two versions are matched differently. A function version must equal the requested
+//! one: a single function has no layout to extend, so any new version may change its
+//! behavior behind the same signature. An interface version must be at least the requested
+//! one: tables are append-only, so a newer table is a valid older one.
+//!
+//! Registrations record their provider, the core extension which registered them. When
+//! another provider registers a name again, the [`ConflictPolicy`] applies.
+//!
+//! The registry is shared between threads. Lookups only take read locks, registrations
+//! are rare and mostly happen while core extensions are loaded.
+
+use std::{collections::HashMap, ffi::c_void, sync::RwLock};
+
+use serde::{Deserialize, Serialize};
+use shared::export::core_extension::{normalize_signature, InterfaceHeader};
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Default)]
+pub struct FunctionRegistry {
+    policy: ConflictPolicy,
+    functions: RwLock<HashMap<String, RegisteredFunction>>,
+    interfaces: RwLock<HashMap<String, RegisteredInterface>>,
+}
+
+/// Applied when a name is registered by another provider.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Refuse the new registration with an error.
+    Reject,
+    /// Keep the existing registration, the new one is ignored.
+    FirstWins,
+    /// Replace the existing registration.
+    #[default]
+    LastWins,
+    /// Keep the existing registration, the new one is registered as `provider::name`.
+    Namespaced,
+}
+
+/// How a registration was applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Registration {
+    /// Registered under its name, which was free or registered by the same provider.
+    Registered,
+    /// Registered under its name, replacing the registration of `previous`.
+    Replaced { previous: String },
+    /// Not registered, `existing` registered the name first.
+    Ignored { existing: String },
+    /// Registered under this namespaced name, as another provider registered its name first.
+    Namespaced(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct RegisteredFunction {
+    pub function: *const c_void,
+    /// Handle of the core extension which registered the function, 0 if unknown.
+    pub owner: usize,
+    /// Name of the core extension which registered the function.
+    pub provider: String,
+    /// Normalized signature, `None` for untyped registrations.
+    pub signature: Option<String>,
+    /// Interface version, 0 if unversioned.
+    pub version: u32,
+}
+
+// Addresses of functions in loaded modules, never dereferenced by the registry.
+unsafe impl Send for RegisteredFunction {}
+unsafe impl Sync for RegisteredFunction {}
+
+impl RegisteredFunction {
+    pub fn new(function: *const c_void, owner: usize, provider: &str) -> Self {
+        Self {
+            function,
+            owner,
+            provider: provider.to_string(),
+            signature: None,
+            version: 0,
+        }
+    }
+
+    pub fn with_descriptor(mut self, signature: Option<&str>, version: u32) -> Self {
+        self.signature = signature.map(normalize_signature);
+        self.version = version;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RegisteredInterface {
+    pub table: *const InterfaceHeader,
+    /// Handle of the core extension which registered the table, 0 if unknown.
+    pub owner: usize,
+    /// Name of the core extension which registered the table.
+    pub provider: String,
+    /// Version read from the table header.
+    pub version: u32,
+}
+
+// Tables are immutable once registered, and only read when registered.
+unsafe impl Send for RegisteredInterface {}
+unsafe impl Sync for RegisteredInterface {}
+
+/// Registered functions and interfaces, sorted by name.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Listing {
+    pub functions: Vec<FunctionInfo>,
+    pub interfaces: Vec<InterfaceInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FunctionInfo {
+    pub name: String,
+    pub provider: String,
+    pub signature: Option<String>,
+    pub version: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InterfaceInfo {
+    pub name: String,
+    pub provider: String,
+    pub version: u32,
+}
+
+/// Registrations with a provider.
+trait Provided {
+    fn owner(&self) -> usize;
+
+    fn provider(&self) -> &str;
+
+    /// Unknown providers are never the same, so the conflict policy applies to them.
+    fn is_same_provider(&self, other: &Self) -> bool {
+        self.owner() != 0 && self.owner() == other.owner() && self.provider() == other.provider()
+    }
+}
+
+impl Provided for RegisteredFunction {
+    fn owner(&self) -> usize {
+        self.owner
+    }
+
+    fn provider(&self) -> &str {
+        &self.provider
+    }
+}
+
+impl Provided for RegisteredInterface {
+    fn owner(&self) -> usize {
+        self.owner
+    }
+
+    fn provider(&self) -> &str {
+        &self.provider
+    }
+}
+
+impl FunctionRegistry {
+    pub fn new(policy: ConflictPolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    pub fn policy(&self) -> ConflictPolicy {
+        self.policy
+    }
+
+    /// Register a function, applying the conflict policy if another provider registered
+    /// the name.
+    pub fn register(&self, name: &str, function: RegisteredFunction) -> Result<Registration> {
+        let mut functions = self.functions.write().unwrap();
+        self.insert(&mut functions, name, function)
+    }
+
+    pub fn get(&self, name: &str) -> Option<RegisteredFunction> {
+        self.functions.read().unwrap().get(name).cloned()
+    }
+
+    /// Get a function, checking its signature and interface version.
+    ///
+    /// `signature` `None` and `version` 0 accept any function. Otherwise the version must
+    /// match exactly, see the module docs. A requested signature never matches an untyped
+    /// registration.
+    pub fn get_checked(
+        &self,
+        name: &str,
+        signature: Option<&str>,
+        version: u32,
+    ) -> Result<*const c_void> {
+        let functions = self.functions.read().unwrap();
+        let function = functions
+            .get(name)
+            .ok_or_else(|| Error::CoreFunctionNotFound(name.to_string()))?;
+
+        if let Some(requested) = signature {
+            let requested = normalize_signature(requested);
+            if function.signature.as_ref() != Some(&requested) {
+                return Err(Error::CoreFunctionSignatureMismatch {
+                    name: name.to_string(),
+                    requested,
+                    registered: function
+                        .signature
+                        .clone()
+                        .unwrap_or_else(|| "unknown".to_string()),
+                });
+            }
+        }
+        if version != 0 && function.version != version {
+            return Err(Error::CoreFunctionVersionMismatch {
+                name: name.to_string(),
+                requested: version,
+                registered: function.version,
+            });
+        }
+
+        Ok(function.function)
+    }
+
+    /// Register an interface table, applying the conflict policy if another provider
+    /// registered the name.
+    ///
+    /// # Safety
+    ///
+    /// `table` must be null, or point to a table starting with an [`InterfaceHeader`].
+    pub unsafe fn register_interface(
+        &self,
+        name: &str,
+        table: *const InterfaceHeader,
+        owner: usize,
+        provider: &str,
+    ) -> Result<Registration> {
+        let header = table
+            .as_ref()
+            .filter(|header| header.size as usize >= std::mem::size_of::<InterfaceHeader>())
+            .ok_or_else(|| Error::InvalidInterface(name.to_string()))?;
+        let interface = RegisteredInterface {
+            table,
+            owner,
+            provider: provider.to_string(),
+            version: header.version,
+        };
+
+        let mut interfaces = self.interfaces.write().unwrap();
+        self.insert(&mut interfaces, name, interface)
+    }
+
+    /// Get an interface table of at least `min_version`, see the module docs.
+    pub fn query_interface(&self, name: &str, min_version: u32) -> Result<*const InterfaceHeader> {
+        let interfaces = self.interfaces.read().unwrap();
+        let interface = interfaces
+            .get(name)
+            .ok_or_else(|| Error::InterfaceNotFound(name.to_string()))?;
+        if interface.version < min_version {
+            return Err(Error::InterfaceVersionTooOld {
+                name: name.to_string(),
+                required: min_version,
+                registered: interface.version,
+            });
+        }
+
+        Ok(interface.table)
+    }
+
+    /// Who provides what.
+    pub fn list(&self) -> Listing {
+        let mut functions = self
+            .functions
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, f)| FunctionInfo {
+                name: name.clone(),
+                provider: f.provider.clone(),
+                signature: f.signature.clone(),
+                version: f.version,
+            })
+            .collect::<Vec<_>>();
+        functions.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut interfaces = self
+            .interfaces
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, i)| InterfaceInfo {
+                name: name.clone(),
+                provider: i.provider.clone(),
+                version: i.version,
+            })
+            .collect::<Vec<_>>();
+        interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Listing {
+            functions,
+            interfaces,
+        }
+    }
+
+    fn insert<T: Provided>(
+        &self,
+        entries: &mut HashMap<String, T>,
+        name: &str,
+        entry: T,
+    ) -> Result<Registration> {
+        let Some(existing) = entries.get(name) else {
+            entries.insert(name.to_string(), entry);
+            return Ok(Registration::Registered);
+        };
+        if existing.is_same_provider(&entry) {
+            entries.insert(name.to_string(), entry);
+            return Ok(Registration::Registered);
+        }
+
+        let existing = existing.provider().to_string();
+        match self.policy {
+            ConflictPolicy::Reject => Err(Error::CoreFunctionConflict {
+                name: name.to_string(),
+                provider: entry.provider().to_string(),
+                existing,
+            }),
+            ConflictPolicy::FirstWins => Ok(Registration::Ignored { existing }),
+            ConflictPolicy::LastWins => {
+                entries.insert(name.to_string(), entry);
+                Ok(Registration::Replaced { previous: existing })
+            }
+            ConflictPolicy::Namespaced => {
+                let namespaced = format!("{}::{}", entry.provider(), name);
+                entries.insert(namespaced.clone(), entry);
+                Ok(Registration::Namespaced(namespaced))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_signature_and_version() {
+        let function = 0x1000 as *const c_void;
+        let registry = FunctionRegistry::default();
+        registry
+            .register(
+                "draw",
+                RegisteredFunction::new(function, 1, "renderer")
+                    .with_descriptor(Some("fn(ptr, u32) -> void"), 2),
+            )
+            .unwrap();
+        registry
+            .register("legacy", RegisteredFunction::new(function, 2, "legacy"))
+            .unwrap();
+
+        assert_eq!(registry.get_checked("draw", None, 0).unwrap(), function);
+        assert_eq!(
+            registry
+                .get_checked("draw", Some("fn(ptr,u32) -> void"), 2)
+                .unwrap(),
+            function
+        );
+        assert!(matches!(
+            registry.get_checked("draw", Some("fn(ptr) -> void"), 0),
+            Err(Error::CoreFunctionSignatureMismatch { .. })
+        ));
+        assert!(matches!(
+            registry.get_checked("draw", None, 3),
+            Err(Error::CoreFunctionVersionMismatch {
+                requested: 3,
+                registered: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            registry.get_checked("missing", None, 0),
+            Err(Error::CoreFunctionNotFound(_))
+        ));
+
+        // untyped registrations only satisfy untyped requests
+        assert!(registry.get_checked("legacy", None, 0).is_ok());
+        assert!(matches!(
+            registry.get_checked("legacy", Some("fn() -> i32"), 0),
+            Err(Error::CoreFunctionSignatureMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn unknown_providers_conflict() {
+        let registry = FunctionRegistry::new(ConflictPolicy::Reject);
+        registry
+            .register("draw", RegisteredFunction::new(0x1000 as _, 0, "unknown"))
+            .unwrap();
+
+        assert!(matches!(
+            registry.register("draw", RegisteredFunction::new(0x2000 as _, 0, "unknown")),
+            Err(Error::CoreFunctionConflict { .. })
+        ));
+        assert_eq!(
+            registry.get("draw").unwrap().function,
+            0x1000 as *const c_void
+        );
+
+        // a known provider may replace its own registration
+        registry
+            .register("fill", RegisteredFunction::new(0x1000 as _, 1, "renderer"))
+            .unwrap();
+        assert_eq!(
+            registry
+                .register("fill", RegisteredFunction::new(0x2000 as _, 1, "renderer"))
+                .unwrap(),
+            Registration::Registered
+        );
+        assert_eq!(
+            registry.get("fill").unwrap().function,
+            0x2000 as *const c_void
+        );
+    }
+
+    #[test]
+    fn apply_conflict_policies() {
+        let first = RegisteredFunction::new(0x1000 as _, 1, "first");
+        let second = RegisteredFunction::new(0x2000 as _, 2, "second");
+        let registered = |registry: &FunctionRegistry, name: &str| {
+            registry
+                .get(name)
+                .map(|f| (f.function as usize, f.provider))
+        };
+
+        for policy in [
+            ConflictPolicy::Reject,
+            ConflictPolicy::FirstWins,
+            ConflictPolicy::LastWins,
+            ConflictPolicy::Namespaced,
+        ] {
+            let registry = FunctionRegistry::new(policy);
+            assert_eq!(
+                registry.register("draw", first.clone()).unwrap(),
+                Registration::Registered
+            );
+            // the same provider can always register again
+            assert_eq!(
+                registry.register("draw", first.clone()).unwrap(),
+                Registration::Registered
+            );
+
+            let result = registry.register("draw", second.clone());
+            match policy {
+                ConflictPolicy::Reject => {
+                    assert!(matches!(
+                        result,
+                        Err(Error::CoreFunctionConflict { ref existing, .. }) if existing == "first"
+                    ));
+                    assert_eq!(
+                        registered(&registry, "draw"),
+                        Some((0x1000, "first".to_string()))
+                    );
+                }
+                ConflictPolicy::FirstWins => {
+                    assert_eq!(
+                        result.unwrap(),
+                        Registration::Ignored {
+                            existing: "first".to_string()
+                        }
+                    );
+                    assert_eq!(
+                        registered(&registry, "draw"),
+                        Some((0x1000, "first".to_string()))
+                    );
+                }
+                ConflictPolicy::LastWins => {
+                    assert_eq!(
+                        result.unwrap(),
+                        Registration::Replaced {
+                            previous: "first".to_string()
+                        }
+                    );
+                    assert_eq!(
+                        registered(&registry, "draw"),
+                        Some((0x2000, "second".to_string()))
+                    );
+                }
+                ConflictPolicy::Namespaced => {
+                    assert_eq!(
+                        result.unwrap(),
+                        Registration::Namespaced("second::draw".to_string())
+                    );
+                    assert_eq!(
+                        registered(&registry, "draw"),
+                        Some((0x1000, "first".to_string()))
+                    );
+                    assert_eq!(
+                        registered(&registry, "second::draw"),
+                        Some((0x2000, "second".to_string()))
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn list_providers() {
+        let registry = FunctionRegistry::new(ConflictPolicy::Namespaced);
+        let table = InterfaceHeader {
+            size: 8,
+            version: 3,
+        };
+        registry
+            .register(
+                "present",
+                RegisteredFunction::new(0x1000 as _, 1, "d3d")
+                    .with_descriptor(Some("fn() -> i32"), 1),
+            )
+            .unwrap();
+        registry
+            .register("draw", RegisteredFunction::new(0x2000 as _, 1, "d3d"))
+            .unwrap();
+        registry
+            .register("draw", RegisteredFunction::new(0x3000 as _, 2, "overlay"))
+            .unwrap();
+        unsafe {
+            registry
+                .register_interface("d3d:renderer", &table, 1, "d3d")
+                .unwrap();
+        }
+
+        let listing = registry.list();
+        let functions = listing
+            .functions
+            .iter()
+            .map(|f| (f.name.as_str(), f.provider.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            functions,
+            vec![
+                ("draw", "d3d"),
+                ("overlay::draw", "overlay"),
+                ("present", "d3d")
+            ]
+        );
+        assert_eq!(listing.functions[2].signature.as_deref(), Some("fn()->i32"));
+        assert_eq!(
+            listing.interfaces,
+            vec![InterfaceInfo {
+                name: "d3d:renderer".to_string(),
+                provider: "d3d".to_string(),
+                version: 3,
+            }]
+        );
+
+        let json = serde_json::to_value(&listing).unwrap();
+        assert_eq!(json["functions"][2]["version"], 1);
+        assert_eq!(json["interfaces"][0]["provider"], "d3d");
+    }
+
+    #[test]
+    fn query_interfaces() {
+        let table = InterfaceHeader {
+            size: 32,
+            version: 2,
+        };
+        let invalid = InterfaceHeader {
+            size: 4,
+            version: 1,
+        };
+        let registry = FunctionRegistry::default();
+
+        unsafe {
+            registry
+                .register_interface("renderer", &table, 1, "renderer")
+                .unwrap();
+            assert!(matches!(
+                registry.register_interface("invalid", &invalid, 1, "renderer"),
+                Err(Error::InvalidInterface(_))
+            ));
+            assert!(matches!(
+                registry.register_interface("null", std::ptr::null(), 1, "renderer"),
+                Err(Error::InvalidInterface(_))
+            ));
+        }
+
+        assert_eq!(
+            registry.query_interface("renderer", 1).unwrap(),
+            &table as *const _
+        );
+        assert!(registry.query_interface("renderer", 2).is_ok());
+        assert!(matches!(
+            registry.query_interface("renderer", 3),
+            Err(Error::InterfaceVersionTooOld {
+                required: 3,
+                registered: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            registry.query_interface("invalid", 0),
+            Err(Error::InterfaceNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn version_rules() {
+        let function = 0x1000 as *const c_void;
+        let table = InterfaceHeader {
+            size: 32,
+            version: 2,
+        };
+        let registry = FunctionRegistry::default();
+        registry
+            .register(
+                "draw",
+                RegisteredFunction::new(function, 1, "renderer").with_descriptor(None, 2),
+            )
+            .unwrap();
+        unsafe {
+            registry
+                .register_interface("renderer", &table, 1, "renderer")
+                .unwrap();
+        }
+
+        // functions: exact version, 0 accepts any
+        assert!(registry.get_checked("draw", None, 0).is_ok());
+        assert!(registry.get_checked("draw", None, 2).is_ok());
+        assert!(matches!(
+            registry.get_checked("draw", None, 1),
+            Err(Error::CoreFunctionVersionMismatch { .. })
+        ));
+        assert!(matches!(
+            registry.get_checked("draw", None, 3),
+            Err(Error::CoreFunctionVersionMismatch { .. })
+        ));
+
+        // interfaces: minimum version
+        assert!(registry.query_interface("renderer", 0).is_ok());
+        assert!(registry.query_interface("renderer", 1).is_ok());
+        assert!(registry.query_interface("renderer", 2).is_ok());
+        assert!(matches!(
+            registry.query_interface("renderer", 3),
+            Err(Error::InterfaceVersionTooOld { .. })
+        ));
+    }
+
+    #[test]
+    fn concurrent_register_and_lookup() {
+        const THREADS: usize = 8;
+        const FUNCTIONS: usize = 500;
+
+        let registry = FunctionRegistry::default();
+        let function = |thread: usize, i: usize| (thread * FUNCTIONS + i + 1) as *const c_void;
+
+        std::thread::scope(|scope| {
+            for thread in 0..THREADS {
+                let registry = &registry;
+                scope.spawn(move || {
+                    for i in 0..FUNCTIONS {
+                        let name = format!("fn_{thread}_{i}");
+                        registry
+                            .register(
+                                &name,
+                                RegisteredFunction::new(function(thread, i), thread, "stress")
+                                    .with_descriptor(Some("fn() -> i32"), 1),
+                            )
+                            .unwrap();
+                        assert_eq!(
+                            registry.get_checked(&name, Some("fn() -> i32"), 1).unwrap(),
+                            function(thread, i)
+                        );
+
+                        // functions of another thread, registered or not yet
+                        let other = (thread + 1) % THREADS;
+                        if let Ok(found) = registry.get_checked(&format!("fn_{other}_{i}"), None, 0)
+                        {
+                            assert_eq!(found, function(other, i));
+                        }
+                    }
+                });
+            }
+        });
+
+        assert_eq!(registry.list().functions.len(), THREADS * FUNCTIONS);
+    }
+
+    #[test]
+    fn concurrent_replace_while_reading() {
+        const READERS: usize = 8;
+        const WRITES: usize = 2000;
+
+        let registry = FunctionRegistry::default();
+        // addresses, as pointers are not Sync
+        let old = 0x1000;
+        let new = 0x2000;
+        registry
+            .register("shared", RegisteredFunction::new(old as _, 1, "shared"))
+            .unwrap();
+
+        std::thread::scope(|scope| {
+            for _ in 0..READERS {
+                scope.spawn(|| {
+                    for _ in 0..WRITES {
+                        // replaced functions are never missing, or mixed with the other version
+                        let found = registry.get("shared").unwrap();
+                        match found.version {
+                            0 => assert_eq!(found.function as usize, old),
+                            2 => assert_eq!(found.function as usize, new),
+                            version => panic!("unexpected version {version}"),
+                        }
+                    }
+                });
+            }
+
+            scope.spawn(|| {
+                for i in 0..WRITES {
+                    let function = if i % 2 == 0 {
+                        RegisteredFunction::new(new as _, 1, "shared")
+                            .with_descriptor(Some("fn() -> void"), 2)
+                    } else {
+                        RegisteredFunction::new(old as _, 1, "shared")
+                    };
+                    registry.register("shared", function).unwrap();
+                }
+            });
+        });
+    }
+}
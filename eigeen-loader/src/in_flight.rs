@@ -0,0 +1,145 @@
+//! Calls into plugin modules in progress.
+//!
+//! Dispatchers copy callbacks out of their registry to call them without holding its lock.
+//! To keep a module loaded while one of its callbacks runs, a dispatcher [`enter`]s the owner
+//! of each callback while still holding the lock, and the loader [`wait_idle`]s after removing
+//! the callbacks of a module under the same lock, before freeing it.
+
+use std::{
+    collections::HashMap,
+    sync::{Condvar, LazyLock, Mutex},
+};
+
+static IN_FLIGHT: LazyLock<InFlight> = LazyLock::new(InFlight::default);
+
+/// Number of calls in flight per owner key, e.g. the module handle of a plugin.
+#[derive(Debug, Default)]
+pub struct InFlight {
+    calls: Mutex<HashMap<usize, usize>>,
+    idle: Condvar,
+}
+
+/// A call into `owner` in flight, ends when dropped.
+#[derive(Debug)]
+pub struct Guard<'a> {
+    in_flight: &'a InFlight,
+    owner: usize,
+}
+
+impl InFlight {
+    pub fn enter(&self, owner: usize) -> Guard<'_> {
+        *self.calls.lock().unwrap().entry(owner).or_default() += 1;
+
+        Guard {
+            in_flight: self,
+            owner,
+        }
+    }
+
+    /// Block until no call into `owner` is in flight.
+    ///
+    /// Must not be called from within a call into `owner`, it would never return.
+    pub fn wait_idle(&self, owner: usize) {
+        let calls = self.calls.lock().unwrap();
+        let _calls = self
+            .idle
+            .wait_while(calls, |calls| calls.contains_key(&owner))
+            .unwrap();
+    }
+
+    #[cfg(test)]
+    fn count(&self, owner: usize) -> usize {
+        self.calls
+            .lock()
+            .unwrap()
+            .get(&owner)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn leave(&self, owner: usize) {
+        let mut calls = self.calls.lock().unwrap();
+        if let Some(count) = calls.get_mut(&owner) {
+            *count -= 1;
+            if *count == 0 {
+                calls.remove(&owner);
+                self.idle.notify_all();
+            }
+        }
+    }
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        self.in_flight.leave(self.owner);
+    }
+}
+
+pub fn enter(owner: usize) -> Guard<'static> {
+    IN_FLIGHT.enter(owner)
+}
+
+pub fn wait_idle(owner: usize) {
+    IN_FLIGHT.wait_idle(owner)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[test]
+    fn count_calls() {
+        let in_flight = InFlight::default();
+
+        let a = in_flight.enter(1);
+        let b = in_flight.enter(1);
+        let c = in_flight.enter(2);
+        assert_eq!(in_flight.count(1), 2);
+        assert_eq!(in_flight.count(2), 1);
+
+        drop(a);
+        assert_eq!(in_flight.count(1), 1);
+        drop(b);
+        drop(c);
+        assert_eq!(in_flight.count(1), 0);
+        assert_eq!(in_flight.count(2), 0);
+
+        // nothing in flight
+        in_flight.wait_idle(1);
+    }
+
+    #[test]
+    fn wait_for_calls_in_flight() {
+        let in_flight = Arc::new(InFlight::default());
+        let finished = Arc::new(AtomicBool::new(false));
+
+        let guard = in_flight.enter(1);
+        let other = in_flight.enter(2);
+        let waiter = std::thread::spawn({
+            let in_flight = in_flight.clone();
+            let finished = finished.clone();
+            move || {
+                in_flight.wait_idle(1);
+                finished.store(true, Ordering::SeqCst);
+            }
+        });
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!finished.load(Ordering::SeqCst));
+
+        drop(guard);
+        waiter.join().unwrap();
+        assert!(finished.load(Ordering::SeqCst));
+        // calls into other owners do not block
+        assert_eq!(in_flight.count(2), 1);
+        drop(other);
+    }
+}
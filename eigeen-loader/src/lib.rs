@@ -7,10 +7,16 @@ use windows::Win32::Foundation::{BOOL, TRUE};
 
 mod address;
 mod command;
+mod config;
 mod error;
+mod event_bus;
 mod export;
+mod function_registry;
 mod hook;
+mod in_flight;
 mod logger;
+mod module_loader;
+mod report;
 mod singleton;
 mod utility;
 
@@ -25,7 +31,6 @@ fn panic_hook(info: &std::panic::PanicHookInfo) {
 }
 
 pub static PLUGIN_LOADER: Mutex<Option<plugin::PluginLoader>> = Mutex::new(None);
-pub static CORE_PLUGIN_API: Mutex<Option<core_extension::CoreAPI>> = Mutex::new(None);
 static INITIALIZED: AtomicBool = AtomicBool::new(false);
 
 #[no_mangle]
@@ -51,22 +56,34 @@ pub extern "C-unwind" fn Initialize() -> BOOL {
     } else if let Err(e) = address::AddressRepository::initialize(address_file_path) {
         log::error!("Failed to initialize address repository: {}", e);
         log::error!("Some plugins may not work correctly.");
+    } else {
+        // resolve all records in a single pass, missing ones are retried on demand
+        match address::AddressRepository::resolve_all() {
+            Ok((total, resolved)) => info!(
+                "Resolved {} addresses ({} total, {} missing).",
+                resolved,
+                total,
+                total - resolved
+            ),
+            Err(e) => log::error!("Failed to resolve addresses: {}", e),
+        }
     }
 
     // setup hooks
-    let result = hook::mh_main::hook_after_mh_main_ctor(|_mh_main_addr| {
+    let result = hook::mh_main::hook_after_mh_main_ctor(|mh_main_addr| {
         debug!("After MhMainCtor");
         // parse game singletons
         singleton::SingletonManager::parse_singletons();
+        event_bus::publish_retained(event_bus::AFTER_MH_MAIN_CTOR, &mh_main_addr.to_le_bytes());
         // load core extensions
         let result = core_extension::CoreAPI::instance().load_core_exts();
         match result {
-            Ok((total, success)) => {
+            Ok(report) => {
                 info!(
                     "Loaded {} core extensions ({} total, {} failed).",
-                    success,
-                    total,
-                    total - success
+                    report.loaded(),
+                    report.total(),
+                    report.failed()
                 )
             }
             Err(e) => log::error!("Failed to load core extensions: {}", e),
@@ -82,18 +99,29 @@ pub extern "C-unwind" fn Initialize() -> BOOL {
 
         // create plugin loader
         let mut loader = plugin::PluginLoader::new();
+        if std::env::var_os(plugin::PluginLoader::DEV_MODE_ENV).is_some() {
+            if let Err(e) = loader.enable_dev_mode() {
+                log::error!("Failed to enable plugin dev mode: {}", e);
+            }
+        }
         // load plugins
         let result = loader.auto_load_plugins();
         match result {
-            Ok((total, success)) => info!(
-                "Loaded {} plugins ({} total, {} failed).",
-                success,
-                total,
-                total - success
+            Ok(report) => info!(
+                "Loaded {} plugins ({} total, {} skipped, {} failed).",
+                report.loaded(),
+                report.total(),
+                report.skipped(),
+                report.failed()
             ),
             Err(e) => log::error!("Failed to load any plugins: {}", e),
         }
+        loader.dispatch(plugin::PluginEvent::GameInitialized);
+        let dev_mode = loader.is_dev_mode();
         PLUGIN_LOADER.lock().unwrap().replace(loader);
+        if dev_mode {
+            plugin::spawn_watcher();
+        }
     });
     if let Err(e) = result {
         log::error!("Fatal error: Failed to hook MhMainCtor: {}", e);
@@ -102,6 +130,8 @@ pub extern "C-unwind" fn Initialize() -> BOOL {
 
     let result = hook::chat::hook_chat_sent(|msg| {
         command::CommandHandler::on_message(msg);
+        event_bus::publish(event_bus::CHAT_SENT, msg.as_bytes());
+        plugin::dispatch(plugin::PluginEvent::ChatMessage(msg));
     });
     if let Err(e) = result {
         log::warn!("Error: Failed to hook ChatSent: {}", e);
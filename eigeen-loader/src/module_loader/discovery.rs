@@ -0,0 +1,229 @@
+//! Module discovery in the plugins and core extensions directories.
+//!
+//! Two layouts are supported:
+//!
+//! - flat: `plugins/MyPlugin.dll`
+//! - folder: `plugins/MyPlugin/MyPlugin.dll`, with the assets and dependency DLLs of the
+//!   module beside it. Other DLLs in the folder are not loaded as modules.
+//!
+//! File names are compared case-insensitively, like Windows does. If both layouts exist for
+//! the same name, the folder one is loaded and the flat one is skipped.
+
+use std::{
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+/// A module DLL found in a module directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleLocation {
+    /// File stem of the DLL.
+    pub name: String,
+    pub dll: PathBuf,
+    /// Folder of the module, in the folder layout.
+    pub folder: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Discovery {
+    /// Modules by name, case-insensitively sorted.
+    pub modules: Vec<ModuleLocation>,
+    /// DLLs skipped because another one has the same name.
+    pub conflicts: Vec<PathBuf>,
+}
+
+/// Find the modules in `dir`.
+pub fn scan(dir: &Path) -> io::Result<Discovery> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            paths.push(path);
+        } else if path.is_dir() {
+            for entry in std::fs::read_dir(&path)? {
+                let path = entry?.path();
+                if path.is_file() {
+                    paths.push(path);
+                }
+            }
+        }
+    }
+
+    Ok(resolve(dir, paths))
+}
+
+/// Pick the module DLLs from the files in `dir` and its direct subdirectories.
+pub fn resolve(dir: &Path, paths: Vec<PathBuf>) -> Discovery {
+    let mut locations = Vec::new();
+    for path in paths {
+        if !is_dll(&path) {
+            continue;
+        }
+        let Ok(relative) = path.strip_prefix(dir) else {
+            continue;
+        };
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let name = name.to_string();
+
+        let components = relative.components().collect::<Vec<_>>();
+        match components.as_slice() {
+            [Component::Normal(_)] => locations.push(ModuleLocation {
+                name,
+                dll: path,
+                folder: None,
+            }),
+            [Component::Normal(folder), Component::Normal(_)]
+                if folder.eq_ignore_ascii_case(&name) =>
+            {
+                let folder = dir.join(folder);
+                locations.push(ModuleLocation {
+                    name,
+                    dll: path,
+                    folder: Some(folder),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    // folder layout first within the same name
+    locations.sort_by(|a, b| {
+        a.name
+            .to_lowercase()
+            .cmp(&b.name.to_lowercase())
+            .then(b.folder.is_some().cmp(&a.folder.is_some()))
+            .then(a.dll.cmp(&b.dll))
+    });
+
+    let mut discovery = Discovery::default();
+    for location in locations {
+        let conflict = discovery
+            .modules
+            .last()
+            .is_some_and(|last| last.name.eq_ignore_ascii_case(&location.name));
+        if conflict {
+            discovery.conflicts.push(location.dll);
+        } else {
+            discovery.modules.push(location);
+        }
+    }
+
+    discovery
+}
+
+/// Data directory of the plugin `name` in `data_root`.
+///
+/// `None` if `name` is not a plain file name.
+pub fn data_dir(data_root: &Path, name: &str) -> Option<PathBuf> {
+    let invalid = name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.contains(['/', '\\', ':'])
+        || name.trim() != name;
+    if invalid {
+        return None;
+    }
+
+    Some(data_root.join(name))
+}
+
+fn is_dll(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("dll"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve_names(paths: &[&str]) -> (Vec<(String, String)>, Vec<String>) {
+        let dir = Path::new("plugins");
+        let paths = paths.iter().map(|path| dir.join(path)).collect();
+        let discovery = resolve(dir, paths);
+
+        let plugins = discovery
+            .modules
+            .into_iter()
+            .map(|p| {
+                let dll = p.dll.strip_prefix(dir).unwrap();
+                (p.name, dll.to_string_lossy().replace('\\', "/"))
+            })
+            .collect();
+        let conflicts = discovery
+            .conflicts
+            .into_iter()
+            .map(|path| {
+                let dll = path.strip_prefix(dir).unwrap();
+                dll.to_string_lossy().replace('\\', "/")
+            })
+            .collect();
+        (plugins, conflicts)
+    }
+
+    #[test]
+    fn resolve_layouts() {
+        let (plugins, conflicts) = resolve_names(&[
+            "b.dll",
+            "A.DLL",
+            "readme.txt",
+            "C/C.dll",
+            "C/dependency.dll",
+            "C/assets/texture.dll",
+            "D/other.dll",
+            "E/e.dll",
+        ]);
+
+        assert_eq!(
+            plugins,
+            vec![
+                ("A".to_string(), "A.DLL".to_string()),
+                ("b".to_string(), "b.dll".to_string()),
+                ("C".to_string(), "C/C.dll".to_string()),
+                ("e".to_string(), "E/e.dll".to_string()),
+            ]
+        );
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn resolve_conflicts() {
+        // same result whatever the listing order
+        let paths = ["a.dll", "A/A.dll", "b.dll", "B.dll"];
+        let expected = (
+            vec![
+                ("A".to_string(), "A/A.dll".to_string()),
+                ("B".to_string(), "B.dll".to_string()),
+            ],
+            vec!["a.dll".to_string(), "b.dll".to_string()],
+        );
+
+        assert_eq!(resolve_names(&paths), expected);
+        let mut reversed = paths;
+        reversed.reverse();
+        assert_eq!(resolve_names(&reversed), expected);
+    }
+
+    #[test]
+    fn plugin_data_dirs() {
+        let root = Path::new("eigeen_loader/data");
+
+        assert_eq!(
+            data_dir(root, "MyPlugin"),
+            Some(PathBuf::from("eigeen_loader/data/MyPlugin"))
+        );
+        for name in [
+            "",
+            ".",
+            "..",
+            "../MyPlugin",
+            "a/b",
+            "a\\b",
+            "C:",
+            " MyPlugin",
+        ] {
+            assert_eq!(data_dir(root, name), None, "{name:?}");
+        }
+    }
+}
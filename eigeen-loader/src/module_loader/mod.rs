@@ -0,0 +1,419 @@
+//! Loading of plugin and core extension modules.
+//!
+//! Both kinds of modules go through the same pipeline, configured by a [`ModulePolicy`]:
+//!
+//! 1. check the loader version required by the manifest, if any, before loading
+//! 2. load the module
+//! 3. check the loader version required by the `LoaderVersionRange` or `LoaderVersion`
+//!    export, before running any code of the module
+//! 4. call the initialize export
+//!
+//! The module is freed if a version check fails. It is not freed if it fails to
+//! initialize, as it may already have started running.
+//!
+//! Modules with an uninitialize export can be unloaded, see [`ModuleLoader::unload`].
+
+use std::{
+    ffi::{c_void, CStr},
+    path::Path,
+};
+
+use log::{error, warn};
+use shared::export::{LoaderVersion, LoaderVersionRange};
+
+use crate::{
+    error::{Error, Result},
+    utility::version,
+};
+
+pub mod discovery;
+mod windows;
+
+pub use windows::WindowsLoader;
+
+/// The OS module loader.
+pub trait OsLoader {
+    type Handle: Copy;
+
+    fn load(&self, path: &Path) -> Result<Self::Handle>;
+
+    fn symbol(&self, handle: Self::Handle, name: &CStr) -> Option<*const c_void>;
+
+    fn free(&self, handle: Self::Handle);
+}
+
+/// Loading rules of a kind of module.
+#[derive(Debug, Clone, Copy)]
+pub struct ModulePolicy {
+    /// Kind of module, for logs.
+    pub kind: &'static str,
+    /// Name of the initialize export.
+    pub init_symbol: &'static CStr,
+    /// Refuse modules which declare no required loader version.
+    pub require_version: bool,
+    /// Error of a non-zero initialize result.
+    pub init_error: fn(i32) -> Error,
+    /// Name of the uninitialize export, `None` if modules of this kind are never unloaded.
+    pub uninit_symbol: Option<&'static CStr>,
+}
+
+impl ModulePolicy {
+    pub const PLUGIN: Self = Self {
+        kind: "plugin",
+        init_symbol: c"Initialize",
+        require_version: false,
+        init_error: Error::InitPlugin,
+        uninit_symbol: Some(c"Uninitialize"),
+    };
+
+    pub const CORE_EXTENSION: Self = Self {
+        kind: "core extension",
+        init_symbol: c"CoreInitialize",
+        require_version: true,
+        init_error: Error::InitCoreExtension,
+        uninit_symbol: None,
+    };
+}
+
+#[derive(Debug)]
+pub struct ModuleLoader<L> {
+    os: L,
+    policy: ModulePolicy,
+}
+
+impl<L: OsLoader> ModuleLoader<L> {
+    pub const fn new(os: L, policy: ModulePolicy) -> Self {
+        Self { os, policy }
+    }
+
+    /// Load and initialize the module at `path`.
+    ///
+    /// `manifest_version` is the required loader version range declared in its manifest.
+    /// `init` calls the initialize export, with the module handle and the export address, and
+    /// returns its result.
+    pub fn load<F>(&self, path: &Path, manifest_version: Option<&str>, init: F) -> Result<L::Handle>
+    where
+        F: FnOnce(L::Handle, *const c_void) -> i32,
+    {
+        let manifest_required = manifest_version
+            .map(str::parse::<LoaderVersionRange>)
+            .transpose()?;
+        if let Some(required) = &manifest_required {
+            version::check_version(required)?;
+        }
+
+        let handle = self.os.load(path)?;
+        if let Err(e) = self.check_exported_version(handle, path, manifest_required.is_some()) {
+            self.os.free(handle);
+            return Err(e);
+        }
+
+        match self.os.symbol(handle, self.policy.init_symbol) {
+            Some(init_func) => {
+                let code = init(handle, init_func);
+                if code != 0 {
+                    return Err((self.policy.init_error)(code));
+                }
+            }
+            None => warn!(
+                "[{}] Function {} not found. Is it a valid {}?",
+                file_name(path),
+                self.policy.init_symbol.to_string_lossy(),
+                self.policy.kind
+            ),
+        }
+
+        Ok(handle)
+    }
+
+    /// Uninitialize and free a module loaded by [`ModuleLoader::load`].
+    ///
+    /// 1. check the uninitialize export, refusing to unload a module without one
+    /// 2. `revoke` what the module registered in the loader, and wait until none of its
+    ///    callbacks is running, so no loader call reaches it from now on
+    /// 3. call the uninitialize export with `uninit`
+    /// 4. free the module
+    ///
+    /// The module is not freed if it fails to uninitialize, as it may still be running.
+    pub fn unload<R, U>(&self, handle: L::Handle, revoke: R, uninit: U) -> Result<()>
+    where
+        R: FnOnce(L::Handle),
+        U: FnOnce(*const c_void) -> i32,
+    {
+        let uninit_func = self
+            .policy
+            .uninit_symbol
+            .and_then(|symbol| self.os.symbol(handle, symbol))
+            .ok_or(Error::UnloadPlugin)?;
+
+        revoke(handle);
+
+        let code = uninit(uninit_func);
+        if code != 0 {
+            error!(
+                "Failed to uninitialize {}: code = {}",
+                self.policy.kind, code
+            );
+            return Err(Error::UnloadPlugin);
+        }
+
+        self.os.free(handle);
+
+        Ok(())
+    }
+
+    fn check_exported_version(
+        &self,
+        handle: L::Handle,
+        path: &Path,
+        has_manifest_version: bool,
+    ) -> Result<()> {
+        match unsafe { self.required_version_range(handle) } {
+            Some(required) => version::check_version(&required),
+            None if has_manifest_version => Ok(()),
+            None if self.policy.require_version => Err(Error::MissingRequiredVersion),
+            None => {
+                let file_name = file_name(path);
+                warn!("[{file_name}] Function LoaderVersion(&mut LoaderVersion) not found, or version is not set.");
+                warn!(
+                    "[{file_name}] If it is a compatible {}, ignore this warning.",
+                    self.policy.kind
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Read the loader versions supported by a module.
+    ///
+    /// From the `LoaderVersionRange` export, or the `LoaderVersion` export as `^version`.
+    /// Does not run any other code of the module.
+    unsafe fn required_version_range(&self, handle: L::Handle) -> Option<LoaderVersionRange> {
+        if let Some(range_func) = self.os.symbol(handle, c"LoaderVersionRange") {
+            let range_func: VersionRangeFunc = std::mem::transmute(range_func);
+
+            let mut range = LoaderVersionRange::default();
+            range_func(&mut range);
+            return Some(range);
+        }
+
+        if let Some(version_func) = self.os.symbol(handle, c"LoaderVersion") {
+            let version_func: VersionFunc = std::mem::transmute(version_func);
+
+            let mut version = LoaderVersion::default();
+            version_func(&mut version);
+            if version != LoaderVersion::default() {
+                return Some(LoaderVersionRange::compatible(version));
+            }
+        }
+
+        None
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+type VersionFunc = unsafe extern "C" fn(&mut LoaderVersion);
+type VersionRangeFunc = unsafe extern "C" fn(&mut LoaderVersionRange);
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::HashMap};
+
+    use super::*;
+
+    /// Modules are sets of exports, by path.
+    #[derive(Default)]
+    struct FakeLoader {
+        modules: HashMap<&'static str, Vec<(&'static CStr, *const c_void)>>,
+        loaded: RefCell<Vec<&'static str>>,
+        freed: RefCell<Vec<&'static str>>,
+    }
+
+    impl FakeLoader {
+        fn module(
+            mut self,
+            path: &'static str,
+            exports: &[(&'static CStr, *const c_void)],
+        ) -> Self {
+            self.modules.insert(path, exports.to_vec());
+            self
+        }
+    }
+
+    impl OsLoader for FakeLoader {
+        type Handle = &'static str;
+
+        fn load(&self, path: &Path) -> Result<Self::Handle> {
+            let (&path, _) = self
+                .modules
+                .iter()
+                .find(|(p, _)| Path::new(p) == path)
+                .ok_or_else(|| Error::PluginNotFound(path.display().to_string()))?;
+            self.loaded.borrow_mut().push(path);
+            Ok(path)
+        }
+
+        fn symbol(&self, handle: Self::Handle, name: &CStr) -> Option<*const c_void> {
+            self.modules[handle]
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, func)| *func)
+        }
+
+        fn free(&self, handle: Self::Handle) {
+            self.freed.borrow_mut().push(handle);
+        }
+    }
+
+    extern "C" fn current_version(version: &mut LoaderVersion) {
+        *version = version::loader_version();
+    }
+
+    extern "C" fn future_range(range: &mut LoaderVersionRange) {
+        let current = version::loader_version();
+        range.min = LoaderVersion::new(current.major + 1, 0, 0);
+    }
+
+    fn init(code: i32) -> impl FnOnce(&'static str, *const c_void) -> i32 {
+        move |_, _| code
+    }
+
+    fn exports(version: Option<*const c_void>) -> Vec<(&'static CStr, *const c_void)> {
+        let mut exports = vec![(c"Initialize", std::ptr::null())];
+        exports.extend(version.map(|func| (c"LoaderVersion", func)));
+        exports
+    }
+
+    #[test]
+    fn load_with_policies() {
+        let os = FakeLoader::default()
+            .module("versioned.dll", &exports(Some(current_version as _)))
+            .module("unversioned.dll", &exports(None));
+
+        let plugins = ModuleLoader::new(os, ModulePolicy::PLUGIN);
+        assert!(plugins
+            .load("versioned.dll".as_ref(), None, init(0))
+            .is_ok());
+        assert!(plugins
+            .load("unversioned.dll".as_ref(), None, init(0))
+            .is_ok());
+        assert!(matches!(
+            plugins.load("versioned.dll".as_ref(), None, init(3)),
+            Err(Error::InitPlugin(3))
+        ));
+        assert!(matches!(
+            plugins.load("missing.dll".as_ref(), None, init(0)),
+            Err(Error::PluginNotFound(_))
+        ));
+        assert!(plugins.os.freed.borrow().is_empty());
+
+        // core extensions must declare a version, and are freed otherwise
+        let os = FakeLoader::default()
+            .module("unversioned.dll", &[(c"CoreInitialize", std::ptr::null())]);
+        let core_extensions = ModuleLoader::new(os, ModulePolicy::CORE_EXTENSION);
+        let mut initialized = false;
+        let result = core_extensions.load("unversioned.dll".as_ref(), None, |_, _| {
+            initialized = true;
+            0
+        });
+        assert!(matches!(result, Err(Error::MissingRequiredVersion)));
+        assert!(!initialized);
+        assert_eq!(*core_extensions.os.freed.borrow(), vec!["unversioned.dll"]);
+    }
+
+    #[test]
+    fn unload_in_order() {
+        thread_local! {
+            static STEPS: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+        }
+
+        extern "C" fn uninitialize() -> i32 {
+            STEPS.with_borrow_mut(|steps| steps.push("uninitialize"));
+            0
+        }
+
+        extern "C" fn fail() -> i32 {
+            STEPS.with_borrow_mut(|steps| steps.push("uninitialize"));
+            1
+        }
+
+        let uninit = |func: *const c_void| {
+            let func: extern "C" fn() -> i32 = unsafe { std::mem::transmute(func) };
+            func()
+        };
+        let os = FakeLoader::default()
+            .module("plugin.dll", &[(c"Uninitialize", uninitialize as _)])
+            .module("failing.dll", &[(c"Uninitialize", fail as _)])
+            .module("permanent.dll", &exports(None));
+        let plugins = ModuleLoader::new(os, ModulePolicy::PLUGIN);
+
+        // revoked before uninitializing, freed last
+        let result = plugins.unload(
+            "plugin.dll",
+            |_| STEPS.with_borrow_mut(|steps| steps.push("revoke")),
+            uninit,
+        );
+        assert!(result.is_ok());
+        assert_eq!(STEPS.take(), vec!["revoke", "uninitialize"]);
+        assert_eq!(*plugins.os.freed.borrow(), vec!["plugin.dll"]);
+
+        // kept loaded if it fails to uninitialize
+        let result = plugins.unload(
+            "failing.dll",
+            |_| STEPS.with_borrow_mut(|steps| steps.push("revoke")),
+            uninit,
+        );
+        assert!(matches!(result, Err(Error::UnloadPlugin)));
+        assert_eq!(STEPS.take(), vec!["revoke", "uninitialize"]);
+
+        // left untouched without an uninitialize export
+        let result = plugins.unload(
+            "permanent.dll",
+            |_| STEPS.with_borrow_mut(|steps| steps.push("revoke")),
+            uninit,
+        );
+        assert!(matches!(result, Err(Error::UnloadPlugin)));
+        assert!(STEPS.take().is_empty());
+        assert_eq!(*plugins.os.freed.borrow(), vec!["plugin.dll"]);
+    }
+
+    #[test]
+    fn check_versions() {
+        let os = FakeLoader::default()
+            .module("future.dll", &[(c"LoaderVersionRange", future_range as _)])
+            .module("unversioned.dll", &exports(None));
+        let modules = ModuleLoader::new(os, ModulePolicy::CORE_EXTENSION);
+
+        // the range export is checked before initializing
+        let result = modules.load("future.dll".as_ref(), None, init(0));
+        assert!(matches!(
+            result,
+            Err(Error::IncompatiblePluginRequiredVersion { .. })
+        ));
+        assert_eq!(*modules.os.freed.borrow(), vec!["future.dll"]);
+
+        // the manifest version is checked before loading
+        let future = format!(">={}.0", version::loader_version().major + 1);
+        let result = modules.load("unversioned.dll".as_ref(), Some(&future), init(0));
+        assert!(matches!(
+            result,
+            Err(Error::IncompatiblePluginRequiredVersion { .. })
+        ));
+        assert_eq!(*modules.os.loaded.borrow(), vec!["future.dll"]);
+
+        // and stands for the exports
+        let current = format!(">={}", version::loader_version());
+        let result = modules.load("unversioned.dll".as_ref(), Some(&current), init(0));
+        assert!(result.is_ok());
+        assert!(matches!(
+            modules.load("unversioned.dll".as_ref(), Some("1.x"), init(0)),
+            Err(Error::Version(_))
+        ));
+    }
+}
@@ -0,0 +1,56 @@
+use std::{
+    ffi::{c_void, CStr},
+    path::Path,
+};
+
+use log::error;
+use windows::{
+    core::{PCSTR, PCWSTR},
+    Win32::{
+        Foundation::{FreeLibrary, HANDLE, HMODULE},
+        System::LibraryLoader::{GetProcAddress, LoadLibraryExW, LOAD_WITH_ALTERED_SEARCH_PATH},
+    },
+};
+
+use crate::{error::Result, utility};
+
+use super::OsLoader;
+
+/// Loads modules with `LoadLibraryExW`.
+///
+/// Dependencies of a module are searched in its own directory first.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WindowsLoader;
+
+impl OsLoader for WindowsLoader {
+    type Handle = HMODULE;
+
+    fn load(&self, path: &Path) -> Result<HMODULE> {
+        // the altered search path requires an absolute path
+        let path = std::path::absolute(path)?;
+        let path_w = utility::string::to_wstring_bytes_with_nul(path.to_str().unwrap());
+
+        let hmodule = unsafe {
+            LoadLibraryExW(
+                PCWSTR::from_raw(path_w.as_ptr()),
+                HANDLE::default(),
+                LOAD_WITH_ALTERED_SEARCH_PATH,
+            )?
+        };
+
+        Ok(hmodule)
+    }
+
+    fn symbol(&self, handle: HMODULE, name: &CStr) -> Option<*const c_void> {
+        unsafe {
+            GetProcAddress(handle, PCSTR::from_raw(name.as_ptr() as *const u8))
+                .map(|func| func as *const c_void)
+        }
+    }
+
+    fn free(&self, handle: HMODULE) {
+        if let Err(e) = unsafe { FreeLibrary(handle) } {
+            error!("Failed to free library: {}", e);
+        }
+    }
+}
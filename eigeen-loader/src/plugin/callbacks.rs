@@ -0,0 +1,127 @@
+//! Optional lifecycle callbacks exported by plugins.
+//!
+//! All callbacks return 0 on success, like `Initialize`. They are called as `extern "C-unwind"`:
+//! a panic unwinding out of a callback is caught and logged like a failure code, and the event
+//! is still dispatched to the other plugins. C++ exceptions must not escape a callback, and
+//! faults such as access violations are not caught.
+//!
+//! ```cpp
+//! extern "C" int32_t OnGameInitialized();
+//! extern "C" int32_t OnChatMessage(const uint8_t* msg, size_t len);
+//! ```
+//!
+//! `OnQuestEnter`, `OnQuestLeave` and `OnUpdate` are not supported yet. They need a per-frame
+//! hook on the game thread, and there is no verified address for one.
+
+use std::panic::AssertUnwindSafe;
+
+use log::error;
+use windows::{
+    core::{s, PCSTR},
+    Win32::{Foundation::HMODULE, System::LibraryLoader::GetProcAddress},
+};
+
+use super::events::PluginEvent;
+
+type EventFunc = unsafe extern "C-unwind" fn() -> i32;
+type ChatMessageFunc = unsafe extern "C-unwind" fn(*const u8, usize) -> i32;
+
+/// Callbacks discovered in a plugin module.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PluginCallbacks {
+    on_game_initialized: Option<EventFunc>,
+    on_chat_message: Option<ChatMessageFunc>,
+}
+
+impl PluginCallbacks {
+    /// Look up the callbacks exported by `hmodule`.
+    pub unsafe fn discover(hmodule: HMODULE) -> Self {
+        unsafe fn get<T>(hmodule: HMODULE, name: PCSTR) -> Option<T> {
+            GetProcAddress(hmodule, name).map(|func| std::mem::transmute_copy(&func))
+        }
+
+        Self {
+            on_game_initialized: get(hmodule, s!("OnGameInitialized")),
+            on_chat_message: get(hmodule, s!("OnChatMessage")),
+        }
+    }
+
+    /// Call the callback of `event` of plugin `name`, if exported, and log a failure code or
+    /// a panic.
+    pub fn dispatch(&self, name: &str, event: &PluginEvent) {
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| unsafe {
+            match *event {
+                PluginEvent::GameInitialized => self.on_game_initialized.map(|f| f()),
+                PluginEvent::ChatMessage(msg) => {
+                    self.on_chat_message.map(|f| f(msg.as_ptr(), msg.len()))
+                }
+            }
+        }));
+
+        match result {
+            Ok(Some(code)) if code != 0 => error!(
+                "Plugin {} {} failed: code = {}",
+                name,
+                event.callback_name(),
+                code
+            ),
+            Ok(_) => {}
+            Err(_) => error!("Plugin {} {} panicked.", name, event.callback_name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn dispatch_after_panic() {
+        static RECEIVED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+        unsafe extern "C-unwind" fn record(msg: *const u8, len: usize) -> i32 {
+            let msg = std::slice::from_raw_parts(msg, len);
+            RECEIVED
+                .lock()
+                .unwrap()
+                .push(String::from_utf8(msg.to_vec()).unwrap());
+            0
+        }
+
+        unsafe extern "C-unwind" fn panic(_: *const u8, _: usize) -> i32 {
+            panic!("plugin failed");
+        }
+
+        unsafe extern "C-unwind" fn fail() -> i32 {
+            1
+        }
+
+        let plugins = [
+            PluginCallbacks {
+                on_chat_message: Some(panic),
+                ..Default::default()
+            },
+            PluginCallbacks {
+                on_game_initialized: Some(fail),
+                ..Default::default()
+            },
+            PluginCallbacks {
+                on_chat_message: Some(record),
+                ..Default::default()
+            },
+        ];
+        for event in [
+            PluginEvent::ChatMessage("hello"),
+            PluginEvent::GameInitialized,
+            PluginEvent::ChatMessage("again"),
+        ] {
+            for (i, plugin) in plugins.iter().enumerate() {
+                plugin.dispatch(&format!("plugin{}", i), &event);
+            }
+        }
+
+        assert_eq!(*RECEIVED.lock().unwrap(), vec!["hello", "again"]);
+    }
+}
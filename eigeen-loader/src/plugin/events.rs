@@ -0,0 +1,22 @@
+//! Game events dispatched to plugins.
+
+/// An event dispatched to the lifecycle callbacks of plugins.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PluginEvent<'a> {
+    /// The game finished initializing, after mhMain is constructed.
+    ///
+    /// Plugins loaded later receive it right after they are loaded.
+    GameInitialized,
+    /// A chat message sent by the player.
+    ChatMessage(&'a str),
+}
+
+impl PluginEvent<'_> {
+    /// Name of the plugin export handling the event.
+    pub fn callback_name(&self) -> &'static str {
+        match self {
+            PluginEvent::GameInitialized => "OnGameInitialized",
+            PluginEvent::ChatMessage(_) => "OnChatMessage",
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Plugin hot reload for plugin development.
+//!
+//! In dev mode, plugins are loaded from shadow copies, so the original DLLs stay writable.
+//! The originals are polled for changes, and a plugin is reloaded once its file has stopped
+//! changing for a debounce period, so a half-written DLL is not loaded.
+
+use std::{
+    collections::HashMap,
+    fs::Metadata,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Modification stamp of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    pub modified: SystemTime,
+    pub len: u64,
+}
+
+impl FileStamp {
+    pub fn from_metadata(metadata: &Metadata) -> Option<Self> {
+        Some(Self {
+            modified: metadata.modified().ok()?,
+            len: metadata.len(),
+        })
+    }
+
+    /// Stamp of the file at `path`, `None` if it does not exist.
+    pub fn read(path: &Path) -> Option<Self> {
+        Self::from_metadata(&std::fs::metadata(path).ok()?)
+    }
+}
+
+/// Detects settled changes of watched files.
+#[derive(Debug)]
+pub struct ChangeDebouncer {
+    debounce: Duration,
+    files: HashMap<PathBuf, WatchState>,
+}
+
+#[derive(Debug)]
+struct WatchState {
+    /// Stamp of the loaded file.
+    stamp: Option<FileStamp>,
+    /// Latest different stamp, and when it was first seen.
+    pending: Option<(Option<FileStamp>, Instant)>,
+}
+
+impl ChangeDebouncer {
+    pub fn new(debounce: Duration) -> Self {
+        Self {
+            debounce,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Watch `path`, with the stamp of the loaded file.
+    pub fn watch(&mut self, path: PathBuf, stamp: Option<FileStamp>) {
+        self.files.insert(
+            path,
+            WatchState {
+                stamp,
+                pending: None,
+            },
+        );
+    }
+
+    pub fn unwatch(&mut self, path: &Path) {
+        self.files.remove(path);
+    }
+
+    /// Update the current stamp of `path`.
+    ///
+    /// Returns `true` if the file changed and its stamp has not changed since for the
+    /// debounce period. A removed file is not reported until it comes back.
+    pub fn update(&mut self, path: &Path, stamp: Option<FileStamp>, now: Instant) -> bool {
+        let Some(state) = self.files.get_mut(path) else {
+            return false;
+        };
+
+        if stamp == state.stamp {
+            state.pending = None;
+            return false;
+        }
+
+        match state.pending {
+            Some((pending, since)) if pending == stamp => {
+                if now.duration_since(since) < self.debounce || stamp.is_none() {
+                    return false;
+                }
+                state.stamp = stamp;
+                state.pending = None;
+                true
+            }
+            _ => {
+                state.pending = Some((stamp, now));
+                false
+            }
+        }
+    }
+}
+
+/// Path of the shadow copy of `original`.
+///
+/// Each copy goes to its own `generation` directory, to keep the file name of the plugin
+/// while the previous copy may still be mapped.
+pub fn shadow_path(shadow_dir: &Path, generation: u32, original: &Path) -> Option<PathBuf> {
+    Some(
+        shadow_dir
+            .join(generation.to_string())
+            .join(original.file_name()?),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEBOUNCE: Duration = Duration::from_millis(500);
+
+    fn stamp(secs: u64, len: u64) -> Option<FileStamp> {
+        Some(FileStamp {
+            modified: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+            len,
+        })
+    }
+
+    #[test]
+    fn debounce_changes() {
+        let path = Path::new("plugins/MyPlugin.dll");
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        let mut debouncer = ChangeDebouncer::new(DEBOUNCE);
+        debouncer.watch(path.to_path_buf(), stamp(1, 100));
+
+        assert!(!debouncer.update(path, stamp(1, 100), at(0)));
+        // still being written
+        assert!(!debouncer.update(path, stamp(2, 50), at(100)));
+        assert!(!debouncer.update(path, stamp(2, 200), at(300)));
+        assert!(!debouncer.update(path, stamp(2, 200), at(700)));
+        // settled
+        assert!(debouncer.update(path, stamp(2, 200), at(800)));
+        assert!(!debouncer.update(path, stamp(2, 200), at(2000)));
+
+        // not watched
+        assert!(!debouncer.update(Path::new("other.dll"), stamp(3, 1), at(0)));
+    }
+
+    #[test]
+    fn ignore_reverted_and_removed_files() {
+        let path = Path::new("plugins/MyPlugin.dll");
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        let mut debouncer = ChangeDebouncer::new(DEBOUNCE);
+        debouncer.watch(path.to_path_buf(), stamp(1, 100));
+
+        // reverted before settling
+        assert!(!debouncer.update(path, stamp(2, 100), at(0)));
+        assert!(!debouncer.update(path, stamp(1, 100), at(100)));
+        assert!(!debouncer.update(path, stamp(1, 100), at(1000)));
+
+        // removed, then replaced
+        assert!(!debouncer.update(path, None, at(1000)));
+        assert!(!debouncer.update(path, None, at(2000)));
+        assert!(!debouncer.update(path, stamp(3, 100), at(2100)));
+        assert!(debouncer.update(path, stamp(3, 100), at(2600)));
+
+        debouncer.unwatch(path);
+        assert!(!debouncer.update(path, stamp(4, 100), at(3000)));
+        assert!(!debouncer.update(path, stamp(4, 100), at(4000)));
+    }
+
+    #[test]
+    fn shadow_paths() {
+        let shadow_dir = Path::new("eigeen_loader/shadow");
+
+        assert_eq!(
+            shadow_path(
+                shadow_dir,
+                3,
+                Path::new("eigeen_loader/plugins/MyPlugin.dll")
+            ),
+            Some(PathBuf::from("eigeen_loader/shadow/3/MyPlugin.dll"))
+        );
+        assert_eq!(shadow_path(shadow_dir, 3, Path::new("..")), None);
+    }
+}
@@ -0,0 +1,116 @@
+//! Plugin manifests.
+//!
+//! A manifest is an optional sidecar file next to the plugin DLL, with the same file stem:
+//! `MyPlugin.toml` or `MyPlugin.json` for `MyPlugin.dll`.
+//!
+//! ```toml
+//! name = "MyPlugin"
+//! version = "1.0.0"
+//! author = "Someone"
+//! loader_version = ">=1.0"
+//! depends_on = ["OtherPlugin"]
+//! load_after = ["OptionalPlugin"]
+//! ```
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginManifest {
+    /// Plugin name, the file stem of the DLL by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Required loader version range, e.g. `1.2` or `>=1.2, <3`.
+    ///
+    /// Checked before the plugin is loaded, see [`shared::export::LoaderVersionRange`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader_version: Option<String>,
+    /// Plugins which must be loaded before this one.
+    ///
+    /// The plugin is not loaded if any of them is missing or fails to load.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Plugins which are loaded before this one if present.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub load_after: Vec<String>,
+}
+
+impl PluginManifest {
+    const EXTENSIONS: [&'static str; 2] = ["toml", "json"];
+
+    pub fn from_toml(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn from_json(content: &str) -> Result<Self> {
+        Ok(serde_json::from_str(content)?)
+    }
+
+    /// Load the manifest of the plugin at `plugin_path`, if any.
+    pub fn load_for(plugin_path: &Path) -> Result<Option<Self>> {
+        for extension in Self::EXTENSIONS {
+            let path = plugin_path.with_extension(extension);
+            if !path.is_file() {
+                continue;
+            }
+
+            let content = std::fs::read_to_string(&path)?;
+            let manifest = match extension {
+                "toml" => Self::from_toml(&content)?,
+                _ => Self::from_json(&content)?,
+            };
+            return Ok(Some(manifest));
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_manifest() {
+        let toml = PluginManifest::from_toml(
+            r#"
+            name = "MyPlugin"
+            version = "1.0.0"
+            loader_version = ">=1.0"
+            depends_on = ["Base"]
+            load_after = ["Optional"]
+            "#,
+        )
+        .unwrap();
+        let json = PluginManifest::from_json(
+            r#"{
+                "name": "MyPlugin",
+                "version": "1.0.0",
+                "loader_version": ">=1.0",
+                "depends_on": ["Base"],
+                "load_after": ["Optional"]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(toml, json);
+        assert_eq!(toml.name.as_deref(), Some("MyPlugin"));
+        assert_eq!(toml.author, None);
+        assert_eq!(toml.depends_on, vec!["Base"]);
+
+        // every field is optional
+        assert_eq!(
+            PluginManifest::from_toml("").unwrap(),
+            PluginManifest::default()
+        );
+    }
+}
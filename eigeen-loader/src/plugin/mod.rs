@@ -0,0 +1,674 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use log::{error, info, warn};
+use windows::Win32::Foundation::HMODULE;
+
+use crate::{
+    config::LoaderConfig,
+    error::{Error, Result},
+    event_bus, in_flight,
+    module_loader::{
+        discovery::{self, ModuleLocation},
+        ModuleLoader, ModulePolicy, WindowsLoader,
+    },
+    report::{LoadEntry, LoadReport, LoadStatus},
+};
+
+use callbacks::PluginCallbacks;
+use hot_reload::{ChangeDebouncer, FileStamp};
+use manifest::PluginManifest;
+
+pub use events::PluginEvent;
+
+mod callbacks;
+mod events;
+mod hot_reload;
+mod manifest;
+mod order;
+
+/// Load report of the plugins, kept apart from [`crate::PLUGIN_LOADER`] so it can be read
+/// while the loader is busy or not created yet.
+static REPORT: Mutex<LoadReport> = Mutex::new(LoadReport::new());
+
+pub struct PluginLoader {
+    plugins: Vec<Plugin>,
+    config: LoaderConfig,
+    dev_mode: Option<DevMode>,
+    /// Whether [`PluginEvent::GameInitialized`] was dispatched.
+    game_initialized: bool,
+}
+
+/// A loaded plugin.
+///
+/// Freed by [`PluginLoader::release`] only, never on drop: a plugin freed without
+/// `Uninitialize` would leave its hooks pointing to unloaded code.
+pub struct Plugin {
+    name: String,
+    /// The DLL in the plugins directory.
+    source: PathBuf,
+    /// Folder of the plugin, in the folder layout.
+    folder: Option<PathBuf>,
+    /// The loaded DLL, a shadow copy of `source` in dev mode.
+    loaded_path: PathBuf,
+    handle: HMODULE,
+    callbacks: PluginCallbacks,
+    /// `depends_on` of the manifest.
+    depends_on: Vec<String>,
+}
+
+/// Callbacks of a plugin copied out of the loader, keeping the plugin loaded while they are
+/// called.
+struct PluginCall {
+    name: String,
+    callbacks: PluginCallbacks,
+    _in_flight: in_flight::Guard<'static>,
+}
+
+/// Hot reload state, see [`PluginLoader::enable_dev_mode`].
+struct DevMode {
+    generation: u32,
+    debouncer: ChangeDebouncer,
+}
+
+unsafe impl Send for Plugin {}
+
+impl Plugin {
+    /// Call the lifecycle callback of `event`.
+    fn dispatch(&self, event: &PluginEvent) {
+        self.callbacks.dispatch(&self.name, event);
+    }
+}
+
+impl PluginLoader {
+    const PLUGIN_DIR: &'static str = "./eigeen_loader/plugins/";
+    const DATA_DIR: &'static str = "./eigeen_loader/data/";
+    const MODULE_LOADER: ModuleLoader<WindowsLoader> =
+        ModuleLoader::new(WindowsLoader, ModulePolicy::PLUGIN);
+    const SHADOW_DIR: &'static str = "./eigeen_loader/shadow/";
+    /// Environment variable enabling dev mode when set.
+    pub const DEV_MODE_ENV: &'static str = "EIGEEN_LOADER_DEV_MODE";
+    const RELOAD_DEBOUNCE: Duration = Duration::from_millis(500);
+    const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+    pub fn new() -> Self {
+        let config = LoaderConfig::load(Path::new(LoaderConfig::PATH)).unwrap_or_else(|e| {
+            error!("Failed to load loader config, using defaults: {}", e);
+            LoaderConfig::default()
+        });
+
+        PluginLoader {
+            plugins: Vec::new(),
+            config,
+            dev_mode: None,
+            game_initialized: false,
+        }
+    }
+
+    /// Enable dev mode: load plugins from shadow copies in `./eigeen_loader/shadow/`, so
+    /// the DLLs in the plugins directory can be rebuilt while loaded, and reload plugins
+    /// when their DLL changes.
+    ///
+    /// Only applies to plugins loaded afterwards.
+    pub fn enable_dev_mode(&mut self) -> Result<()> {
+        // copies from the last session
+        if let Err(e) = std::fs::remove_dir_all(Self::SHADOW_DIR) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to clean shadow directory: {}", e);
+            }
+        }
+        std::fs::create_dir_all(Self::SHADOW_DIR)?;
+
+        self.dev_mode = Some(DevMode {
+            generation: 0,
+            debouncer: ChangeDebouncer::new(Self::RELOAD_DEBOUNCE),
+        });
+        info!("Plugin dev mode enabled, plugins are reloaded on change.");
+
+        Ok(())
+    }
+
+    /// Data directory of the plugin `name`, `./eigeen_loader/data/<name>/`, created if missing.
+    ///
+    /// The same for both plugin layouts, and kept when the plugin is updated or removed.
+    pub fn data_dir(name: &str) -> Result<PathBuf> {
+        let dir = discovery::data_dir(Path::new(Self::DATA_DIR), name)
+            .ok_or_else(|| Error::PluginNotFound(name.to_string()))?;
+        std::fs::create_dir_all(&dir)?;
+
+        Ok(std::path::absolute(dir)?)
+    }
+
+    pub fn is_dev_mode(&self) -> bool {
+        self.dev_mode.is_some()
+    }
+
+    /// Load results of all plugins found, updated by later loads, reloads and unloads.
+    ///
+    /// Updated as each plugin is loaded, so it is available during the first load too.
+    pub fn report() -> LoadReport {
+        REPORT.lock().unwrap().clone()
+    }
+
+    /// Load all plugins in default plugins directory. `./eigeen_loader/plugins/`
+    ///
+    /// Plugins are loaded after the plugins they depend on, as declared in their manifests.
+    /// Plugins disabled in the loader config are skipped.
+    pub fn auto_load_plugins(&mut self) -> Result<LoadReport> {
+        if !Path::new(Self::PLUGIN_DIR).exists() {
+            info!("Plugin directory not found, skipping plugin auto-load.");
+            return Ok(Self::report());
+        }
+
+        let discovery = discovery::scan(Path::new(Self::PLUGIN_DIR))?;
+        for path in discovery.conflicts {
+            let name = file_stem(&path);
+            warn!(
+                "Plugin {} skipped, another plugin has the same file name: {}",
+                name,
+                path.display()
+            );
+            REPORT.lock().unwrap().record(LoadEntry::new(
+                &name,
+                None,
+                LoadStatus::Skipped(format!("file name conflict: {}", path.display())),
+                Duration::ZERO,
+            ));
+        }
+
+        let mut candidates = Vec::new();
+        for location in discovery.modules {
+            match PluginCandidate::new(&location) {
+                Ok(candidate) if !self.config.is_plugin_enabled(&candidate.name) => {
+                    info!("Plugin disabled: {}", candidate.name);
+                    REPORT.lock().unwrap().record(
+                        candidate
+                            .entry(LoadStatus::Skipped("disabled".to_string()), Duration::ZERO),
+                    );
+                }
+                Ok(candidate) => candidates.push(candidate),
+                Err(e) => {
+                    let name = location.name;
+                    error!("Failed to read manifest of plugin {}: {}", name, e);
+                    REPORT.lock().unwrap().record(LoadEntry::new(
+                        &name,
+                        None,
+                        LoadStatus::from_error(&e),
+                        Duration::ZERO,
+                    ));
+                }
+            }
+        }
+
+        let nodes = candidates
+            .iter()
+            .map(PluginCandidate::node)
+            .collect::<Vec<_>>();
+        let load_order = order::load_order(&nodes);
+        for (index, e) in load_order.rejected {
+            let candidate = &candidates[index];
+            error!("Failed to load plugin {}: {}", candidate.name, e);
+            REPORT
+                .lock()
+                .unwrap()
+                .record(candidate.entry(LoadStatus::from_error(&e), Duration::ZERO));
+        }
+
+        for index in load_order.order {
+            let candidate = &candidates[index];
+            // a dependency may have failed to initialize
+            if let Err(e) = self.check_dependencies(candidate) {
+                error!("Failed to load plugin {}: {}", candidate.name, e);
+                REPORT
+                    .lock()
+                    .unwrap()
+                    .record(candidate.entry(LoadStatus::from_error(&e), Duration::ZERO));
+                continue;
+            }
+
+            match self.load_candidate(candidate) {
+                Ok(plugin) => {
+                    info!("Plugin loaded: {}", plugin.name);
+                    self.plugins.push(plugin);
+                }
+                Err(e) => error!("Failed to load plugin {}: {}", candidate.name, e),
+            }
+        }
+
+        Ok(Self::report())
+    }
+
+    /// Dispatch `event` to the lifecycle callbacks of all plugins, in load order.
+    ///
+    /// A failing plugin does not prevent the others from receiving the event.
+    pub fn dispatch(&mut self, event: PluginEvent) {
+        if event == PluginEvent::GameInitialized {
+            self.game_initialized = true;
+        }
+
+        for plugin in self.plugins.iter() {
+            plugin.dispatch(&event);
+        }
+    }
+
+    /// Copy the callbacks of all plugins, in load order, see [`dispatch`].
+    fn calls(&self) -> Vec<PluginCall> {
+        self.plugins
+            .iter()
+            .map(|plugin| PluginCall {
+                name: plugin.name.clone(),
+                callbacks: plugin.callbacks,
+                _in_flight: in_flight::enter(plugin.handle.0 as usize),
+            })
+            .collect()
+    }
+
+    /// Load a specific plugin by name.
+    ///
+    /// Will search for the plugin in the default plugins directory, by DLL file name,
+    /// in both the flat and the folder layout.
+    pub fn load(&mut self, name: &str) -> Result<()> {
+        if !Path::new(Self::PLUGIN_DIR).exists() {
+            return Err(Error::PluginNotFound(Self::PLUGIN_DIR.to_string()));
+        }
+
+        let file_name = name.strip_suffix(".dll").unwrap_or(name);
+        let discovery = discovery::scan(Path::new(Self::PLUGIN_DIR))?;
+        let Some(location) = discovery
+            .modules
+            .iter()
+            .find(|location| location.name.eq_ignore_ascii_case(file_name))
+        else {
+            return Err(Error::PluginNotFound(
+                Path::new(Self::PLUGIN_DIR)
+                    .join(format!("{}.dll", file_name))
+                    .to_string_lossy()
+                    .to_string(),
+            ));
+        };
+
+        let candidate = PluginCandidate::new(location)?;
+        if let Err(e) = self.check_dependencies(&candidate) {
+            REPORT
+                .lock()
+                .unwrap()
+                .record(candidate.entry(LoadStatus::from_error(&e), Duration::ZERO));
+            return Err(e);
+        }
+
+        match self.load_candidate(&candidate) {
+            Ok(plugin) => {
+                info!("Plugin loaded: {}", plugin.name);
+                self.plugins.push(plugin);
+                // TODO: show in-game message
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Unload a specific plugin by name.
+    ///
+    /// Its event subscriptions are revoked before it uninitializes, see
+    /// [`PluginLoader::release`]. Refuses to unload a plugin other loaded plugins depend on.
+    pub fn unload(&mut self, name: &str) -> Result<()> {
+        let Some(index) = self.plugins.iter().position(|p| p.name == name) else {
+            return Err(Error::PluginNotFound(name.to_string()));
+        };
+        self.check_dependents(name)?;
+
+        // removed first, so no new lifecycle call reaches it
+        let plugin = self.plugins.remove(index);
+        if let Err(e) = Self::release(&plugin) {
+            self.plugins.insert(index, plugin);
+            return Err(e);
+        }
+        if let Some(dev_mode) = &mut self.dev_mode {
+            dev_mode.debouncer.unwatch(&plugin.source);
+        }
+        REPORT
+            .lock()
+            .unwrap()
+            .set_status(&plugin.name, LoadStatus::Unloaded);
+
+        Ok(())
+    }
+
+    /// Enable or disable a plugin by name in the loader config, and save the config.
+    ///
+    /// Takes effect on the next launch, loaded plugins are not unloaded.
+    pub fn set_plugin_enabled(&mut self, name: &str, enabled: bool) -> Result<()> {
+        self.config.set_plugin_enabled(name, enabled);
+        self.config.save(Path::new(LoaderConfig::PATH))
+    }
+
+    /// Reload a specific plugin by name, from its DLL in the plugins directory.
+    ///
+    /// The plugin keeps its position in the load order. Like [`PluginLoader::unload`],
+    /// refuses to reload a plugin other loaded plugins depend on.
+    pub fn reload(&mut self, name: &str) -> Result<()> {
+        let Some(index) = self.plugins.iter().position(|p| p.name == name) else {
+            return Err(Error::PluginNotFound(name.to_string()));
+        };
+        self.check_dependents(name)?;
+
+        let plugin = self.plugins.remove(index);
+        if let Err(e) = Self::release(&plugin) {
+            self.plugins.insert(index, plugin);
+            return Err(e);
+        }
+        let location = ModuleLocation {
+            name: file_stem(&plugin.source),
+            dll: plugin.source.clone(),
+            folder: plugin.folder.clone(),
+        };
+        let source = plugin.source.clone();
+        REPORT
+            .lock()
+            .unwrap()
+            .set_status(&plugin.name, LoadStatus::Unloaded);
+
+        let plugin = PluginCandidate::new(&location).and_then(|c| self.load_candidate(&c));
+        let plugin = match plugin {
+            Ok(plugin) => plugin,
+            Err(e) => {
+                if let Some(dev_mode) = &mut self.dev_mode {
+                    dev_mode.debouncer.unwatch(&source);
+                }
+                return Err(e);
+            }
+        };
+        info!("Plugin reloaded: {}", plugin.name);
+        self.plugins.insert(index, plugin);
+
+        Ok(())
+    }
+
+    /// Reload the plugins whose DLL changed, in dev mode.
+    pub fn reload_changed(&mut self) {
+        let Some(dev_mode) = &mut self.dev_mode else {
+            return;
+        };
+
+        let now = Instant::now();
+        let changed = self
+            .plugins
+            .iter()
+            .filter(|p| {
+                let stamp = FileStamp::read(&p.source);
+                dev_mode.debouncer.update(&p.source, stamp, now)
+            })
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>();
+
+        for name in changed {
+            info!("Plugin {} changed, reloading.", name);
+            if let Err(e) = self.reload(&name) {
+                error!("Failed to reload plugin {}: {}", name, e);
+            }
+        }
+    }
+
+    /// Load a plugin, and record the result in the report.
+    fn load_candidate(&mut self, candidate: &PluginCandidate) -> Result<Plugin> {
+        let start = Instant::now();
+        let result = self.load_candidate_inner(candidate);
+        let status = match &result {
+            Ok(_) => LoadStatus::Loaded,
+            Err(e) => LoadStatus::from_error(e),
+        };
+        REPORT
+            .lock()
+            .unwrap()
+            .record(candidate.entry(status, start.elapsed()));
+
+        // loaded after the game is initialized
+        if let Ok(plugin) = &result {
+            if self.game_initialized {
+                plugin.dispatch(&PluginEvent::GameInitialized);
+            }
+        }
+
+        result
+    }
+
+    /// Load a plugin, from a shadow copy in dev mode.
+    fn load_candidate_inner(&mut self, candidate: &PluginCandidate) -> Result<Plugin> {
+        let Some(dev_mode) = &mut self.dev_mode else {
+            return Self::init_plugin(candidate, &candidate.path);
+        };
+
+        let stamp = FileStamp::read(&candidate.path);
+        dev_mode.generation += 1;
+        let shadow_dir = Path::new(Self::SHADOW_DIR);
+        let shadow_path = hot_reload::shadow_path(shadow_dir, dev_mode.generation, &candidate.path)
+            .ok_or_else(|| Error::PluginNotFound(candidate.path.display().to_string()))?;
+        if let Some(parent) = shadow_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(&candidate.path, &shadow_path)?;
+        // dependency DLLs are searched beside the loaded DLL
+        if let (Some(folder), Some(shadow_folder)) = (&candidate.folder, shadow_path.parent()) {
+            for entry in std::fs::read_dir(folder)? {
+                let path = entry?.path();
+                let is_dependency = path != candidate.path
+                    && path.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("dll"));
+                if let (true, Some(file_name)) = (is_dependency, path.file_name()) {
+                    std::fs::copy(&path, shadow_folder.join(file_name))?;
+                }
+            }
+        }
+
+        let plugin = Self::init_plugin(candidate, &shadow_path)?;
+        dev_mode.debouncer.watch(candidate.path.clone(), stamp);
+
+        Ok(plugin)
+    }
+
+    /// Uninitialize and free a plugin removed from the loader, and remove its shadow copy.
+    ///
+    /// Its event subscriptions are revoked, and its callbacks still running are waited for,
+    /// before `Uninitialize` is called, so its lifecycle and event callbacks are no longer
+    /// running. Core functions and commands are not tracked: only core extensions register
+    /// core functions, and plugins cannot register commands. Hooks and other changes the
+    /// plugin made to the game itself are not tracked either, the plugin must undo them in
+    /// `Uninitialize`.
+    ///
+    /// The plugin stays loaded if it has no `Uninitialize` or fails to uninitialize, with
+    /// its event subscriptions revoked in the latter case.
+    fn release(plugin: &Plugin) -> Result<()> {
+        let revoke = |hmodule: HMODULE| {
+            let owner = hmodule.0 as usize;
+            let subscriptions = event_bus::unsubscribe_owner(owner);
+            if subscriptions > 0 {
+                info!(
+                    "Revoked {} event subscriptions of plugin {}.",
+                    subscriptions, plugin.name
+                );
+            }
+
+            // callbacks copied before the plugin was removed may still be running
+            in_flight::wait_idle(owner);
+        };
+        Self::MODULE_LOADER.unload(plugin.handle, revoke, |uninit_func| unsafe {
+            let uninit_func: UninitializeFunc = std::mem::transmute(uninit_func);
+            uninit_func()
+        })?;
+
+        let loaded_path = &plugin.loaded_path;
+        let is_shadow = std::path::absolute(Self::SHADOW_DIR)
+            .is_ok_and(|shadow_dir| loaded_path.starts_with(shadow_dir));
+        if is_shadow {
+            if let Some(dir) = loaded_path.parent() {
+                if let Err(e) = std::fs::remove_dir_all(dir) {
+                    warn!("Failed to remove shadow copy {}: {}", dir.display(), e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that no loaded plugin depends on the plugin `name`.
+    fn check_dependents(&self, name: &str) -> Result<()> {
+        let dependents = self
+            .plugins
+            .iter()
+            .filter(|p| p.depends_on.iter().any(|dependency| dependency == name))
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>();
+        if !dependents.is_empty() {
+            return Err(Error::PluginInUse {
+                plugin: name.to_string(),
+                dependents,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Check that the `depends_on` plugins of `candidate` are loaded.
+    fn check_dependencies(&self, candidate: &PluginCandidate) -> Result<()> {
+        let Some(manifest) = &candidate.manifest else {
+            return Ok(());
+        };
+
+        for dependency in manifest.depends_on.iter() {
+            if !self.plugins.iter().any(|p| &p.name == dependency) {
+                return Err(Error::MissingDependency {
+                    plugin: candidate.name.clone(),
+                    dependency: dependency.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn init_plugin(candidate: &PluginCandidate, load_path: &Path) -> Result<Plugin> {
+        let manifest_version = candidate
+            .manifest
+            .as_ref()
+            .and_then(|manifest| manifest.loader_version.as_deref());
+        let hmodule =
+            Self::MODULE_LOADER.load(load_path, manifest_version, |_, init_func| unsafe {
+                let init_func: InitializeFunc = std::mem::transmute(init_func);
+                init_func()
+            })?;
+
+        Ok(Plugin {
+            name: candidate.name.clone(),
+            source: candidate.path.clone(),
+            folder: candidate.folder.clone(),
+            loaded_path: std::path::absolute(load_path)?,
+            handle: hmodule,
+            callbacks: unsafe { PluginCallbacks::discover(hmodule) },
+            depends_on: candidate
+                .manifest
+                .as_ref()
+                .map(|manifest| manifest.depends_on.clone())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// A plugin found in the plugins directory, not loaded yet.
+struct PluginCandidate {
+    name: String,
+    path: PathBuf,
+    folder: Option<PathBuf>,
+    manifest: Option<PluginManifest>,
+}
+
+impl PluginCandidate {
+    /// Read the manifest of the plugin at `location`.
+    fn new(location: &ModuleLocation) -> Result<Self> {
+        let manifest = PluginManifest::load_for(&location.dll)?;
+        let name = manifest
+            .as_ref()
+            .and_then(|manifest| manifest.name.clone())
+            .unwrap_or_else(|| location.name.clone());
+
+        Ok(Self {
+            name,
+            path: location.dll.clone(),
+            folder: location.folder.clone(),
+            manifest,
+        })
+    }
+
+    fn entry(&self, status: LoadStatus, duration: Duration) -> LoadEntry {
+        let version = self
+            .manifest
+            .as_ref()
+            .and_then(|manifest| manifest.version.as_deref());
+
+        LoadEntry::new(&self.name, version, status, duration)
+    }
+
+    fn node(&self) -> order::PluginNode<'_> {
+        let (depends_on, load_after): (&[String], &[String]) = match &self.manifest {
+            Some(manifest) => (&manifest.depends_on, &manifest.load_after),
+            None => (&[], &[]),
+        };
+
+        order::PluginNode {
+            name: &self.name,
+            depends_on,
+            load_after,
+        }
+    }
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+impl Default for PluginLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reload changed plugins of [`crate::PLUGIN_LOADER`] in the background, while in dev mode.
+pub fn spawn_watcher() {
+    std::thread::spawn(|| loop {
+        std::thread::sleep(PluginLoader::WATCH_INTERVAL);
+
+        let mut loader = crate::PLUGIN_LOADER.lock().unwrap();
+        let Some(loader) = loader.as_mut() else {
+            continue;
+        };
+        if !loader.is_dev_mode() {
+            break;
+        }
+        loader.reload_changed();
+    });
+}
+
+/// Dispatch `event` to the plugins of [`crate::PLUGIN_LOADER`], in load order.
+///
+/// The callbacks are copied out of the loader and called after releasing it, so a slow
+/// callback does not block commands, reloads or the load report.
+pub fn dispatch(event: PluginEvent) {
+    let calls = match crate::PLUGIN_LOADER.lock().unwrap().as_ref() {
+        Some(loader) => loader.calls(),
+        None => return,
+    };
+
+    for call in calls.iter() {
+        call.callbacks.dispatch(&call.name, &event);
+    }
+}
+
+type InitializeFunc = unsafe extern "C" fn() -> i32;
+type UninitializeFunc = unsafe extern "C" fn() -> i32;
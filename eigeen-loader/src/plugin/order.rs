@@ -0,0 +1,279 @@
+//! Plugin load order, from the dependencies declared in manifests.
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use crate::error::Error;
+
+/// A plugin in the dependency graph.
+#[derive(Debug, Clone, Copy)]
+pub struct PluginNode<'a> {
+    pub name: &'a str,
+    pub depends_on: &'a [String],
+    pub load_after: &'a [String],
+}
+
+#[derive(Debug, Default)]
+pub struct LoadOrder {
+    /// Indices of the plugins to load, in order.
+    pub order: Vec<usize>,
+    /// Plugins which cannot be loaded, with the reason.
+    pub rejected: Vec<(usize, Error)>,
+}
+
+/// Sort plugins so that each one is loaded after its `depends_on` and `load_after` plugins.
+///
+/// Unrelated plugins keep their relative order. Plugins with a missing dependency, or in a
+/// dependency cycle, are rejected, and so are the plugins depending on them.
+pub fn load_order(nodes: &[PluginNode]) -> LoadOrder {
+    let mut indices = HashMap::new();
+    for (index, node) in nodes.iter().enumerate() {
+        indices.entry(node.name).or_insert(index);
+    }
+
+    let mut rejected: Vec<Option<Error>> = nodes.iter().map(|_| None).collect();
+    loop {
+        reject_missing(nodes, &indices, &mut rejected);
+
+        match topological_sort(nodes, &indices, &rejected) {
+            Ok(order) => {
+                let rejected = rejected
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(index, error)| Some((index, error?)))
+                    .collect();
+                return LoadOrder { order, rejected };
+            }
+            Err(cycle) => {
+                let mut names = cycle
+                    .iter()
+                    .map(|&i| nodes[i].name.to_string())
+                    .collect::<Vec<_>>();
+                names.push(names[0].clone());
+                for index in cycle {
+                    rejected[index] = Some(Error::DependencyCycle(names.clone()));
+                }
+            }
+        }
+    }
+}
+
+/// Reject plugins whose `depends_on` plugins are missing or rejected.
+fn reject_missing(
+    nodes: &[PluginNode],
+    indices: &HashMap<&str, usize>,
+    rejected: &mut [Option<Error>],
+) {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (index, node) in nodes.iter().enumerate() {
+            if rejected[index].is_some() {
+                continue;
+            }
+            let missing = node.depends_on.iter().find(|dependency| {
+                indices
+                    .get(dependency.as_str())
+                    .is_none_or(|&dependency| rejected[dependency].is_some())
+            });
+            if let Some(dependency) = missing {
+                rejected[index] = Some(Error::MissingDependency {
+                    plugin: node.name.to_string(),
+                    dependency: dependency.clone(),
+                });
+                changed = true;
+            }
+        }
+    }
+}
+
+/// Kahn's algorithm over the plugins which are not rejected, taking the lowest index first.
+///
+/// Returns a dependency cycle if the plugins cannot be sorted.
+fn topological_sort(
+    nodes: &[PluginNode],
+    indices: &HashMap<&str, usize>,
+    rejected: &[Option<Error>],
+) -> std::result::Result<Vec<usize>, Vec<usize>> {
+    let dependencies = nodes
+        .iter()
+        .map(|node| {
+            let mut dependencies = node
+                .depends_on
+                .iter()
+                .chain(node.load_after.iter())
+                .filter_map(|name| indices.get(name.as_str()).copied())
+                .filter(|&dependency| rejected[dependency].is_none())
+                .collect::<Vec<_>>();
+            dependencies.sort_unstable();
+            dependencies.dedup();
+            dependencies
+        })
+        .collect::<Vec<_>>();
+
+    let mut dependants = vec![Vec::new(); nodes.len()];
+    let mut in_degree = vec![0; nodes.len()];
+    for (index, node_dependencies) in dependencies.iter().enumerate() {
+        if rejected[index].is_some() {
+            continue;
+        }
+        for &dependency in node_dependencies {
+            dependants[dependency].push(index);
+            in_degree[index] += 1;
+        }
+    }
+
+    let mut ready = (0..nodes.len())
+        .filter(|&index| rejected[index].is_none() && in_degree[index] == 0)
+        .map(Reverse)
+        .collect::<BinaryHeap<_>>();
+    let mut order = Vec::new();
+    while let Some(Reverse(index)) = ready.pop() {
+        order.push(index);
+        for &dependant in dependants[index].iter() {
+            in_degree[dependant] -= 1;
+            if in_degree[dependant] == 0 {
+                ready.push(Reverse(dependant));
+            }
+        }
+    }
+
+    let remaining = (0..nodes.len())
+        .filter(|&index| rejected[index].is_none() && in_degree[index] > 0)
+        .collect::<Vec<_>>();
+    let Some(&first) = remaining.first() else {
+        return Ok(order);
+    };
+
+    // every remaining plugin waits for another remaining one, so following them leads to a cycle
+    let mut path = vec![first];
+    loop {
+        let current = *path.last().unwrap();
+        let next = dependencies[current]
+            .iter()
+            .copied()
+            .find(|&dependency| in_degree[dependency] > 0)
+            .unwrap();
+        if let Some(start) = path.iter().position(|&index| index == next) {
+            return Err(path.split_off(start));
+        }
+        path.push(next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Plugin {
+        name: &'static str,
+        depends_on: Vec<String>,
+        load_after: Vec<String>,
+    }
+
+    fn plugin(name: &'static str, depends_on: &[&str], load_after: &[&str]) -> Plugin {
+        Plugin {
+            name,
+            depends_on: depends_on.iter().map(|s| s.to_string()).collect(),
+            load_after: load_after.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn sort(plugins: &[Plugin]) -> (Vec<&str>, Vec<(&str, String)>) {
+        let nodes = plugins
+            .iter()
+            .map(|plugin| PluginNode {
+                name: plugin.name,
+                depends_on: &plugin.depends_on,
+                load_after: &plugin.load_after,
+            })
+            .collect::<Vec<_>>();
+        let result = load_order(&nodes);
+
+        let order = result.order.iter().map(|&i| plugins[i].name).collect();
+        let rejected = result
+            .rejected
+            .iter()
+            .map(|(i, e)| (plugins[*i].name, e.to_string()))
+            .collect();
+        (order, rejected)
+    }
+
+    #[test]
+    fn sort_by_dependencies() {
+        let plugins = [
+            plugin("A", &["C"], &[]),
+            plugin("B", &[], &[]),
+            plugin("C", &[], &["D"]),
+            plugin("D", &[], &["Absent"]),
+        ];
+
+        let (order, rejected) = sort(&plugins);
+        assert_eq!(order, vec!["B", "D", "C", "A"]);
+        assert!(rejected.is_empty());
+    }
+
+    #[test]
+    fn reject_missing_dependencies() {
+        let plugins = [
+            plugin("A", &["Absent"], &[]),
+            plugin("B", &["A"], &[]),
+            plugin("C", &[], &["A"]),
+        ];
+
+        let (order, rejected) = sort(&plugins);
+        assert_eq!(order, vec!["C"]);
+        assert_eq!(
+            rejected,
+            vec![
+                (
+                    "A",
+                    Error::MissingDependency {
+                        plugin: "A".to_string(),
+                        dependency: "Absent".to_string()
+                    }
+                    .to_string()
+                ),
+                (
+                    "B",
+                    Error::MissingDependency {
+                        plugin: "B".to_string(),
+                        dependency: "A".to_string()
+                    }
+                    .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_cycles() {
+        let plugins = [
+            plugin("A", &["B"], &[]),
+            plugin("B", &[], &["C"]),
+            plugin("C", &["A"], &[]),
+            plugin("D", &["C"], &[]),
+            plugin("E", &[], &["A"]),
+            plugin("F", &["F"], &[]),
+        ];
+
+        let (order, rejected) = sort(&plugins);
+        assert_eq!(order, vec!["E"]);
+
+        let cycle =
+            Error::DependencyCycle(["A", "B", "C", "A"].map(String::from).to_vec()).to_string();
+        assert_eq!(rejected[0], ("A", cycle.clone()));
+        assert_eq!(rejected[1], ("B", cycle.clone()));
+        assert_eq!(rejected[2], ("C", cycle));
+        assert!(matches!(rejected[3].0, "D"));
+        assert_eq!(
+            rejected[4],
+            (
+                "F",
+                Error::DependencyCycle(vec!["F".to_string(), "F".to_string()]).to_string()
+            )
+        );
+    }
+}
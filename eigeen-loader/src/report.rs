@@ -0,0 +1,235 @@
+//! Load reports of plugins and core extensions.
+
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+
+use crate::error::Error;
+
+/// Load results of the modules of a loader, in load order.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct LoadReport {
+    entries: Vec<LoadEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoadEntry {
+    pub name: String,
+    /// Module version, from the plugin manifest.
+    pub version: Option<String>,
+    #[serde(flatten)]
+    pub status: LoadStatus,
+    /// Time spent loading and initializing the module.
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", content = "message", rename_all = "snake_case")]
+pub enum LoadStatus {
+    Loaded,
+    /// Not loaded on purpose, e.g. disabled in the loader config.
+    Skipped(String),
+    /// The module does not support this loader version.
+    IncompatibleVersion(String),
+    Failed(String),
+    /// Loaded, then unloaded by the user.
+    Unloaded,
+}
+
+impl LoadStatus {
+    pub fn from_error(error: &Error) -> Self {
+        match error {
+            Error::IncompatiblePluginRequiredVersion { .. } | Error::MissingRequiredVersion => {
+                Self::IncompatibleVersion(error.to_string())
+            }
+            _ => Self::Failed(error.to_string()),
+        }
+    }
+
+    /// Short description of the status.
+    pub fn label(&self) -> &'static str {
+        match self {
+            LoadStatus::Loaded => "loaded",
+            LoadStatus::Skipped(_) => "skipped",
+            LoadStatus::IncompatibleVersion(_) => "incompatible version",
+            LoadStatus::Failed(_) => "failed",
+            LoadStatus::Unloaded => "unloaded",
+        }
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            LoadStatus::Skipped(message)
+            | LoadStatus::IncompatibleVersion(message)
+            | LoadStatus::Failed(message) => Some(message),
+            LoadStatus::Loaded | LoadStatus::Unloaded => None,
+        }
+    }
+}
+
+impl LoadEntry {
+    pub fn new(name: &str, version: Option<&str>, status: LoadStatus, duration: Duration) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.map(str::to_string),
+            status,
+            duration,
+        }
+    }
+}
+
+impl std::fmt::Display for LoadEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(version) = &self.version {
+            write!(f, " {}", version)?;
+        }
+        write!(f, ": {}", self.status.label())?;
+        if let Some(message) = self.status.message() {
+            write!(f, " ({})", message)?;
+        }
+        if self.status == LoadStatus::Loaded {
+            write!(f, " in {} ms", self.duration.as_millis())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl LoadReport {
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn entries(&self) -> &[LoadEntry] {
+        &self.entries
+    }
+
+    /// Record the result of a module, replacing its previous entry.
+    pub fn record(&mut self, entry: LoadEntry) {
+        match self.entries.iter_mut().find(|e| e.name == entry.name) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    pub fn set_status(&mut self, name: &str, status: LoadStatus) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.name == name) {
+            entry.status = status;
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn loaded(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| e.status == LoadStatus::Loaded)
+            .count()
+    }
+
+    /// Modules which failed to load, including incompatible ones.
+    pub fn failed(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| {
+                matches!(
+                    e.status,
+                    LoadStatus::Failed(_) | LoadStatus::IncompatibleVersion(_)
+                )
+            })
+            .count()
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.status, LoadStatus::Skipped(_)))
+            .count()
+    }
+}
+
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_entries() {
+        let mut report = LoadReport::default();
+        report.record(LoadEntry::new(
+            "A",
+            Some("1.0.0"),
+            LoadStatus::Loaded,
+            Duration::from_millis(12),
+        ));
+        report.record(LoadEntry::new(
+            "B",
+            None,
+            LoadStatus::Skipped("disabled".to_string()),
+            Duration::ZERO,
+        ));
+        report.record(LoadEntry::new(
+            "C",
+            None,
+            LoadStatus::IncompatibleVersion("requires >=2.0.0".to_string()),
+            Duration::ZERO,
+        ));
+
+        assert_eq!(report.total(), 3);
+        assert_eq!(report.loaded(), 1);
+        assert_eq!(report.skipped(), 1);
+        assert_eq!(report.failed(), 1);
+
+        // reloaded
+        report.record(LoadEntry::new(
+            "C",
+            None,
+            LoadStatus::Loaded,
+            Duration::from_millis(3),
+        ));
+        report.set_status("A", LoadStatus::Unloaded);
+        assert_eq!(report.total(), 3);
+        assert_eq!(report.loaded(), 1);
+        assert_eq!(report.failed(), 0);
+
+        assert_eq!(report.entries()[0].to_string(), "A 1.0.0: unloaded");
+        assert_eq!(report.entries()[1].to_string(), "B: skipped (disabled)");
+        assert_eq!(report.entries()[2].to_string(), "C: loaded in 3 ms");
+    }
+
+    #[test]
+    fn serialize_report() {
+        let mut report = LoadReport::default();
+        report.record(LoadEntry::new(
+            "A",
+            Some("1.0.0"),
+            LoadStatus::Loaded,
+            Duration::from_millis(12),
+        ));
+        report.record(LoadEntry::new(
+            "B",
+            None,
+            LoadStatus::Failed("code 1".to_string()),
+            Duration::from_micros(1500),
+        ));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {"name": "A", "version": "1.0.0", "status": "loaded", "duration_ms": 12.0},
+                {"name": "B", "version": null, "status": "failed", "message": "code 1", "duration_ms": 1.5},
+            ])
+        );
+    }
+}
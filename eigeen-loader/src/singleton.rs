@@ -14,6 +14,7 @@ use shared::{
 
 use crate::address::AddressRepository;
 use crate::error::Result;
+use crate::event_bus;
 
 static HOOK: Mutex<Option<InlineHook>> = Mutex::new(None);
 static SINGLETONS: LazyLock<Mutex<HashMap<String, usize>>> =
@@ -46,6 +47,7 @@ impl SingletonManager {
     pub fn parse_singletons() {
         let mut singletons = SINGLETONS.lock().unwrap();
         let mut temp_singletons = unsafe { SINGLETONS_TEMP.borrow_mut() };
+        let mut registered = Vec::new();
 
         for addr in temp_singletons.iter().cloned() {
             let mt_obj = EmptyGameObject::from_ptr(addr as *mut _);
@@ -63,10 +65,20 @@ impl SingletonManager {
             trace!("Found singleton: {} at 0x{:x}", name, addr);
 
             singletons.insert(name.to_string(), addr);
+            registered.push((name.to_string(), addr));
         }
 
         temp_singletons.clear();
         temp_singletons.shrink_to_fit();
+        // subscribers may query singletons
+        drop(temp_singletons);
+        drop(singletons);
+
+        for (name, addr) in registered {
+            let mut payload = addr.to_le_bytes().to_vec();
+            payload.extend_from_slice(name.as_bytes());
+            event_bus::publish_retained(event_bus::SINGLETON_REGISTERED, &payload);
+        }
     }
 
     pub fn get_address_by_name(name: &str) -> Option<usize> {
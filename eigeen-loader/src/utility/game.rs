@@ -1,12 +1,16 @@
 use std::{
-    ffi::{c_void, CStr, CString},
+    ffi::{c_void, CString},
     sync::Mutex,
 };
 
+use address_core::revision::read_game_revision;
+
 use shared::export::{AddressName, SingletonName};
 
 use crate::{address::AddressRepository, singleton::SingletonManager};
 
+use super::memory::ProcessMemory;
+
 pub fn show_system_message(message: &str, flag: i8) {
     // 为了防止panic或消息丢弃，通过检查玩家基址是否为空判断是否进入游戏场景
     // 可能存在不稳定性，请勿依赖该保险措施
@@ -56,38 +60,28 @@ pub fn get_game_revision() -> Option<String> {
         return Some(revision.clone());
     }
 
-    let func_ptr: *const u8 = match AddressRepository::get_ptr(&AddressName::CORE_GAME_REVISION) {
-        Ok(ptr) => ptr,
+    let func_addr = match AddressRepository::get_address(&AddressName::CORE_GAME_REVISION) {
+        Ok(addr) => addr,
         Err(e) => {
             log::error!("Get game revision failed: {}", e);
             return None;
         }
     };
 
-    unsafe {
-        // validate if it is MOV op
-        let mov_start = func_ptr.add(4);
-        let op = std::slice::from_raw_parts(mov_start, 2);
-        if op != [0x48, 0x8B] {
-            log::error!("Get game revision failed: invalid MOV op");
+    let game_revision_str = match read_game_revision(&ProcessMemory, func_addr) {
+        Ok(revision) => revision,
+        Err(e) => {
+            log::error!("Get game revision failed: {}", e);
             return None;
         }
+    };
 
-        let const_addr_rel: i32 = *(mov_start.add(3) as *const i32);
-        let mov_end = mov_start.add(7);
-
-        let const_ptr = *(mov_end.offset(const_addr_rel as isize) as *const *const i8);
-
-        let game_revision_cstr = CStr::from_ptr(const_ptr);
-        let game_revision_str = game_revision_cstr.to_string_lossy().to_string();
-
-        GAME_REVISION
-            .lock()
-            .unwrap()
-            .replace(game_revision_str.clone());
+    GAME_REVISION
+        .lock()
+        .unwrap()
+        .replace(game_revision_str.clone());
 
-        Some(game_revision_str)
-    }
+    Some(game_revision_str)
 }
 
 /// Get game revision integer.
@@ -1,8 +1,11 @@
 #![allow(dead_code)]
 
-use std::{io::Cursor, slice};
+use std::{slice, str::FromStr};
 
-use super::pattern_scan;
+use address_core::{
+    pattern_scan::{self, Pattern},
+    step::{MemoryReader, ResolveError},
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum MemoryError {
@@ -18,13 +21,16 @@ pub enum MemoryError {
 }
 
 /// 扫描内存，查找匹配的第一个地址
+///
+/// 若特征码中包含引用标记（`^` 或 `&`），返回标记所在位置的地址
 pub fn scan_first(base: usize, size: usize, pattern: &str) -> Result<usize, MemoryError> {
     let memory_slice = unsafe { slice::from_raw_parts(base as *const u8, size) };
 
-    let matches = pattern_scan::scan_first_match(Cursor::new(memory_slice), pattern)
-        .map_err(MemoryError::PatternScan)?;
-    if let Some(matches) = matches {
-        let real_ptr = base + matches;
+    let pattern = Pattern::from_str(pattern)?;
+    let result_offset = pattern.result_offset();
+
+    if let Some(matches) = pattern.find_first(memory_slice) {
+        let real_ptr = base + matches + result_offset;
         return Ok(real_ptr);
     }
 
@@ -32,13 +38,17 @@ pub fn scan_first(base: usize, size: usize, pattern: &str) -> Result<usize, Memo
 }
 
 /// 扫描内存，查找匹配的所有地址
+///
+/// 若特征码中包含引用标记（`^` 或 `&`），返回标记所在位置的地址
 pub fn scan_all(base: usize, size: usize, pattern: &str) -> Result<Vec<usize>, MemoryError> {
     let memory_slice = unsafe { slice::from_raw_parts(base as *const u8, size) };
 
-    let result = pattern_scan::scan(Cursor::new(memory_slice), pattern)
-        .map_err(MemoryError::PatternScan)?
-        .into_iter()
-        .map(|v| v + base)
+    let pattern = Pattern::from_str(pattern)?;
+    let result_offset = pattern.result_offset();
+
+    let result = pattern
+        .find_iter(memory_slice)
+        .map(|v| v + base + result_offset)
         .collect::<Vec<_>>();
 
     if result.is_empty() {
@@ -62,6 +72,22 @@ pub fn auto_scan_all(pattern: &str) -> Result<Vec<usize>, MemoryError> {
     scan_all(base, size, pattern)
 }
 
+/// Reads the memory of the current process, for address resolve steps.
+///
+/// Steps also run on false-positive matches, so reads are guarded: unreadable memory is
+/// reported as an error instead of crashing the game.
+pub struct ProcessMemory;
+
+impl MemoryReader for ProcessMemory {
+    fn read_bytes(&self, addr: usize, buf: &mut [u8]) -> Result<(), ResolveError> {
+        if addr == 0 {
+            return Err(ResolveError::NullPointer(addr));
+        }
+
+        super::windows::read_memory(addr, buf).map_err(|_| ResolveError::Read(addr))
+    }
+}
+
 pub fn space_hex_to_bytes(text_hex: &str) -> Result<Vec<u8>, String> {
     text_hex
         .split_whitespace()
@@ -89,5 +115,9 @@ mod tests {
         let bytes = space_hex_to_bytes("45 33 C0 48 8D 81 08 10 00 00 48 8D 15 B7 FF AA 00 66 44 89 01 48 3B D0 74 0A 44 89 81 04 10 00 00 44 88 00").unwrap();
         let bytes_slice = bytes.as_slice();
         pattern_scan::scan_first_match(Cursor::new(bytes_slice), pattern).unwrap();
+        assert_eq!(
+            pattern_scan::scan_slice_first(bytes_slice, pattern).unwrap(),
+            Some(5)
+        );
     }
 }
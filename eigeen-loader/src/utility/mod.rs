@@ -1,6 +1,5 @@
-mod pattern_scan;
-
 pub mod game;
 pub mod memory;
 pub mod string;
+pub mod version;
 pub mod windows;
@@ -0,0 +1,23 @@
+//! Loader version checks of plugins and core extensions.
+
+use shared::export::{LoaderVersion, LoaderVersionRange};
+
+use crate::error::{Error, Result};
+
+/// Version of this loader.
+pub fn loader_version() -> LoaderVersion {
+    env!("CARGO_PKG_VERSION").parse().unwrap()
+}
+
+/// Check that this loader is in the `required` range.
+pub fn check_version(required: &LoaderVersionRange) -> Result<()> {
+    let current = loader_version();
+    if !required.contains(&current) {
+        return Err(Error::IncompatiblePluginRequiredVersion {
+            required: *required,
+            current,
+        });
+    }
+
+    Ok(())
+}
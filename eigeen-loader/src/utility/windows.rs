@@ -1,10 +1,15 @@
-use std::{cell::RefCell, sync::LazyLock};
+use std::{cell::RefCell, ffi::c_void, sync::LazyLock};
 
 use windows::{
     core::{w, HSTRING, PCWSTR},
     Win32::{
         Foundation::{FALSE, HMODULE, HWND},
         System::{
+            Diagnostics::Debug::ReadProcessMemory,
+            LibraryLoader::{
+                GetModuleHandleExW, GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS,
+                GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+            },
             ProcessStatus::{EnumProcessModules, GetModuleInformation, MODULEINFO},
             Threading::{GetCurrentProcess, GetCurrentProcessId},
         },
@@ -64,6 +69,40 @@ pub unsafe fn get_base_module_space() -> Result<(usize, usize)> {
     Ok((0, 0))
 }
 
+/// Get the module containing `address`, e.g. the plugin of a callback.
+///
+/// The reference count of the module is not incremented.
+pub fn module_from_address(address: *const c_void) -> Option<HMODULE> {
+    let mut hmodule = HMODULE::default();
+    unsafe {
+        GetModuleHandleExW(
+            GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS | GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+            PCWSTR(address as *const u16),
+            &mut hmodule,
+        )
+        .ok()?;
+    }
+
+    Some(hmodule)
+}
+
+/// Read memory of the current process into `buf`.
+///
+/// Fails instead of faulting if any byte of the range is not readable.
+pub fn read_memory(address: usize, buf: &mut [u8]) -> Result<()> {
+    unsafe {
+        ReadProcessMemory(
+            GetCurrentProcess(),
+            address as *const c_void,
+            buf.as_mut_ptr() as *mut c_void,
+            buf.len(),
+            None,
+        )?;
+    }
+
+    Ok(())
+}
+
 /// 显示错误信息对话框
 pub fn message_box_fatal(message: &str) {
     let msg_str: HSTRING = message.into();
@@ -42,9 +42,43 @@ extern "C" EL_API void LoaderVersion(Version *version)
     version->patch = LOADER_VERSION_PATCH;
 }
 
+/// @brief Supported loader version range
+/// @note Optional, takes precedence over LoaderVersion.
+// extern "C" EL_API void LoaderVersionRange(VersionRange *range)
+// {
+//     range->min = {1, 0, 0};
+//     range->max = {2, 0, 0};
+// }
+
+/// @brief Called once the game is initialized, or right after loading if the plugin is loaded later.
+/// @note Optional. Game singletons like sPlayer are available from here.
+/// @return Ok = 0
+extern "C" EL_API int32_t OnGameInitialized()
+{
+    Logger::info("sPlayer found at address: 0x{:X}", Memory::get_singleton("sPlayer"));
+
+    // Functions provided by core extensions, if installed.
+    // Pass a signature, e.g. "fn() -> i32", to check it against the one registered by the extension.
+    auto d3d_initialize = Core::get_function<int32_t()>("d3d_initialize");
+    if (d3d_initialize == nullptr)
+    {
+        Logger::debug("d3d_initialize not provided.");
+    }
+
+    return 0;
+}
+
+/// @brief Other optional lifecycle callbacks
+/// @note All callbacks are called on the game thread. They must not throw.
+/// @note OnQuestEnter, OnQuestLeave and OnUpdate are not supported yet.
+// extern "C" EL_API int32_t OnChatMessage(const uint8_t *msg, size_t len) { return 0; }
+
 /// @brief Uninitialize function
 /// @note Optional, but recommended.
 /// @note You can do nothing here, but if it's not defined, the loader will not unload the plugin.
+/// @note Event subscriptions and lifecycle callbacks are revoked by the loader before it is called,
+/// and none of them is running anymore. Core functions and commands are not tracked, plugins cannot register them.
+/// Remove hooks and anything else the plugin installed in the game here, they are not tracked.
 /// @return Ok = 0
 extern "C" EL_API int32_t Uninitialize()
 {
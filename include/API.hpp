@@ -3,6 +3,7 @@
 #endif
 
 #include <string>
+#include <cstddef>
 #include <cstring>
 #include <vector>
 #include <format>
@@ -14,11 +15,23 @@ namespace elapi {
         void Log(const uint8_t* msg, size_t len, uint8_t level);
 
         int32_t GetAddress(const uint8_t* name, size_t len, uintptr_t* result);
+        int32_t GetAddressPatternIndex(const uint8_t* name, size_t len, size_t* result);
         int32_t PatternScanFirst(const uint8_t* pattern, size_t len, uintptr_t* result);
         int32_t PatternScanAll(const uint8_t* pattern, size_t len, uintptr_t* results, size_t results_cap, size_t* results_count);
         int32_t GetSingleton(const uint8_t* name, size_t len, uintptr_t* result);
 
         void ShowSystemMessage(const uint8_t* msg, size_t len);
+
+        int32_t GetLoadReport(uint8_t* buf, size_t cap, size_t* len);
+        int32_t GetPluginDataDir(const uint8_t* name, size_t len, uint8_t* buf, size_t cap, size_t* result_len);
+        int32_t ListCoreFunctions(uint8_t* buf, size_t cap, size_t* len);
+        int32_t GetCoreFunction(const uint8_t* name, size_t len, const uint8_t* signature, size_t signature_len, uint32_t version, uintptr_t* result);
+        int32_t QueryInterface(const uint8_t* name, size_t len, uint32_t min_version, uintptr_t* result);
+
+        typedef void (*EventCallback)(const uint8_t* name, size_t name_len, const uint8_t* payload, size_t payload_len, void* userdata);
+        int32_t SubscribeEvent(const uint8_t* name, size_t len, EventCallback callback, void* userdata, uint64_t* result);
+        int32_t UnsubscribeEvent(uint64_t id);
+        int32_t PublishEvent(const uint8_t* name, size_t len, const uint8_t* payload, size_t payload_len);
     }
 
 #define EL_API __declspec(dllexport)
@@ -34,6 +47,14 @@ namespace elapi {
         int32_t patch;
     };
 
+    /// @brief Supported loader versions, for the optional `LoaderVersionRange` export.
+    /// @note min is inclusive, max is exclusive. Set max to 0.0.0 for no upper bound.
+    struct VersionRange
+    {
+        Version min;
+        Version max;
+    };
+
     class Logger
     {
     public:
@@ -102,8 +123,24 @@ namespace elapi {
             return result;
         }
 
+        /// @brief Get which pattern of a loader managed address record matched.
+        /// @param name Address name.
+        /// @return 0 for the primary pattern, n for the n-th fallback pattern, or -1 if not found.
+        static int64_t get_address_pattern_index(const std::string& name)
+        {
+            size_t result;
+
+            int32_t status = GetAddressPatternIndex(reinterpret_cast<const uint8_t*>(name.c_str()), name.size(), &result);
+            if (status != 0)
+            {
+                return -1;
+            }
+
+            return static_cast<int64_t>(result);
+        }
+
         /// @brief Scan for the first occurrence of a pattern in memory.
-        /// @param pattern Pattern string. Space seperated hex bytes. E.g. "48 8B 05 ?? ?? ?? ?? 48 8B 40 10". Supported wildcards: `?` `??` `*` `**`
+        /// @param pattern Pattern string. Space seperated hex bytes. E.g. "48 8B 05 ?? ?? ?? ?? 48 8B 40 10". Supported wildcards: `?` `??` `*` `**`, nibbles `4?` `?B`, masks `40[F0]`. Use `^` or `&` to mark the result address.
         /// @return Target address or 0 if not found.
         /// @note Scan range: base ~ base + size of the first module.
         static uintptr_t pattern_scan_first(const std::string& pattern)
@@ -120,7 +157,7 @@ namespace elapi {
         }
 
         /// @brief Scan all occurrences of a pattern in memory.
-        /// @param pattern Pattern string. Space seperated hex bytes. E.g. "48 8B 05 ?? ?? ?? ?? 48 8B 40 10". Supported wildcards: `?` `??` `*` `**`
+        /// @param pattern Pattern string. Space seperated hex bytes. E.g. "48 8B 05 ?? ?? ?? ?? 48 8B 40 10". Supported wildcards: `?` `??` `*` `**`, nibbles `4?` `?B`, masks `40[F0]`. Use `^` or `&` to mark the result address.
         /// @return Target addresses or empty vector if not found.
         /// @note Scan range: base ~ base + size of the first module.
         static std::vector<uintptr_t> pattern_scan_all(const std::string& pattern)
@@ -170,12 +207,258 @@ namespace elapi {
         }
     };
 
+    class Loader {
+    public:
+        /// @brief Get the load report of core extensions and plugins.
+        /// @return JSON string `{"core_extensions": [...], "plugins": [...]}`, each entry with `name`, `version`, `status`, `message` and `duration_ms`. Empty string on failure.
+        /// @note Available at any time, including in `Initialize` and `OnGameInitialized` during the first load.
+        static std::string get_load_report()
+        {
+            std::string report(4096, '\0');
+            size_t len = 0;
+
+            int32_t status = GetLoadReport(reinterpret_cast<uint8_t*>(report.data()), report.size(), &len);
+            if (status == 4)
+            {
+                report.resize(len);
+                status = GetLoadReport(reinterpret_cast<uint8_t*>(report.data()), report.size(), &len);
+            }
+            if (status != 0)
+            {
+                return {};
+            }
+
+            report.resize(len);
+            return report;
+        }
+
+        /// @brief List the functions and interfaces registered by core extensions, with their providers.
+        /// @return JSON `{"functions": [{name, provider, signature, version}], "interfaces": [{name, provider, version}]}`,
+        /// or an empty string on failure.
+        /// @note Duplicate names are handled by `conflict_policy` in the `[core_functions]` section of the loader config:
+        /// reject, first_wins, last_wins (default) or namespaced (`provider::name`).
+        static std::string list_core_functions()
+        {
+            std::string listing(4096, '\0');
+            size_t len = 0;
+
+            int32_t status = ListCoreFunctions(reinterpret_cast<uint8_t*>(listing.data()), listing.size(), &len);
+            if (status == 4)
+            {
+                listing.resize(len);
+                status = ListCoreFunctions(reinterpret_cast<uint8_t*>(listing.data()), listing.size(), &len);
+            }
+            if (status != 0)
+            {
+                return {};
+            }
+
+            listing.resize(len);
+            return listing;
+        }
+
+        /// @brief Get the data directory of a plugin, for its config and saved data.
+        /// @param name Plugin name.
+        /// @return Absolute path of `eigeen_loader/data/<name>/`, created if missing. Empty string on failure.
+        /// @note Stable across plugin updates and layouts, unlike the plugin folder.
+        static std::string get_plugin_data_dir(const std::string& name)
+        {
+            std::string dir(260, '\0');
+            size_t len = 0;
+
+            int32_t status = GetPluginDataDir(reinterpret_cast<const uint8_t*>(name.c_str()), name.size(),
+                reinterpret_cast<uint8_t*>(dir.data()), dir.size(), &len);
+            if (status == 4)
+            {
+                dir.resize(len);
+                status = GetPluginDataDir(reinterpret_cast<const uint8_t*>(name.c_str()), name.size(),
+                    reinterpret_cast<uint8_t*>(dir.data()), dir.size(), &len);
+            }
+            if (status != 0)
+            {
+                return {};
+            }
+
+            dir.resize(len);
+            return dir;
+        }
+    };
+
+    /// @brief Named events between plugins and the loader.
+    /// @note Built-in events: "loader:chat_sent" (UTF-8 message), "loader:after_mh_main_ctor" (mhMain address),
+    /// "loader:singleton_registered" (singleton address, then its UTF-8 name).
+    /// @note The mhMain and singleton events are published before plugins are loaded, and replayed to each new subscriber.
+    class Events {
+    public:
+        /// @brief Subscribe a callback to an event. Removed automatically when the plugin is unloaded.
+        /// @note Replayed events are passed to the callback before this function returns.
+        /// @return Subscription id, or 0 on failure.
+        static uint64_t subscribe(const std::string& name, EventCallback callback, void* userdata = nullptr)
+        {
+            uint64_t id = 0;
+
+            int32_t status = SubscribeEvent(reinterpret_cast<const uint8_t*>(name.c_str()), name.size(), callback, userdata, &id);
+            if (status != 0)
+            {
+                return 0;
+            }
+
+            return id;
+        }
+
+        static bool unsubscribe(uint64_t id)
+        {
+            return UnsubscribeEvent(id) == 0;
+        }
+
+        /// @brief Publish an event. Subscribers are called before this function returns.
+        static void publish(const std::string& name, const void* payload = nullptr, size_t payload_len = 0)
+        {
+            PublishEvent(reinterpret_cast<const uint8_t*>(name.c_str()), name.size(), static_cast<const uint8_t*>(payload), payload_len);
+        }
+    };
+
     typedef void (*AddCoreFunctionPtr)(const char* name, uint32_t len, const void* func);
     typedef const void* (*GetCoreFunctionPtr)(const char* name, uint32_t len);
 
+    /// @brief Signature and interface version of a core function.
+    /// @note Signatures are written as `fn(<args>) -> <ret>`, e.g. `fn(ptr, u32) -> i32`.
+    /// Type tags: void, bool, i8-i64, u8-u64, isize, usize, f32, f64, ptr. Whitespaces are ignored.
+    struct FunctionDescriptor
+    {
+        /// @brief UTF-8 signature, nullptr if unknown.
+        const char* signature;
+        size_t signature_len;
+        /// @brief Interface version. 0 for unversioned functions, or to accept any version.
+        /// @note Unlike interface tables, a requested function version must match exactly:
+        /// a new version may change the behavior of a function behind the same signature.
+        uint32_t version;
+    };
+
+    /// @brief Result codes of the typed core functions.
+    enum class CoreFunctionCode : int32_t
+    {
+        Ok = 0,
+        InvalidUtf8String = 1,
+        NotFound = 2,
+        SignatureMismatch = 3,
+        VersionMismatch = 4,
+        InvalidInterface = 5,
+        /// @brief Another module registered the name, and the conflict policy refused the registration.
+        Conflict = 6,
+        /// @brief The loader is too old to provide the requested function of CoreParam.
+        Unsupported = 7,
+    };
+
+    /// @brief Header of interface tables.
+    /// @note Newer versions of an interface may only append functions.
+    struct InterfaceHeader
+    {
+        /// @brief Size of the whole table in bytes, including the header.
+        uint32_t size;
+        uint32_t version;
+    };
+
+    /// @brief Header of an interface table type.
+    /// @note TInterface must be standard layout, start with an InterfaceHeader `header`,
+    /// and declare `static constexpr const char* NAME` and `static constexpr uint32_t VERSION`.
+    /// @code
+    /// struct Renderer {
+    ///     static constexpr const char* NAME = "d3d:renderer";
+    ///     static constexpr uint32_t VERSION = 1;
+    ///     InterfaceHeader header = interface_header<Renderer>();
+    ///     void (*draw_text)(const uint8_t* text, size_t len, float x, float y);
+    /// };
+    /// @endcode
+    template<typename TInterface>
+    constexpr InterfaceHeader interface_header()
+    {
+        return InterfaceHeader{ static_cast<uint32_t>(sizeof(TInterface)), TInterface::VERSION };
+    }
+
+    typedef int32_t (*AddTypedCoreFunctionPtr)(const char* name, uint32_t len, const FunctionDescriptor* descriptor, const void* func);
+    typedef int32_t (*GetTypedCoreFunctionPtr)(const char* name, uint32_t len, const FunctionDescriptor* descriptor, const void** result);
+    typedef int32_t (*RegisterInterfacePtr)(const char* name, uint32_t len, const InterfaceHeader* table);
+    typedef int32_t (*QueryInterfacePtr)(const char* name, uint32_t len, uint32_t min_version, const InterfaceHeader** result);
+
+    /// @brief Param of the `Initialize` function of core extensions.
+    /// @note Fields are only appended. Check `size` before using a field added after the header, the helpers below do.
     struct CoreParam {
         AddCoreFunctionPtr add_core_function;
         GetCoreFunctionPtr get_core_function;
+        /// @brief Size of the struct filled by the loader, in bytes.
+        /// @note Placed after the two fields of the first layout, which had no header.
+        uint32_t size;
+        /// @brief Layout version of the loader, see VERSION.
+        uint32_t version;
+        AddTypedCoreFunctionPtr add_typed_core_function;
+        GetTypedCoreFunctionPtr get_typed_core_function;
+        RegisterInterfacePtr register_interface;
+        QueryInterfacePtr query_interface;
+
+        static constexpr uint32_t VERSION = 1;
+
+        /// @brief Whether the loader filled the function pointer field at `offset`.
+        bool provides(size_t offset) const {
+            return size >= offset + sizeof(void*);
+        }
+
+        /// @brief Publish an interface table. It must stay valid until the extension is unloaded.
+        template<typename TInterface>
+        CoreFunctionCode add_interface(const TInterface* table) const {
+            if (!provides(offsetof(CoreParam, register_interface)))
+            {
+                return CoreFunctionCode::Unsupported;
+            }
+            return static_cast<CoreFunctionCode>(register_interface(TInterface::NAME, 0, &table->header));
+        }
+
+        /// @brief Get an interface table of at least TInterface::VERSION.
+        /// @return nullptr if not found, or older than TInterface.
+        template<typename TInterface>
+        const TInterface* get_interface() const {
+            if (!provides(offsetof(CoreParam, query_interface)))
+            {
+                return nullptr;
+            }
+            const InterfaceHeader* result = nullptr;
+            if (query_interface(TInterface::NAME, 0, TInterface::VERSION, &result) != 0 || result == nullptr)
+            {
+                return nullptr;
+            }
+            if (result->size < sizeof(TInterface))
+            {
+                return nullptr;
+            }
+            return reinterpret_cast<const TInterface*>(result);
+        }
+
+        /// @brief Get a function, if registered with `signature` and `version`.
+        /// @return nullptr if not found or mismatched, see the logs.
+        template<typename TFunc>
+        TFunc* get_typed_method(std::string_view method, std::string_view signature, uint32_t version = 0) const {
+            if (!provides(offsetof(CoreParam, get_typed_core_function)))
+            {
+                return nullptr;
+            }
+            FunctionDescriptor descriptor{ signature.data(), signature.size(), version };
+            const void* result = nullptr;
+            if (get_typed_core_function(method.data(), 0, &descriptor, &result) != 0)
+            {
+                return nullptr;
+            }
+            return reinterpret_cast<TFunc*>(const_cast<void*>(result));
+        }
+
+        template<typename TFunc>
+        CoreFunctionCode add_typed_method(std::string_view method, TFunc* func, std::string_view signature, uint32_t version) const {
+            if (!provides(offsetof(CoreParam, add_typed_core_function)))
+            {
+                return CoreFunctionCode::Unsupported;
+            }
+            FunctionDescriptor descriptor{ signature.data(), signature.size(), version };
+            return static_cast<CoreFunctionCode>(add_typed_core_function(method.data(), 0, &descriptor, reinterpret_cast<const void*>(func)));
+        }
 
         template<typename TFunc>
         TFunc* get_method(std::string_view method) const {
@@ -187,4 +470,49 @@ namespace elapi {
             return static_cast<TFunc*>(add_core_function(method.data(), 0, static_cast<void*>(func)));
         }
     };
+
+    /// @brief Functions and interfaces provided by core extensions, for plugins.
+    class Core {
+    public:
+        /// @brief Get a function registered by a core extension.
+        /// @param signature Required signature, e.g. "fn(ptr, u32) -> i32", see FunctionDescriptor. Empty accepts any function.
+        /// @param version Required interface version, 0 accepts any version.
+        /// @return nullptr if not found or mismatched.
+        template<typename TFunc>
+        static TFunc* get_function(const std::string& name, const std::string& signature = "", uint32_t version = 0)
+        {
+            uintptr_t result = 0;
+
+            int32_t status = GetCoreFunction(reinterpret_cast<const uint8_t*>(name.c_str()), name.size(),
+                signature.empty() ? nullptr : reinterpret_cast<const uint8_t*>(signature.c_str()), signature.size(), version, &result);
+            if (status != 0)
+            {
+                return nullptr;
+            }
+
+            return reinterpret_cast<TFunc*>(result);
+        }
+
+        /// @brief Get an interface table of at least TInterface::VERSION, see CoreParam::get_interface.
+        /// @return nullptr if not found, or older than TInterface.
+        template<typename TInterface>
+        static const TInterface* get_interface()
+        {
+            uintptr_t result = 0;
+            std::string_view name = TInterface::NAME;
+
+            int32_t status = QueryInterface(reinterpret_cast<const uint8_t*>(name.data()), name.size(), TInterface::VERSION, &result);
+            if (status != 0 || result == 0)
+            {
+                return nullptr;
+            }
+
+            auto header = reinterpret_cast<const InterfaceHeader*>(result);
+            if (header->size < sizeof(TInterface))
+            {
+                return nullptr;
+            }
+            return reinterpret_cast<const TInterface*>(header);
+        }
+    };
 }
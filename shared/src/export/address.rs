@@ -4,6 +4,9 @@ pub enum Code {
     Ok = 0,
     InvalidUtf8String = 1,
     NotFound = 2,
+    BadPattern = 3,
+    /// The result does not fit in the provided buffer, call again with a larger one.
+    BufferTooSmall = 4,
 }
 
 /// Managed address names.
@@ -1,13 +1,46 @@
-use std::ffi::{c_void, CString};
+use std::{
+    ffi::{c_void, CString},
+    mem::offset_of,
+};
 
 /// Core plugin initialize function param.
+///
+/// Fields are only appended. Check [`CoreAPIParam::size`] before using a field added after
+/// the header, the helpers below do.
 #[repr(C)]
 pub struct CoreAPIParam {
     pub add_core_function: *const c_void,
     pub get_core_function: *const c_void,
+    /// Size of the struct filled by the loader, in bytes.
+    ///
+    /// Placed after the two fields of the first layout, which had no header, so extensions
+    /// built against it still work.
+    pub size: u32,
+    /// [`CoreAPIParam::VERSION`] of the loader.
+    pub version: u32,
+    /// [`AddTypedCoreFunctionFn`]
+    pub add_typed_core_function: *const c_void,
+    /// [`GetTypedCoreFunctionFn`]
+    pub get_typed_core_function: *const c_void,
+    /// [`RegisterInterfaceFn`]
+    pub register_interface: *const c_void,
+    /// [`QueryInterfaceFn`]
+    pub query_interface: *const c_void,
 }
 
 impl CoreAPIParam {
+    /// Version of the layout, increased when fields are appended.
+    pub const VERSION: u32 = 1;
+
+    /// Check that the loader filled the function pointer field at `offset`.
+    fn check_field(&self, offset: usize) -> Result<(), CoreFunctionCode> {
+        if (self.size as usize) < offset + std::mem::size_of::<*const c_void>() {
+            return Err(CoreFunctionCode::Unsupported);
+        }
+
+        Ok(())
+    }
+
     pub fn add_method(&self, name: &str, method: *const c_void) {
         let func: AddCoreFunctionFn = unsafe { std::mem::transmute(self.add_core_function) };
 
@@ -32,7 +65,499 @@ impl CoreAPIParam {
             Some(unsafe { std::mem::transmute::<*const c_void, extern "C" fn()>(result) })
         }
     }
+
+    /// Register `function` with its signature and interface `version`.
+    pub fn add_function<F: TypedFunction>(
+        &self,
+        name: &str,
+        version: u32,
+        function: F,
+    ) -> Result<(), CoreFunctionCode> {
+        self.check_field(offset_of!(Self, add_typed_core_function))?;
+        let func: AddTypedCoreFunctionFn =
+            unsafe { std::mem::transmute(self.add_typed_core_function) };
+
+        let c_name = CString::new(name).unwrap();
+        let signature = F::signature();
+        let descriptor = FunctionDescriptor::new(&signature, version);
+
+        let code = func(
+            c_name.as_ptr() as *const u8,
+            c_name.as_bytes().len() as u32,
+            &descriptor,
+            function.as_ptr(),
+        );
+        CoreFunctionCode::result(code)
+    }
+
+    /// Get a function registered with the signature of `F`, and interface `version`.
+    ///
+    /// `version` 0 accepts any version.
+    pub fn get_function<F: TypedFunction>(
+        &self,
+        name: &str,
+        version: u32,
+    ) -> Result<F, CoreFunctionCode> {
+        self.check_field(offset_of!(Self, get_typed_core_function))?;
+        let func: GetTypedCoreFunctionFn =
+            unsafe { std::mem::transmute(self.get_typed_core_function) };
+
+        let c_name = CString::new(name).unwrap();
+        let signature = F::signature();
+        let descriptor = FunctionDescriptor::new(&signature, version);
+
+        let mut result = std::ptr::null();
+        let code = func(
+            c_name.as_ptr() as *const u8,
+            c_name.as_bytes().len() as u32,
+            &descriptor,
+            &mut result,
+        );
+        CoreFunctionCode::result(code)?;
+        if result.is_null() {
+            return Err(CoreFunctionCode::NotFound);
+        }
+
+        Ok(unsafe { F::from_ptr(result) })
+    }
+
+    /// Publish the interface table `table` under [`Interface::NAME`].
+    pub fn register_interface<T: Interface>(
+        &self,
+        table: &'static T,
+    ) -> Result<(), CoreFunctionCode> {
+        self.check_field(offset_of!(Self, register_interface))?;
+        let func: RegisterInterfaceFn = unsafe { std::mem::transmute(self.register_interface) };
+
+        let c_name = CString::new(T::NAME).unwrap();
+        let code = func(
+            c_name.as_ptr() as *const u8,
+            c_name.as_bytes().len() as u32,
+            table.header(),
+        );
+        CoreFunctionCode::result(code)
+    }
+
+    /// Get the interface table named [`Interface::NAME`], of at least [`Interface::VERSION`].
+    pub fn query_interface<T: Interface>(&self) -> Result<&'static T, CoreFunctionCode> {
+        self.check_field(offset_of!(Self, query_interface))?;
+        let func: QueryInterfaceFn = unsafe { std::mem::transmute(self.query_interface) };
+
+        let c_name = CString::new(T::NAME).unwrap();
+        let mut result = std::ptr::null();
+        let code = func(
+            c_name.as_ptr() as *const u8,
+            c_name.as_bytes().len() as u32,
+            T::VERSION,
+            &mut result,
+        );
+        CoreFunctionCode::result(code)?;
+
+        unsafe { InterfaceHeader::cast(result) }
+    }
 }
 
 pub type AddCoreFunctionFn = extern "C" fn(name: *const u8, len: u32, func: *const c_void);
 pub type GetCoreFunctionFn = extern "C" fn(name: *const u8, len: u32) -> *const c_void;
+/// Returns a [`CoreFunctionCode`].
+pub type AddTypedCoreFunctionFn = extern "C" fn(
+    name: *const u8,
+    len: u32,
+    descriptor: &FunctionDescriptor,
+    func: *const c_void,
+) -> i32;
+/// Returns a [`CoreFunctionCode`].
+pub type GetTypedCoreFunctionFn = extern "C" fn(
+    name: *const u8,
+    len: u32,
+    descriptor: &FunctionDescriptor,
+    result: &mut *const c_void,
+) -> i32;
+
+/// Returns a [`CoreFunctionCode`].
+///
+/// The table must stay valid until the module registering it is unloaded.
+pub type RegisterInterfaceFn =
+    extern "C" fn(name: *const u8, len: u32, table: *const InterfaceHeader) -> i32;
+/// Returns a [`CoreFunctionCode`].
+pub type QueryInterfaceFn = extern "C" fn(
+    name: *const u8,
+    len: u32,
+    min_version: u32,
+    result: &mut *const InterfaceHeader,
+) -> i32;
+
+/// Result codes of the typed core function registry.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum CoreFunctionCode {
+    #[error("ok")]
+    Ok = 0,
+    #[error("invalid UTF-8 string")]
+    InvalidUtf8String = 1,
+    #[error("function not found")]
+    NotFound = 2,
+    #[error("function signature mismatch")]
+    SignatureMismatch = 3,
+    #[error("function interface version mismatch")]
+    VersionMismatch = 4,
+    #[error("invalid interface table")]
+    InvalidInterface = 5,
+    /// Another module registered the name, and the conflict policy refused the registration.
+    #[error("name registered by another module")]
+    Conflict = 6,
+    /// The loader is too old to provide the requested function of [`CoreAPIParam`].
+    #[error("not supported by this loader")]
+    Unsupported = 7,
+}
+
+impl CoreFunctionCode {
+    /// Unknown codes are reported as [`CoreFunctionCode::NotFound`].
+    pub fn from_code(code: i32) -> Self {
+        match code {
+            0 => Self::Ok,
+            1 => Self::InvalidUtf8String,
+            3 => Self::SignatureMismatch,
+            4 => Self::VersionMismatch,
+            5 => Self::InvalidInterface,
+            6 => Self::Conflict,
+            7 => Self::Unsupported,
+            _ => Self::NotFound,
+        }
+    }
+
+    fn result(code: i32) -> Result<(), Self> {
+        match Self::from_code(code) {
+            Self::Ok => Ok(()),
+            e => Err(e),
+        }
+    }
+}
+
+/// Signature and interface version of a core function.
+///
+/// Signatures are written as `fn(<args>) -> <ret>` with the type tags of [`FfiType`],
+/// e.g. `fn(ptr, u32) -> i32`. Whitespaces are ignored.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FunctionDescriptor {
+    /// UTF-8 signature, null if unknown.
+    pub signature: *const u8,
+    pub signature_len: usize,
+    /// Interface version. 0 for unversioned functions, or to accept any version.
+    ///
+    /// Unlike interface tables, a requested function version must match exactly: a new
+    /// version may change the behavior of a function behind the same signature.
+    pub version: u32,
+}
+
+impl FunctionDescriptor {
+    pub fn new(signature: &str, version: u32) -> Self {
+        Self {
+            signature: signature.as_ptr(),
+            signature_len: signature.len(),
+            version,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `signature` must point to `signature_len` bytes, or be null.
+    pub unsafe fn signature(&self) -> Option<Result<&str, std::str::Utf8Error>> {
+        if self.signature.is_null() || self.signature_len == 0 {
+            return None;
+        }
+
+        let bytes = std::slice::from_raw_parts(self.signature, self.signature_len);
+        Some(std::str::from_utf8(bytes))
+    }
+}
+
+/// Header of interface tables.
+///
+/// Newer versions of an interface may only append functions, so a table can be used as
+/// any older version of its interface.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceHeader {
+    /// Size of the whole table in bytes, including the header.
+    pub size: u32,
+    pub version: u32,
+}
+
+impl InterfaceHeader {
+    /// Header of a table of interface `T`.
+    pub const fn of<T: Interface>() -> Self {
+        Self {
+            size: std::mem::size_of::<T>() as u32,
+            version: T::VERSION,
+        }
+    }
+
+    /// Cast a queried table to `T`, checking that it is large enough.
+    ///
+    /// # Safety
+    ///
+    /// `table` must be null, or a valid table of interface `T`, of any version.
+    pub unsafe fn cast<T: Interface>(
+        table: *const InterfaceHeader,
+    ) -> Result<&'static T, CoreFunctionCode> {
+        let Some(header) = table.as_ref() else {
+            return Err(CoreFunctionCode::NotFound);
+        };
+        if header.version < T::VERSION || (header.size as usize) < std::mem::size_of::<T>() {
+            return Err(CoreFunctionCode::VersionMismatch);
+        }
+
+        Ok(&*(table as *const T))
+    }
+}
+
+/// `#[repr(C)]` tables of function pointers, shared between modules by name.
+///
+/// ```ignore
+/// #[repr(C)]
+/// struct Renderer {
+///     header: InterfaceHeader,
+///     draw_text: extern "C" fn(*const u8, usize, f32, f32),
+/// }
+///
+/// unsafe impl Interface for Renderer {
+///     const NAME: &'static str = "d3d:renderer";
+///     const VERSION: u32 = 1;
+/// }
+///
+/// static RENDERER: Renderer = Renderer {
+///     header: InterfaceHeader::of::<Renderer>(),
+///     draw_text,
+/// };
+/// ```
+///
+/// # Safety
+///
+/// The type must be `#[repr(C)]`, start with an [`InterfaceHeader`], and only contain
+/// function pointers after it.
+pub unsafe trait Interface: Sync + 'static {
+    const NAME: &'static str;
+    const VERSION: u32;
+
+    fn header(&self) -> *const InterfaceHeader {
+        self as *const Self as *const InterfaceHeader
+    }
+}
+
+/// Types allowed in core function signatures.
+pub trait FfiType {
+    const TAG: &'static str;
+}
+
+macro_rules! impl_ffi_type {
+    ($($ty:ty => $tag:literal),* $(,)?) => {
+        $(impl FfiType for $ty {
+            const TAG: &'static str = $tag;
+        })*
+    };
+}
+
+impl_ffi_type! {
+    () => "void",
+    bool => "bool",
+    i8 => "i8",
+    i16 => "i16",
+    i32 => "i32",
+    i64 => "i64",
+    isize => "isize",
+    u8 => "u8",
+    u16 => "u16",
+    u32 => "u32",
+    u64 => "u64",
+    usize => "usize",
+    f32 => "f32",
+    f64 => "f64",
+}
+
+impl<T> FfiType for *const T {
+    const TAG: &'static str = "ptr";
+}
+
+impl<T> FfiType for *mut T {
+    const TAG: &'static str = "ptr";
+}
+
+/// Function pointers which can be registered as typed core functions.
+///
+/// # Safety
+///
+/// [`TypedFunction::signature`] must describe the function pointer type.
+pub unsafe trait TypedFunction: Copy {
+    fn signature() -> String;
+
+    fn as_ptr(self) -> *const c_void;
+
+    /// # Safety
+    ///
+    /// `ptr` must be a function of this type.
+    unsafe fn from_ptr(ptr: *const c_void) -> Self;
+}
+
+macro_rules! impl_typed_function {
+    ($($arg:ident),*) => {
+        unsafe impl<R: FfiType, $($arg: FfiType),*> TypedFunction for extern "C" fn($($arg),*) -> R {
+            fn signature() -> String {
+                let args: &[&str] = &[$($arg::TAG),*];
+                format!("fn({}) -> {}", args.join(", "), R::TAG)
+            }
+
+            fn as_ptr(self) -> *const c_void {
+                self as *const c_void
+            }
+
+            unsafe fn from_ptr(ptr: *const c_void) -> Self {
+                std::mem::transmute::<*const c_void, Self>(ptr)
+            }
+        }
+    };
+}
+
+impl_typed_function!();
+impl_typed_function!(A);
+impl_typed_function!(A, B);
+impl_typed_function!(A, B, C);
+impl_typed_function!(A, B, C, D);
+impl_typed_function!(A, B, C, D, E);
+impl_typed_function!(A, B, C, D, E, F);
+
+/// Signature with whitespaces removed, for comparison.
+pub fn normalize_signature(signature: &str) -> String {
+    signature.split_whitespace().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_function_signatures() {
+        type Initialize = extern "C" fn() -> i32;
+        type Draw = extern "C" fn(*const u8, u32, f32);
+        type Lookup = extern "C" fn(*mut c_void, usize, bool) -> *const c_void;
+
+        assert_eq!(Initialize::signature(), "fn() -> i32");
+        assert_eq!(Draw::signature(), "fn(ptr, u32, f32) -> void");
+        assert_eq!(Lookup::signature(), "fn(ptr, usize, bool) -> ptr");
+
+        assert_eq!(
+            normalize_signature(" fn( ptr,u32, f32 ) ->void"),
+            normalize_signature(&Draw::signature())
+        );
+    }
+
+    #[test]
+    fn cast_interface_tables() {
+        #[repr(C)]
+        struct RendererV1 {
+            header: InterfaceHeader,
+            draw: extern "C" fn(f32) -> i32,
+        }
+
+        #[repr(C)]
+        struct RendererV2 {
+            header: InterfaceHeader,
+            draw: extern "C" fn(f32) -> i32,
+            clear: extern "C" fn() -> i32,
+        }
+
+        unsafe impl Interface for RendererV1 {
+            const NAME: &'static str = "test:renderer";
+            const VERSION: u32 = 1;
+        }
+
+        unsafe impl Interface for RendererV2 {
+            const NAME: &'static str = "test:renderer";
+            const VERSION: u32 = 2;
+        }
+
+        extern "C" fn draw(_: f32) -> i32 {
+            1
+        }
+
+        extern "C" fn clear() -> i32 {
+            2
+        }
+
+        static V1: RendererV1 = RendererV1 {
+            header: InterfaceHeader::of::<RendererV1>(),
+            draw,
+        };
+        static V2: RendererV2 = RendererV2 {
+            header: InterfaceHeader::of::<RendererV2>(),
+            draw,
+            clear,
+        };
+
+        unsafe {
+            // newer tables can be used as older versions
+            let v1 = InterfaceHeader::cast::<RendererV1>(V2.header()).unwrap();
+            assert_eq!((v1.draw)(0.0), 1);
+            let v2 = InterfaceHeader::cast::<RendererV2>(V2.header()).unwrap();
+            assert_eq!((v2.clear)(), 2);
+
+            assert_eq!(
+                InterfaceHeader::cast::<RendererV2>(V1.header()).err(),
+                Some(CoreFunctionCode::VersionMismatch)
+            );
+            assert_eq!(
+                InterfaceHeader::cast::<RendererV1>(std::ptr::null()).err(),
+                Some(CoreFunctionCode::NotFound)
+            );
+        }
+    }
+
+    #[test]
+    fn check_core_api_param_size() {
+        extern "C" fn add(_: *const u8, _: u32, _: &FunctionDescriptor, _: *const c_void) -> i32 {
+            CoreFunctionCode::Ok as i32
+        }
+
+        type Initialize = extern "C" fn() -> i32;
+        extern "C" fn initialize() -> i32 {
+            0
+        }
+
+        let mut param = CoreAPIParam {
+            add_core_function: std::ptr::null(),
+            get_core_function: std::ptr::null(),
+            size: std::mem::size_of::<CoreAPIParam>() as u32,
+            version: CoreAPIParam::VERSION,
+            add_typed_core_function: add as *const c_void,
+            get_typed_core_function: std::ptr::null(),
+            register_interface: std::ptr::null(),
+            query_interface: std::ptr::null(),
+        };
+        assert_eq!(
+            param.add_function::<Initialize>("init", 1, initialize),
+            Ok(())
+        );
+
+        // a loader providing the fields up to the header only
+        param.size = offset_of!(CoreAPIParam, add_typed_core_function) as u32;
+        assert_eq!(
+            param.add_function::<Initialize>("init", 1, initialize),
+            Err(CoreFunctionCode::Unsupported)
+        );
+        assert_eq!(
+            param.query_interface::<Dummy>().err(),
+            Some(CoreFunctionCode::Unsupported)
+        );
+
+        #[repr(C)]
+        struct Dummy {
+            header: InterfaceHeader,
+        }
+
+        unsafe impl Interface for Dummy {
+            const NAME: &'static str = "test:dummy";
+            const VERSION: u32 = 1;
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseVersionError {
+    #[error("invalid version: {0}")]
+    InvalidVersion(String),
+    #[error("invalid version range: {0}")]
+    InvalidRange(String),
+}
+
+/// Loader version, `major.minor.patch`.
+///
+/// Exported by plugins as `LoaderVersion(&mut LoaderVersion)`, the loader version the plugin
+/// is built for.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LoaderVersion {
+    pub major: i32,
+    pub minor: i32,
+    pub patch: i32,
+}
+
+impl LoaderVersion {
+    pub const fn new(major: i32, minor: i32, patch: i32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Whether a plugin built for `required` can be loaded by this loader version.
+    ///
+    /// Same rule as Cargo's `^required`: the loader must not be older, and must not have
+    /// breaking changes. e.g. a plugin built for 1.3 loads on 1.4, but not on 1.1 or 2.0.
+    pub fn is_compatible_with(&self, required: &LoaderVersion) -> bool {
+        LoaderVersionRange::compatible(*required).contains(self)
+    }
+
+    /// The next version with breaking changes.
+    fn next_breaking(&self) -> Self {
+        match (self.major, self.minor) {
+            (0, 0) => Self::new(0, 0, self.patch + 1),
+            (0, minor) => Self::new(0, minor + 1, 0),
+            (major, _) => Self::new(major + 1, 0, 0),
+        }
+    }
+
+    fn next_patch(&self) -> Self {
+        Self::new(self.major, self.minor, self.patch + 1)
+    }
+}
+
+impl fmt::Display for LoaderVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Parse `major[.minor[.patch]]`, missing parts are 0.
+impl FromStr for LoaderVersion {
+    type Err = ParseVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseVersionError::InvalidVersion(s.to_string());
+
+        let mut parts = [0; 3];
+        for (index, part) in s.trim().split('.').enumerate() {
+            let slot = parts.get_mut(index).ok_or_else(invalid)?;
+            *slot = part.parse::<u16>().map_err(|_| invalid())?.into();
+        }
+
+        Ok(Self::new(parts[0], parts[1], parts[2]))
+    }
+}
+
+/// Range of loader versions a plugin can be loaded by.
+///
+/// Exported by plugins as `LoaderVersionRange(&mut LoaderVersionRange)`, which takes
+/// precedence over `LoaderVersion`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct LoaderVersionRange {
+    /// Minimum version, inclusive.
+    pub min: LoaderVersion,
+    /// Maximum version, exclusive. `0.0.0` for no upper bound.
+    pub max: LoaderVersion,
+}
+
+impl LoaderVersionRange {
+    pub fn new(min: LoaderVersion, max: LoaderVersion) -> Self {
+        Self { min, max }
+    }
+
+    /// Versions compatible with `version`, as `^version`.
+    pub fn compatible(version: LoaderVersion) -> Self {
+        Self::new(version, version.next_breaking())
+    }
+
+    pub fn has_upper_bound(&self) -> bool {
+        self.max != LoaderVersion::default()
+    }
+
+    pub fn contains(&self, version: &LoaderVersion) -> bool {
+        *version >= self.min && (!self.has_upper_bound() || *version < self.max)
+    }
+
+    /// Narrow the range to versions also in `other`.
+    fn intersect(&mut self, other: &Self) {
+        self.min = self.min.max(other.min);
+        if other.has_upper_bound() && (!self.has_upper_bound() || other.max < self.max) {
+            self.max = other.max;
+        }
+    }
+}
+
+impl fmt::Display for LoaderVersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, ">={}", self.min)?;
+        if self.has_upper_bound() {
+            write!(f, ", <{}", self.max)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse comma separated requirements, e.g. `>=1.2, <2`.
+///
+/// Supported operators are `>=`, `>`, `<=`, `<`, `=` and `^`, the default.
+impl FromStr for LoaderVersionRange {
+    type Err = ParseVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseVersionError::InvalidRange(s.to_string());
+
+        let mut range = LoaderVersionRange::default();
+        for requirement in s.split(',') {
+            let requirement = requirement.trim();
+            let operator_len = requirement
+                .find(|c: char| c.is_ascii_digit())
+                .ok_or_else(invalid)?;
+            let (operator, version) = requirement.split_at(operator_len);
+            let version = version.parse::<LoaderVersion>().map_err(|_| invalid())?;
+
+            let requirement = match operator.trim() {
+                ">=" => Self::new(version, LoaderVersion::default()),
+                ">" => Self::new(version.next_patch(), LoaderVersion::default()),
+                "<" => Self::new(LoaderVersion::default(), version),
+                "<=" => Self::new(LoaderVersion::default(), version.next_patch()),
+                "=" => Self::new(version, version.next_patch()),
+                "^" | "" => Self::compatible(version),
+                _ => return Err(invalid()),
+            };
+            range.intersect(&requirement);
+        }
+
+        Ok(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> LoaderVersion {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_version() {
+        assert_eq!(v("1.2.3"), LoaderVersion::new(1, 2, 3));
+        assert_eq!(v("1.2"), LoaderVersion::new(1, 2, 0));
+        assert_eq!(v(" 1 "), LoaderVersion::new(1, 0, 0));
+        assert_eq!(v("1.2.3").to_string(), "1.2.3");
+
+        for invalid in ["", "1.", "1.2.3.4", "a.b", "-1", "1.2.3-rc1"] {
+            assert!(invalid.parse::<LoaderVersion>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn compare_versions() {
+        assert!(v("1.2.3") < v("1.3.0"));
+        assert!(v("1.10.0") > v("1.9.9"));
+        assert!(v("2.0.0") > v("1.99.99"));
+    }
+
+    #[test]
+    fn version_compatibility() {
+        let loader = v("1.3.2");
+
+        assert!(loader.is_compatible_with(&v("1.0.0")));
+        assert!(loader.is_compatible_with(&v("1.3.0")));
+        assert!(loader.is_compatible_with(&v("1.3.2")));
+        assert!(!loader.is_compatible_with(&v("1.3.3")));
+        assert!(!loader.is_compatible_with(&v("1.4.0")));
+        assert!(!loader.is_compatible_with(&v("0.9.0")));
+        assert!(!loader.is_compatible_with(&v("2.0.0")));
+
+        // minor versions are breaking before 1.0
+        assert!(v("0.2.5").is_compatible_with(&v("0.2.1")));
+        assert!(!v("0.3.0").is_compatible_with(&v("0.2.1")));
+    }
+
+    #[test]
+    fn parse_range() {
+        let range = |s: &str| s.parse::<LoaderVersionRange>().unwrap();
+
+        assert_eq!(range("1.2"), LoaderVersionRange::new(v("1.2"), v("2")));
+        assert_eq!(range("^1.2"), range("1.2"));
+        assert_eq!(
+            range(">=1.2, <1.5"),
+            LoaderVersionRange::new(v("1.2"), v("1.5"))
+        );
+        assert_eq!(
+            range("> 1.2.3 , <= 1.4"),
+            LoaderVersionRange::new(v("1.2.4"), v("1.4.1"))
+        );
+        assert_eq!(range("=1.2"), LoaderVersionRange::new(v("1.2"), v("1.2.1")));
+        assert_eq!(
+            range(">=1.2, <3, ^1.3"),
+            LoaderVersionRange::new(v("1.3"), v("2"))
+        );
+        assert_eq!(range(">=1.2").to_string(), ">=1.2.0");
+        assert_eq!(range("1.2").to_string(), ">=1.2.0, <2.0.0");
+
+        for invalid in ["", ">=", "~1.2", ">=1.2,", "1.x"] {
+            assert!(
+                invalid.parse::<LoaderVersionRange>().is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn range_contains() {
+        let range = LoaderVersionRange::new(v("1.2"), v("1.5"));
+        assert!(range.contains(&v("1.2.0")));
+        assert!(range.contains(&v("1.4.9")));
+        assert!(!range.contains(&v("1.1.9")));
+        assert!(!range.contains(&v("1.5.0")));
+
+        // no upper bound
+        let range = LoaderVersionRange::new(v("1.2"), LoaderVersion::default());
+        assert!(range.contains(&v("99.0.0")));
+    }
+}
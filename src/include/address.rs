@@ -1,5 +1,6 @@
 extern "C" {
     fn GetAddress(name: *const u8, len: usize, result: &mut usize) -> i32;
+    fn GetAddressPatternIndex(name: *const u8, len: usize, result: &mut usize) -> i32;
     fn PatternScanFirst(pattern: *const u8, len: usize, result: &mut usize) -> i32;
     fn PatternScanAll(
         pattern: *const u8,
@@ -27,6 +28,21 @@ pub fn get_address(name: AddressName) -> Option<usize> {
     Some(result)
 }
 
+/// Get which pattern of an address record matched.
+///
+/// Returns 0 for the primary pattern, n for the n-th fallback pattern.
+pub fn get_address_pattern_index(name: AddressName) -> Option<usize> {
+    let mut result = 0;
+
+    let code = unsafe { GetAddressPatternIndex(name.as_ptr(), name.len(), &mut result) };
+
+    if code != AddressCode::Ok as i32 {
+        return None;
+    }
+
+    Some(result)
+}
+
 /// Get address record as pointer by name.
 pub fn get_ptr<T>(name: AddressName) -> Option<*mut T> {
     get_address(name).map(|addr| addr as *mut T)
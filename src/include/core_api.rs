@@ -0,0 +1,71 @@
+extern "C" {
+    fn GetCoreFunction(
+        name: *const u8,
+        len: usize,
+        signature: *const u8,
+        signature_len: usize,
+        version: u32,
+        result: &mut usize,
+    ) -> i32;
+    fn QueryInterface(name: *const u8, len: usize, min_version: u32, result: &mut usize) -> i32;
+}
+
+use std::ffi::c_void;
+
+use shared::export::core_extension::{CoreFunctionCode, Interface, InterfaceHeader, TypedFunction};
+
+/// Get a function registered by a core extension, with the signature of `F`.
+///
+/// `version` is the interface version, 0 accepts any version.
+pub fn get_function<F: TypedFunction>(name: &str, version: u32) -> Result<F, CoreFunctionCode> {
+    let signature = F::signature();
+    let function = get(name, Some(&signature), version)?;
+
+    Ok(unsafe { F::from_ptr(function) })
+}
+
+/// Get a function registered by a core extension, without checking its signature.
+pub fn get_untyped_function(name: &str) -> Option<*const c_void> {
+    get(name, None, 0).ok()
+}
+
+/// Get the interface table named [`Interface::NAME`], of at least [`Interface::VERSION`].
+pub fn query_interface<T: Interface>() -> Result<&'static T, CoreFunctionCode> {
+    let mut result = 0;
+
+    let code = unsafe { QueryInterface(T::NAME.as_ptr(), T::NAME.len(), T::VERSION, &mut result) };
+    if code != CoreFunctionCode::Ok as i32 {
+        return Err(CoreFunctionCode::from_code(code));
+    }
+
+    unsafe { InterfaceHeader::cast(result as *const InterfaceHeader) }
+}
+
+fn get(
+    name: &str,
+    signature: Option<&str>,
+    version: u32,
+) -> Result<*const c_void, CoreFunctionCode> {
+    let mut result = 0;
+    let (signature, signature_len) =
+        signature.map_or((std::ptr::null(), 0), |s| (s.as_ptr(), s.len()));
+
+    let code = unsafe {
+        GetCoreFunction(
+            name.as_ptr(),
+            name.len(),
+            signature,
+            signature_len,
+            version,
+            &mut result,
+        )
+    };
+    if code != CoreFunctionCode::Ok as i32 {
+        return Err(CoreFunctionCode::from_code(code));
+    }
+    if result == 0 {
+        return Err(CoreFunctionCode::NotFound);
+    }
+
+    Ok(result as *const c_void)
+}
@@ -0,0 +1,63 @@
+extern "C" {
+    fn SubscribeEvent(
+        name: *const u8,
+        len: usize,
+        callback: EventCallback,
+        userdata: *mut c_void,
+        result: &mut u64,
+    ) -> i32;
+    fn UnsubscribeEvent(id: u64) -> i32;
+    fn PublishEvent(name: *const u8, len: usize, payload: *const u8, payload_len: usize) -> i32;
+}
+
+use std::ffi::c_void;
+
+use shared::export::AddressCode;
+
+/// The UTF-8 chat message sent by the player.
+pub const CHAT_SENT: &str = "loader:chat_sent";
+/// The mhMain address, `usize` little endian.
+pub const AFTER_MH_MAIN_CTOR: &str = "loader:after_mh_main_ctor";
+/// The singleton address, `usize` little endian, then its UTF-8 name.
+pub const SINGLETON_REGISTERED: &str = "loader:singleton_registered";
+
+/// Event callback, `fn(name, name_len, payload, payload_len, userdata)`.
+pub type EventCallback =
+    unsafe extern "C-unwind" fn(*const u8, usize, *const u8, usize, *mut c_void);
+
+/// Subscribe `callback` to the event `name`.
+///
+/// The callback is called from the publishing thread. The subscription is removed
+/// automatically when the plugin is unloaded.
+///
+/// [`AFTER_MH_MAIN_CTOR`] and [`SINGLETON_REGISTERED`] are published before plugins are
+/// loaded, and replayed to `callback` before this function returns.
+///
+/// Returns the subscription id, for [`unsubscribe`].
+///
+/// # Safety
+///
+/// `userdata` is passed to `callback` as is, and must stay valid as long as the subscription
+/// exists.
+pub unsafe fn subscribe(name: &str, callback: EventCallback, userdata: *mut c_void) -> Option<u64> {
+    let mut id = 0;
+
+    let code = SubscribeEvent(name.as_ptr(), name.len(), callback, userdata, &mut id);
+    if code != AddressCode::Ok as i32 {
+        return None;
+    }
+
+    Some(id)
+}
+
+/// Remove a subscription, returns `false` if it does not exist.
+pub fn unsubscribe(id: u64) -> bool {
+    unsafe { UnsubscribeEvent(id) == AddressCode::Ok as i32 }
+}
+
+/// Publish the event `name` with a byte payload. Subscribers are called before it returns.
+pub fn publish(name: &str, payload: &[u8]) -> bool {
+    let code = unsafe { PublishEvent(name.as_ptr(), name.len(), payload.as_ptr(), payload.len()) };
+
+    code == AddressCode::Ok as i32
+}
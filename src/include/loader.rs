@@ -0,0 +1,72 @@
+extern "C" {
+    fn GetLoadReport(buf: *mut u8, cap: usize, len: &mut usize) -> i32;
+    fn GetPluginDataDir(
+        name: *const u8,
+        len: usize,
+        buf: *mut u8,
+        cap: usize,
+        result_len: &mut usize,
+    ) -> i32;
+}
+
+use std::path::PathBuf;
+
+use shared::export::AddressCode;
+
+/// Get the load report of core extensions and plugins.
+///
+/// JSON string `{"core_extensions": [...], "plugins": [...]}`, each entry with `name`,
+/// `version`, `status`, `message` and `duration_ms`.
+pub fn get_load_report() -> Option<String> {
+    let mut buf = vec![0; 4096];
+    let mut len = 0;
+
+    let mut code = unsafe { GetLoadReport(buf.as_mut_ptr(), buf.len(), &mut len) };
+    if code == AddressCode::BufferTooSmall as i32 {
+        buf.resize(len, 0);
+        code = unsafe { GetLoadReport(buf.as_mut_ptr(), buf.len(), &mut len) };
+    }
+    if code != AddressCode::Ok as i32 {
+        return None;
+    }
+
+    buf.truncate(len);
+    String::from_utf8(buf).ok()
+}
+
+/// Get the data directory of the plugin `name`, for its config and saved data.
+///
+/// Absolute path of `eigeen_loader/data/<name>/`, created if missing. Stable across plugin
+/// updates and layouts, unlike the plugin folder.
+pub fn get_plugin_data_dir(name: &str) -> Option<PathBuf> {
+    let mut buf = vec![0; 260];
+    let mut len = 0;
+
+    let mut code = unsafe {
+        GetPluginDataDir(
+            name.as_ptr(),
+            name.len(),
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut len,
+        )
+    };
+    if code == AddressCode::BufferTooSmall as i32 {
+        buf.resize(len, 0);
+        code = unsafe {
+            GetPluginDataDir(
+                name.as_ptr(),
+                name.len(),
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut len,
+            )
+        };
+    }
+    if code != AddressCode::Ok as i32 {
+        return None;
+    }
+
+    buf.truncate(len);
+    String::from_utf8(buf).ok().map(PathBuf::from)
+}
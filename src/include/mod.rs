@@ -1,3 +1,6 @@
 pub mod address;
+pub mod core_api;
+pub mod event;
 pub mod game;
+pub mod loader;
 pub mod logging;
@@ -8,13 +8,7 @@ pub use shared::game::*;
 
 /// Get the version of the EigeenLoader library.
 pub fn get_version() -> LoaderVersion {
-    let mut splitted = env!("CARGO_PKG_VERSION").split(".");
-
-    LoaderVersion {
-        major: splitted.next().unwrap().parse().unwrap(),
-        minor: splitted.next().unwrap().parse().unwrap(),
-        patch: splitted.next().unwrap().parse().unwrap(),
-    }
+    env!("CARGO_PKG_VERSION").parse().unwrap()
 }
 
 pub mod prelude {
@@ -23,5 +17,8 @@ pub mod prelude {
     pub use shared::game::mt_type::*;
 
     pub use crate::include::address as el_address;
+    pub use crate::include::core_api as el_core;
+    pub use crate::include::event as el_event;
     pub use crate::include::game as el_game;
+    pub use crate::include::loader as el_loader;
 }